@@ -15,10 +15,36 @@
  */
 
 
-use acton_ern::{Ern, ErnParser};
+use std::any::TypeId;
+use std::time::Duration;
 
-use crate::common::{BrokerRef, ParentRef};
-use crate::traits::Actor;
+use acton_ern::Ern;
+use tracing::Level;
+
+use crate::actor::{RateLimitPolicy, RateLimiter, SupervisionStrategy, TerminatePolicy};
+use crate::common::{BrokerRef, Interceptor, ParentRef, TypeMap};
+use crate::message::{Envelope, InterceptDecision};
+use crate::traits::{ActonMessage, Actor};
+
+/// The number of envelopes an agent's mailbox holds when no capacity is configured.
+pub(crate) const DEFAULT_MAILBOX_CAPACITY: usize = 255;
+
+/// How long an agent's `before_stop` hook may run when no shutdown timeout is configured.
+pub(crate) const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The level of an agent's spans when no trace level is configured.
+pub(crate) const DEFAULT_TRACE_LEVEL: Level = Level::DEBUG;
+
+/// How many events a persistent agent records between snapshots when no interval is configured.
+pub(crate) const DEFAULT_SNAPSHOT_INTERVAL: u64 = 100;
+
+/// How many messages an agent's output stream keeps for slow listeners when no capacity is
+/// configured.
+pub(crate) const DEFAULT_OUTPUT_CAPACITY: usize = 64;
+
+/// How many regular messages an agent may handle ahead of a waiting system signal when no
+/// interval is configured.
+pub(crate) const DEFAULT_SIGNAL_CHECK_INTERVAL: usize = 32;
 
 /// Configuration for creating an actor.
 ///
@@ -29,6 +55,30 @@ pub struct AgentConfig {
     ern: Ern,
     pub(crate) broker: Option<BrokerRef>,
     parent: Option<ParentRef>,
+    mailbox_capacity: Option<usize>,
+    unbounded_mailbox: bool,
+    priority_mailbox: bool,
+    supervision: SupervisionStrategy,
+    panic_recovery: bool,
+    strict_handlers: bool,
+    shutdown_timeout: Option<Duration>,
+    metrics: bool,
+    snapshot_interval: Option<u64>,
+    dedup_window: Option<usize>,
+    idle_timeout: Option<Duration>,
+    signal_check_interval: Option<usize>,
+    max_children: Option<usize>,
+    dependencies: Vec<Ern>,
+    output_capacity: Option<usize>,
+    interceptors: Vec<Interceptor>,
+    rate_limit: Option<(u32, u32)>,
+    rate_limit_policy: RateLimitPolicy,
+    parallelism: Option<usize>,
+    terminate_policy: TerminatePolicy,
+    trace_level: Option<Level>,
+    message_weights: Vec<(TypeId, u32)>,
+    #[cfg(feature = "testing")]
+    recording: bool,
 }
 
 impl AgentConfig {
@@ -49,19 +99,68 @@ impl AgentConfig {
         broker: Option<BrokerRef>,
     ) -> anyhow::Result<AgentConfig> {
         if let Some(parent) = parent {
-            // Get the parent ERN
-            let parent_ern = ErnParser::new(parent.id().to_string()).parse()?;
-            let child_ern = parent_ern + ern;
+            // Get the parent ERN; re-parsing its string form would mint a new root id
+            let parent_ern = parent.id();
+            // The child's root becomes a part under its parent, so every child gets its own ERN
+            let child_ern = parent_ern.add_part(ern.root.to_string())? + ern;
             Ok(AgentConfig {
                 ern: child_ern,
                 broker,
                 parent: Some(parent),
+                mailbox_capacity: None,
+                unbounded_mailbox: false,
+                priority_mailbox: false,
+                supervision: SupervisionStrategy::default(),
+                panic_recovery: false,
+                strict_handlers: false,
+                shutdown_timeout: None,
+                metrics: false,
+                snapshot_interval: None,
+                dedup_window: None,
+                idle_timeout: None,
+                signal_check_interval: None,
+                max_children: None,
+                dependencies: Vec::new(),
+                output_capacity: None,
+                interceptors: Vec::new(),
+                rate_limit: None,
+                rate_limit_policy: RateLimitPolicy::default(),
+                parallelism: None,
+                terminate_policy: TerminatePolicy::default(),
+                trace_level: None,
+                message_weights: Vec::new(),
+                #[cfg(feature = "testing")]
+                recording: false,
             })
         } else {
             Ok(AgentConfig {
                 ern,
                 broker,
                 parent,
+                mailbox_capacity: None,
+                unbounded_mailbox: false,
+                priority_mailbox: false,
+                supervision: SupervisionStrategy::default(),
+                panic_recovery: false,
+                strict_handlers: false,
+                shutdown_timeout: None,
+                metrics: false,
+                snapshot_interval: None,
+                dedup_window: None,
+                idle_timeout: None,
+                signal_check_interval: None,
+                max_children: None,
+                dependencies: Vec::new(),
+                output_capacity: None,
+                interceptors: Vec::new(),
+                rate_limit: None,
+                rate_limit_policy: RateLimitPolicy::default(),
+                parallelism: None,
+                terminate_policy: TerminatePolicy::default(),
+                trace_level: None,
+                message_weights: Vec::new(),
+                #[cfg(feature = "testing")]
+                recording: false,
             })
         }
     }
@@ -73,6 +172,252 @@ impl AgentConfig {
         Self::new(Ern::with_root(name.into())?, None, None)
     }
 
+    /// Sets how many envelopes the agent's mailbox can hold before senders wait.
+    ///
+    /// Defaults to 255 when not set.
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        self.mailbox_capacity = Some(capacity);
+        self
+    }
+
+    /// Gives the agent a mailbox with no capacity limit, so senders never wait.
+    ///
+    /// Suits agents that take in bursts of messages and would rather use more memory than
+    /// hold up their producers. Takes precedence over `with_mailbox_capacity`.
+    pub fn with_unbounded_mailbox(mut self) -> Self {
+        self.unbounded_mailbox = true;
+        self
+    }
+
+    /// Gives the agent a second, high-priority mailbox for messages sent with
+    /// `AgentHandle::send_priority`.
+    ///
+    /// Whenever both have messages waiting, the agent handles the high-priority ones first.
+    /// The second mailbox has the same capacity as the first.
+    pub fn with_priority_mailbox(mut self) -> Self {
+        self.priority_mailbox = true;
+        self
+    }
+
+    /// Sets what happens to the agent when one of its handlers panics.
+    ///
+    /// Defaults to [`SupervisionStrategy::Stop`].
+    pub fn with_supervision(mut self, strategy: SupervisionStrategy) -> Self {
+        self.supervision = strategy;
+        self
+    }
+
+    /// When enabled, a panicking handler is logged and reported to the agent's `on_error`
+    /// hook, and the agent carries on with its next message.
+    ///
+    /// Off by default, so panics stay fail-fast.
+    pub fn with_panic_recovery(mut self, enabled: bool) -> Self {
+        self.panic_recovery = enabled;
+        self
+    }
+
+    /// When enabled, adding a second handler for a message type that already has one is a
+    /// bug: it panics in debug builds and is logged as an error in release builds.
+    ///
+    /// Off by default, when the new handler replaces the old one with a warning.
+    pub fn with_strict_handlers(mut self, enabled: bool) -> Self {
+        self.strict_handlers = enabled;
+        self
+    }
+
+    /// Sets how long the agent's `before_stop` hook may run before the agent stops without it.
+    ///
+    /// Defaults to 5 seconds when not set.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Records how many messages the agent handles and how long its handlers take, read
+    /// with `AgentHandle::metrics`.
+    ///
+    /// Off by default.
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = true;
+        self
+    }
+
+    /// Sets how many events a persistent agent records between snapshots, once it has a
+    /// snapshot store from `ManagedAgent::with_snapshots`.
+    ///
+    /// Defaults to 100 when not set. Zero is treated as one.
+    pub fn with_snapshot_interval(mut self, events: u64) -> Self {
+        self.snapshot_interval = Some(events.max(1));
+        self
+    }
+
+    /// Makes the agent remember the delivery ids of the last `window` messages it handled,
+    /// and skip any message sent with `AgentHandle::send_reliable` whose id it has already
+    /// seen. Skipped messages are acknowledged, so their sender stops redelivering them.
+    ///
+    /// This only catches duplicates that arrive within the window. The ids are kept in
+    /// memory, so they are lost when the agent stops or restarts; handlers that must stay
+    /// idempotent beyond that should record what they processed with persistence.
+    /// A window of zero is treated as one.
+    pub fn with_dedup(mut self, window: usize) -> Self {
+        self.dedup_window = Some(window.max(1));
+        self
+    }
+
+    /// Stops the agent once it has gone `timeout` without a message, for on-demand agents
+    /// such as one per session or connection.
+    ///
+    /// Every message the agent takes restarts the wait. When it runs out, the agent stops as
+    /// if it had been sent `stop`, handling anything that arrived meanwhile first.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how many regular messages the agent may handle ahead of a waiting system signal,
+    /// such as the `Terminate` sent by `stop` or the `Suspend` sent by `suspend`.
+    ///
+    /// Signals travel apart from regular messages. A signal is handled in turn after the
+    /// messages sent before it, unless that would mean waiting behind more than this many of
+    /// them, so a flood of queued messages can't hold up stopping the agent. Zero handles
+    /// signals as soon as they arrive. Defaults to 32 when not set.
+    pub fn with_signal_check_interval(mut self, messages: usize) -> Self {
+        self.signal_check_interval = Some(messages);
+        self
+    }
+
+    /// Limits how many children the agent may supervise at once.
+    ///
+    /// Once the agent has `limit` children, `supervise` and `create_child` fail with
+    /// `MessageError::ChildLimitReached` until one of them stops. Without a limit, an agent
+    /// that starts a child per request can be made to start any number of them.
+    pub fn with_max_children(mut self, limit: usize) -> Self {
+        self.max_children = Some(limit);
+        self
+    }
+
+    /// Declares that the agent depends on the agent with the ERN `dependency`, so
+    /// `AgentRuntime::shutdown_ordered` stops this agent first. Call it again for each
+    /// further dependency.
+    pub fn depends_on(mut self, dependency: Ern) -> Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    /// Sets how many messages the agent's output stream, from `AgentHandle::broadcast_handle`,
+    /// keeps for listeners that fall behind. Defaults to 64 when not set.
+    pub fn with_output_capacity(mut self, capacity: usize) -> Self {
+        self.output_capacity = Some(capacity);
+        self
+    }
+
+    /// Adds an interceptor that sees every message the agent receives before its handler
+    /// does, for concerns such as authorization, logging or rate limiting.
+    ///
+    /// Interceptors run in the order they were added, stopping at the first that doesn't
+    /// return `InterceptDecision::Continue`. A dropped message is discarded; a rejected one
+    /// goes to the dead-letter agent with `DeadLetterReason::Rejected`. System signals, such
+    /// as the one `stop` sends, aren't intercepted.
+    pub fn with_interceptor(
+        mut self,
+        interceptor: impl Fn(&Envelope) -> InterceptDecision + Send + Sync + 'static,
+    ) -> Self {
+        self.interceptors.push(Interceptor::new(move |envelope, _| interceptor(envelope)));
+        self
+    }
+
+    /// Adds an interceptor, like [`AgentConfig::with_interceptor`], that can also leave data
+    /// in the agent's context data for its handlers to read, such as the caller's identity
+    /// or trace context.
+    pub fn with_context_interceptor(
+        mut self,
+        interceptor: impl Fn(&Envelope, &mut TypeMap) -> InterceptDecision + Send + Sync + 'static,
+    ) -> Self {
+        self.interceptors.push(Interceptor::new(interceptor));
+        self
+    }
+
+    /// Limits the agent to handling `permits_per_sec` messages a second on average, with
+    /// bursts of up to `burst` at once after a quiet spell.
+    ///
+    /// Messages over the limit wait their turn, unless `with_rate_limit_policy` says
+    /// otherwise. System signals aren't limited.
+    pub fn with_rate_limit(mut self, permits_per_sec: u32, burst: u32) -> Self {
+        self.rate_limit = Some((permits_per_sec, burst));
+        self
+    }
+
+    /// Sets what happens to messages over the rate set with `with_rate_limit`. Defaults to
+    /// `RateLimitPolicy::Delay`.
+    pub fn with_rate_limit_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.rate_limit_policy = policy;
+        self
+    }
+
+    /// Lets the agent run up to `handlers` of its message handlers at once, instead of one
+    /// after another.
+    ///
+    /// This gives up the guarantee that handlers see the model one message at a time. A
+    /// handler still gets the agent to itself while it is called, but the futures handlers
+    /// return run side by side, on the agent's task tracker, and finish in any order. Only
+    /// use it for stateless agents, such as request handlers whose futures don't depend on
+    /// one another. Handlers that can fail, or that wait with `receive`, still run one at a
+    /// time. A panic in a concurrent handler is logged, and `on_error` isn't called for it.
+    /// Zero is treated as one.
+    pub fn with_parallelism(mut self, handlers: usize) -> Self {
+        self.parallelism = Some(handlers.max(1));
+        self
+    }
+
+    /// Sets what the agent does with the messages still waiting in its mailboxes when it is
+    /// told to stop: handle them first, the default, or drop them and stop right away.
+    ///
+    /// Dropping them lets `stop` return promptly for agents whose queued work is worthless
+    /// once they are shutting down, such as caches or request handlers whose callers have
+    /// gone.
+    pub fn with_terminate_policy(mut self, policy: TerminatePolicy) -> Self {
+        self.terminate_policy = policy;
+        self
+    }
+
+    /// Sets the level of the spans the agent opens for its run and for each message it
+    /// handles, `DEBUG` by default.
+    ///
+    /// Quiet a busy agent with `Level::TRACE` so its spans only show up when tracing is
+    /// turned all the way up, or raise an important one to `INFO`. The level is chosen when
+    /// the span is opened, so no `tracing` features are needed. Warnings and errors the
+    /// agent logs keep their own levels.
+    pub fn with_trace_level(mut self, level: Level) -> Self {
+        self.trace_level = Some(level);
+        self
+    }
+
+    /// Gives messages of type `M` a share of the agent's attention in proportion to `weight`,
+    /// so a flood of other messages can't keep them waiting for long.
+    ///
+    /// Once any type has a weight, the agent takes messages from its mailbox into a queue
+    /// per type and picks the next one by weight rather than in arrival order: with `M`
+    /// weighted 10 and everything else at the default of 1, a waiting `M` is handled ahead
+    /// of ten other messages. Messages of the same type are still handled in the order
+    /// they arrived, but the order across types is given up. This differs from
+    /// `with_priority_mailbox`, which always takes priority messages first; every weighted
+    /// type still gets its turn. Zero is treated as one.
+    pub fn with_message_weight<M: ActonMessage + 'static>(mut self, weight: u32) -> Self {
+        let type_id = TypeId::of::<M>();
+        self.message_weights.retain(|(weighted, _)| *weighted != type_id);
+        self.message_weights.push((type_id, weight.max(1)));
+        self
+    }
+
+    /// Records every message the agent sends, for tests to read back with
+    /// `AgentHandle::recorded`. Messages still go to their recipients as usual.
+    ///
+    /// Only available with the `testing` feature.
+    #[cfg(feature = "testing")]
+    pub fn with_recording(mut self) -> Self {
+        self.recording = true;
+        self
+    }
 
     /// Returns the ERN of the actor.
     pub(crate) fn ern(&self) -> Ern {
@@ -88,4 +433,116 @@ impl AgentConfig {
     pub(crate) fn parent(&self) -> &Option<ParentRef> {
         &self.parent
     }
+
+    /// Returns the configured mailbox capacity, if any.
+    pub(crate) fn mailbox_capacity(&self) -> Option<usize> {
+        self.mailbox_capacity
+    }
+
+    /// Returns whether the mailbox is unbounded.
+    pub(crate) fn unbounded_mailbox(&self) -> bool {
+        self.unbounded_mailbox
+    }
+
+    /// Returns whether the agent has a high-priority mailbox.
+    pub(crate) fn priority_mailbox(&self) -> bool {
+        self.priority_mailbox
+    }
+
+    /// Returns the supervision strategy.
+    pub(crate) fn supervision(&self) -> SupervisionStrategy {
+        self.supervision
+    }
+
+    /// Returns whether handler panics are recovered from.
+    pub(crate) fn panic_recovery(&self) -> bool {
+        self.panic_recovery
+    }
+
+    /// Returns whether adding a second handler for a message type is treated as a bug.
+    pub(crate) fn strict_handlers(&self) -> bool {
+        self.strict_handlers
+    }
+
+    /// Returns the configured shutdown timeout, if any.
+    pub(crate) fn shutdown_timeout(&self) -> Option<Duration> {
+        self.shutdown_timeout
+    }
+
+    /// Returns whether metrics are recorded.
+    pub(crate) fn metrics(&self) -> bool {
+        self.metrics
+    }
+
+    /// Returns the configured snapshot interval, if any.
+    pub(crate) fn snapshot_interval(&self) -> Option<u64> {
+        self.snapshot_interval
+    }
+
+    /// Returns the configured dedup window, if any.
+    pub(crate) fn dedup_window(&self) -> Option<usize> {
+        self.dedup_window
+    }
+
+    /// Returns the configured idle timeout, if any.
+    pub(crate) fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Returns the configured signal check interval, if any.
+    pub(crate) fn signal_check_interval(&self) -> Option<usize> {
+        self.signal_check_interval
+    }
+
+    /// Returns the configured child limit, if any.
+    pub(crate) fn max_children(&self) -> Option<usize> {
+        self.max_children
+    }
+
+    /// Returns the ERNs of the agents this agent depends on.
+    pub(crate) fn dependencies(&self) -> &[Ern] {
+        &self.dependencies
+    }
+
+    /// Returns the configured output stream capacity, if any.
+    pub(crate) fn output_capacity(&self) -> Option<usize> {
+        self.output_capacity
+    }
+
+    /// Returns the interceptors, in the order they run.
+    pub(crate) fn interceptors(&self) -> &[Interceptor] {
+        &self.interceptors
+    }
+
+    /// Returns a rate limiter for the configured rate, if any.
+    pub(crate) fn rate_limiter(&self) -> Option<RateLimiter> {
+        self.rate_limit
+            .map(|(permits_per_sec, burst)| RateLimiter::new(permits_per_sec, burst, self.rate_limit_policy))
+    }
+
+    /// Returns how many handlers the agent may run at once, if set.
+    pub(crate) fn parallelism(&self) -> Option<usize> {
+        self.parallelism
+    }
+
+    /// Returns the weights given to message types, empty unless some were.
+    pub(crate) fn message_weights(&self) -> &[(TypeId, u32)] {
+        &self.message_weights
+    }
+
+    /// Returns what the agent does with waiting messages when told to stop.
+    pub(crate) fn terminate_policy(&self) -> TerminatePolicy {
+        self.terminate_policy
+    }
+
+    /// Returns the level of the agent's run and per-message spans.
+    pub(crate) fn trace_level(&self) -> Level {
+        self.trace_level.unwrap_or(DEFAULT_TRACE_LEVEL)
+    }
+
+    /// Returns whether the messages the agent sends are recorded.
+    #[cfg(feature = "testing")]
+    pub(crate) fn recording(&self) -> bool {
+        self.recording
+    }
 }
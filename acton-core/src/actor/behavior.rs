@@ -0,0 +1,94 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::any::TypeId;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+use crate::actor::managed_agent::{fallible_reactor, message_reactor};
+use crate::actor::{ManagedAgent, Started};
+use crate::common::{FallibleFutureBox, FutureBox, ReactorMap};
+use crate::message::MessageContext;
+use crate::traits::ActonMessage;
+
+/// A set of message handlers a started agent can switch to with
+/// [`ManagedAgent::become_behavior`].
+///
+/// Build it with `act_on`, the same way handlers are added to an idle agent.
+pub struct Behavior<State: Default + Send + Debug + 'static> {
+    pub(crate) reactors: Arc<ReactorMap<State>>,
+}
+
+impl<State: Default + Send + Debug + 'static> Behavior<State> {
+    /// Creates a behavior with no handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an asynchronous message handler for a specific message type.
+    ///
+    /// # Parameters
+    /// - `message_processor`: The function to handle the message.
+    pub fn act_on<M>(
+        &mut self,
+        message_processor: impl for<'a> Fn(
+            &'a mut ManagedAgent<Started, State>,
+            &'a mut MessageContext<M>,
+        ) -> FutureBox
+        + Send
+        + Sync
+        + 'static,
+    ) -> &mut Self
+    where
+        M: ActonMessage + Clone + Send + Sync + 'static,
+    {
+        self.reactors.insert(TypeId::of::<M>(), message_reactor(message_processor));
+        self
+    }
+
+    /// Adds an asynchronous message handler that can fail; see `ManagedAgent::act_on_fallible`.
+    pub fn act_on_fallible<M>(
+        &mut self,
+        message_processor: impl for<'a> Fn(
+            &'a mut ManagedAgent<Started, State>,
+            &'a mut MessageContext<M>,
+        ) -> FallibleFutureBox
+        + Send
+        + Sync
+        + 'static,
+    ) -> &mut Self
+    where
+        M: ActonMessage + Clone + Send + Sync + 'static,
+    {
+        self.reactors.insert(TypeId::of::<M>(), fallible_reactor(message_processor));
+        self
+    }
+}
+
+impl<State: Default + Send + Debug + 'static> Default for Behavior<State> {
+    fn default() -> Self {
+        Behavior { reactors: Default::default() }
+    }
+}
+
+impl<State: Default + Send + Debug + 'static> Debug for Behavior<State> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Behavior")
+            .field("handlers", &self.reactors.len())
+            .finish()
+    }
+}
@@ -0,0 +1,54 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::collections::{HashSet, VecDeque};
+
+use uuid::Uuid;
+
+/// The delivery ids of the last messages an agent handled, oldest first.
+///
+/// Once full, recording a new id forgets the oldest one.
+#[derive(Debug, Default)]
+pub(crate) struct DedupWindow {
+    capacity: usize,
+    order: VecDeque<Uuid>,
+    seen: HashSet<Uuid>,
+}
+
+impl DedupWindow {
+    pub(crate) fn new(capacity: usize) -> Self {
+        DedupWindow {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Records `id`, returning `false` if it is already in the window.
+    pub(crate) fn record(&mut self, id: Uuid) -> bool {
+        if self.seen.contains(&id) {
+            return false;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(id);
+        self.seen.insert(id);
+        true
+    }
+}
@@ -0,0 +1,35 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+/// What a supervising agent does when a child escalates a failure to it; returned from the
+/// hook set with `ManagedAgent::on_child_failure`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscalationAction {
+    /// The child stays stopped and the supervisor carries on. This is what happens when the
+    /// supervisor has no hook.
+    #[default]
+    Ignore,
+    /// The supervisor fails as if one of its handlers had panicked: under its own
+    /// `SupervisionStrategy::Restart`, its parent restarts it, counted against the same
+    /// budget. A supervisor that doesn't restart, or is past its budget, stops and escalates
+    /// to its own parent instead.
+    Restart,
+    /// The supervisor stops, along with its other children.
+    Stop,
+    /// The supervisor stops, along with its other children, and passes the failure on to
+    /// its own parent.
+    Escalate,
+}
@@ -0,0 +1,96 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::any::TypeId;
+use std::collections::{HashMap, VecDeque};
+
+use crate::common::Envelope;
+
+/// The weight of a message type that wasn't given one with `AgentConfig::with_message_weight`.
+pub(crate) const DEFAULT_MESSAGE_WEIGHT: u32 = 1;
+
+/// Messages taken from an agent's mailbox, queued by type and handed out in proportion to
+/// each type's weight, so a flood of one type can't hold up the others for long.
+///
+/// Each type keeps its own order. Types take turns by smooth weighted round robin: every
+/// pick, each waiting type earns its weight in credit, and the type with the most credit
+/// goes next and pays back the total weight of the waiting types.
+#[derive(Debug)]
+pub(crate) struct FairQueue {
+    weights: HashMap<TypeId, u32>,
+    queues: HashMap<TypeId, TypeQueue>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct TypeQueue {
+    envelopes: VecDeque<Envelope>,
+    weight: i64,
+    credit: i64,
+}
+
+impl FairQueue {
+    pub(crate) fn new(weights: &[(TypeId, u32)]) -> Self {
+        FairQueue { weights: weights.iter().copied().collect(), queues: HashMap::new(), len: 0 }
+    }
+
+    /// Queues an envelope carrying a message of type `type_id` behind the others of its type.
+    pub(crate) fn push(&mut self, type_id: TypeId, envelope: Envelope) {
+        let weight = self.weights.get(&type_id).copied().unwrap_or(DEFAULT_MESSAGE_WEIGHT);
+        self.queues
+            .entry(type_id)
+            .or_insert_with(|| TypeQueue { envelopes: VecDeque::new(), weight: i64::from(weight), credit: 0 })
+            .envelopes
+            .push_back(envelope);
+        self.len += 1;
+    }
+
+    /// Takes the next envelope from the type whose turn it is.
+    pub(crate) fn pop(&mut self) -> Option<Envelope> {
+        let mut total = 0;
+        let mut next: Option<(TypeId, i64)> = None;
+        for (type_id, queue) in self.queues.iter_mut().filter(|(_, queue)| !queue.envelopes.is_empty()) {
+            queue.credit += queue.weight;
+            total += queue.weight;
+            if next.is_none_or(|(_, credit)| queue.credit > credit) {
+                next = Some((*type_id, queue.credit));
+            }
+        }
+        let queue = self.queues.get_mut(&next?.0)?;
+        queue.credit -= total;
+        let envelope = queue.envelopes.pop_front();
+        if queue.envelopes.is_empty() {
+            // A type that runs dry starts over, rather than saving up credit while idle
+            queue.credit = 0;
+        }
+        self.len -= 1;
+        envelope
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Takes every queued envelope, for an agent dropping its waiting messages.
+    pub(crate) fn take_all(&mut self) -> Vec<Envelope> {
+        self.len = 0;
+        self.queues.values_mut().flat_map(|queue| queue.envelopes.drain(..)).collect()
+    }
+}
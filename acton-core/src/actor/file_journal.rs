@@ -0,0 +1,85 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::actor::EventJournal;
+
+/// An [`EventJournal`] that keeps each persistence id's events in its own file, one JSON
+/// event per line.
+#[derive(Debug, Clone)]
+pub struct FileJournal {
+    dir: PathBuf,
+}
+
+impl FileJournal {
+    /// Creates a journal writing to `dir`, creating the directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory can't be created.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<FileJournal> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(FileJournal { dir })
+    }
+
+    /// The persistence id is used as the file name, so it can't name another directory.
+    fn path(&self, persistence_id: &str) -> anyhow::Result<PathBuf> {
+        if persistence_id.is_empty() || persistence_id.contains(['/', '\\']) || persistence_id.starts_with('.') {
+            bail!("{:?} can't be used as a journal file name", persistence_id);
+        }
+        Ok(self.dir.join(format!("{persistence_id}.jsonl")))
+    }
+}
+
+impl<E: Serialize + DeserializeOwned> EventJournal<E> for FileJournal {
+    fn append(&self, persistence_id: &str, event: &E) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        let path = self.path(persistence_id)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(&line).with_context(|| format!("appending to {}", path.display()))?;
+        Ok(())
+    }
+
+    fn replay(&self, persistence_id: &str) -> anyhow::Result<Vec<E>> {
+        self.replay_after(persistence_id, 0)
+    }
+
+    fn replay_after(&self, persistence_id: &str, sequence: u64) -> anyhow::Result<Vec<E>> {
+        let path = self.path(persistence_id)?;
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        BufReader::new(file)
+            .lines()
+            // Skipped events are never parsed
+            .skip(sequence as usize)
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<anyhow::Result<_>>()
+            .with_context(|| format!("replaying {}", path.display()))
+    }
+}
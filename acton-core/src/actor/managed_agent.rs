@@ -14,22 +14,44 @@
  * limitations under that License.
  */
 
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Duration;
 
 use acton_ern::prelude::*;
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::Semaphore;
 use tokio_util::task::TaskTracker;
+use tracing::Level;
 
 pub use idle::Idle;
+pub(crate) use idle::{fallible_reactor, message_reactor};
 
+use crate::actor::{DedupWindow, FairQueue, RateLimiter, SignalQueue, SupervisionStrategy, TerminatePolicy};
 use crate::common::{
-    AgentHandle, AsyncLifecycleHandler, BrokerRef, HaltSignal, ParentRef, ReactorMap,
+    AgentHandle, AsyncErrorHandler, ChildFailureHook, Inbox, AsyncLifecycleHandler, BrokerRef, HaltSignal, Interceptor, ParentRef,
+    ReactorMap, ReadinessCheck, ReceiveWaiter, ReconfigureHook, RecoveryHandler, TypeMap,
 };
 use crate::message::Envelope;
 use crate::prelude::AgentRuntime;
 
+/// Opens a span at a level only known at runtime, as `tracing`'s span macros need a
+/// constant one.
+macro_rules! span_at {
+    ($level:expr, $($span:tt)+) => {
+        match $level {
+            tracing::Level::ERROR => tracing::error_span!($($span)+),
+            tracing::Level::WARN => tracing::warn_span!($($span)+),
+            tracing::Level::INFO => tracing::info_span!($($span)+),
+            tracing::Level::DEBUG => tracing::debug_span!($($span)+),
+            _ => tracing::trace_span!($($span)+),
+        }
+    };
+}
+
 mod idle;
 pub mod started;
 
@@ -51,7 +73,11 @@ pub struct ManagedAgent<AgentState, ManagedAgent: Default + Send + Debug + 'stat
 
     pub(crate) tracker: TaskTracker,
 
-    pub(crate) inbox: Receiver<Envelope>,
+    pub(crate) inbox: Inbox,
+    /// Mailbox for high-priority messages, handled ahead of `inbox`.
+    pub(crate) priority_inbox: Option<Inbox>,
+    /// System signals, kept apart from regular messages so they can't be held up for long.
+    pub(crate) signals: SignalQueue,
     /// Reactor called when the actor wakes up but before listening begins.
     pub(crate) before_start: AsyncLifecycleHandler<ManagedAgent>,
     /// Reactor called when the actor wakes up but before listening begins.
@@ -60,8 +86,65 @@ pub struct ManagedAgent<AgentState, ManagedAgent: Default + Send + Debug + 'stat
     pub(crate) before_stop: AsyncLifecycleHandler<ManagedAgent>,
     /// Reactor called when the actor stops listening for messages.
     pub(crate) after_stop: AsyncLifecycleHandler<ManagedAgent>,
+    /// Reactor called when a message handler fails.
+    pub(crate) on_error: AsyncErrorHandler<ManagedAgent>,
     /// Map of reactors for handling different message types.
-    pub(crate) reactors: ReactorMap<ManagedAgent>,
+    pub(crate) reactors: Arc<ReactorMap<ManagedAgent>>,
+    /// Reactor maps replaced by `become_behavior`, most recent last.
+    pub(crate) behaviors: Vec<Arc<ReactorMap<ManagedAgent>>>,
+    /// Agents to notify with `Terminated` when this actor stops.
+    pub(crate) watchers: Vec<AgentHandle>,
+    /// What to do when a handler panics.
+    pub(crate) supervision: SupervisionStrategy,
+    /// Whether handler panics are caught and the agent keeps running.
+    pub(crate) panic_recovery: bool,
+    /// Whether adding a second handler for a message type panics instead of warning.
+    pub(crate) strict_handlers: bool,
+    /// What the agent does with its waiting messages when told to stop.
+    pub(crate) terminate_policy: TerminatePolicy,
+    /// The level of the spans for the agent's run and for each message it handles.
+    pub(crate) trace_level: Level,
+    /// How long `before_stop` may run before the agent stops without it.
+    pub(crate) shutdown_timeout: Duration,
+    /// Decides what the agent does when one of its children escalates a failure.
+    pub(crate) on_child_failure: Option<ChildFailureHook<ManagedAgent>>,
+    /// Hooks applying `Reconfigure` messages, by message type. They are kept apart from the
+    /// reactors so switching behavior doesn't lose them.
+    pub(crate) reconfigure_hooks: HashMap<TypeId, ReconfigureHook<ManagedAgent>>,
+    /// Data kept alongside the model, such as what interceptors attach for handlers.
+    pub(crate) context_data: TypeMap,
+    /// Messages taken from the inbox and queued by type, when message types have weights.
+    pub(crate) fair_queue: Option<FairQueue>,
+    /// Tells the handle whether the agent considers itself ready, judged from its model.
+    pub(crate) readiness: Option<ReadinessCheck<ManagedAgent>>,
+    /// Messages set aside by the agent until it calls `unstash_all`.
+    pub(crate) stash: VecDeque<Envelope>,
+    /// Received messages waiting to be handled again, ahead of the inbox: held while
+    /// suspended, or unstashed.
+    pub(crate) pending: VecDeque<Envelope>,
+    /// The model's `JournalBinding`, if the agent is persistent.
+    pub(crate) journal: Option<Box<dyn Any + Send + Sync>>,
+    /// Rebuilds the model from the journal when the agent starts or restarts.
+    pub(crate) recover: Option<RecoveryHandler<ManagedAgent>>,
+    /// How many events a persistent agent records between snapshots.
+    pub(crate) snapshot_interval: u64,
+    /// Delivery ids of recently handled messages, when deduplication is on.
+    pub(crate) dedup: Option<DedupWindow>,
+    /// How long the agent waits for a message before stopping itself, if it does.
+    pub(crate) idle_timeout: Option<Duration>,
+    /// Checks run on each message before its handler, in order.
+    pub(crate) interceptors: Vec<Interceptor>,
+    /// Paces the messages the agent handles, when it has a rate limit.
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    /// How many handler futures may run at once; one unless set with `with_parallelism`.
+    pub(crate) parallelism: usize,
+    /// Held by each handler future running concurrently, so at most `parallelism` do.
+    pub(crate) handler_slots: Arc<Semaphore>,
+    /// The message type the running handler is waiting for with `receive`, if any.
+    pub(crate) receiving: Option<ReceiveWaiter>,
+    /// Message types whose `act_once` handler has run, to be removed from the reactor map
+    /// once dispatch lets go of it.
+    pub(crate) spent_reactors: Vec<TypeId>,
     _actor_state: std::marker::PhantomData<AgentState>,
 }
 
@@ -78,6 +161,17 @@ impl<ActorState, ManagedEntity: Default + Send + Debug + 'static>
         self.id.root.as_str()
     }
 
+    /// Returns the agent's own store of ad-hoc data, kept alongside its model.
+    pub fn context_data(&self) -> &TypeMap {
+        &self.context_data
+    }
+
+    /// Returns the agent's store of ad-hoc data to change, for example to stash trace context
+    /// or a feature flag without adding a field to the model.
+    pub fn context_data_mut(&mut self) -> &mut TypeMap {
+        &mut self.context_data
+    }
+
     /// Returns the handle of the actor.
     pub fn handle(&self) -> &AgentHandle {
         &self.handle
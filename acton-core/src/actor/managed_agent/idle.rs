@@ -15,17 +15,24 @@
  */
 
 use std::any::TypeId;
+use std::panic::AssertUnwindSafe;
 use std::fmt::Debug;
 use std::future::Future;
-use std::mem;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use acton_ern::{Ern};
-use tokio::sync::mpsc::channel;
+use futures::FutureExt;
+use tokio::sync::{oneshot, Semaphore};
+use tokio::time::Instant;
 use tracing::*;
 
-use crate::actor::{AgentConfig, ManagedAgent, Started};
-use crate::common::{ActonInner, AgentHandle, AgentRuntime,Envelope, FutureBox, OutboundEnvelope, ReactorItem};
-use crate::message::MessageContext;
+use crate::actor::managed_agent::started::panic_message;
+use crate::actor::persistent::{recover_from_journal, JournalBinding, SnapshotBinding};
+use crate::actor::{AgentConfig, DedupWindow, EscalationAction, EventJournal, FairQueue, ManagedAgent, OnTimeout, Persistent, RetryPolicy, SignalQueue, Snapshot, SnapshotStore, Started, TerminatePolicy, DEFAULT_MAILBOX_CAPACITY, DEFAULT_OUTPUT_CAPACITY, DEFAULT_SHUTDOWN_TIMEOUT, DEFAULT_SIGNAL_CHECK_INTERVAL, DEFAULT_SNAPSHOT_INTERVAL, DEFAULT_TRACE_LEVEL};
+use crate::common::{bounded_mailbox, unbounded_mailbox, ActonInner, AgentHandle, AgentRuntime, Envelope, FallibleFutureBox, FutureBox, LazyAgentHandle, OutboundEnvelope, ReactorItem, ReconfigureHook, TypeMap};
+use crate::message::{ChildEscalation, DeadLetter, Reconfigure, DeadLetterReason, MessageContext, MessageError, RetryAttempt};
 use crate::prelude::ActonMessage;
 use crate::traits::Actor;
 
@@ -53,61 +60,219 @@ impl<State: Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
     {
         let type_id = TypeId::of::<M>();
         trace!(type_name=std::any::type_name::<M>(),type_id=?type_id, " Adding message handler");
-        // Create a boxed handler for the message type.
-        let handler_box = Box::new(
-            move |actor: &mut ManagedAgent<Started, State>,
-                  envelope: &mut Envelope|
-                  -> FutureBox {
-                trace!("Creating handler for message type: {:?}", std::any::type_name::<M>());
-
-                let envelope_type_id = envelope.message.as_any().type_id();
-                trace!(
-                "Attempting to downcast message: expected_type_id = {:?}, envelope_type_id = {:?}",
-                type_id, envelope_type_id
-            );
-                if let Some(concrete_msg) = downcast_message::<M>(&*envelope.message) {
-                    trace!(
-                        "Downcast message to name {} and concrete type: {:?}",
-                        std::any::type_name::<M>(),
-                        type_id
-                    );
-
-                    let message = concrete_msg.clone();
-                    let sent_time = envelope.timestamp;
-                    let mut event_record = {
-                        let msg_name = std::any::type_name::<M>();
-                        let sender = envelope.reply_to.sender.root.to_string();
-                        let recipient = envelope.recipient.sender.root.to_string();
-                        let origin_envelope = OutboundEnvelope::new_with_recipient(envelope.reply_to.clone(), envelope.recipient.clone());
-                        let reply_envelope = OutboundEnvelope::new_with_recipient(envelope.recipient.clone(), envelope.reply_to.clone());
-                        trace!("sender {sender}::{msg_name}",);
-                        trace!("recipient {recipient}::{msg_name}",);
-                        MessageContext {
-                            message,
-                            timestamp: sent_time,
-                            origin_envelope,
-                            reply_envelope,
-                        }
-                    };
-
-                    // Call the user-provided function and get the future.
-                    let user_future = message_processor(actor, &mut event_record);
-
-                    // Automatically box and pin the user future.
-                    Box::pin(user_future)
-                } else {
-                    error!(
-                        type_name = std::any::type_name::<M>(),
-                        "Should never get here, message failed to downcast"
-                    );
-                    // Return an immediately resolving future if downcast fails.
-                    Box::pin(async {})
+        self.record_handled::<M>();
+        // Insert the handler into the reactors map.
+        self.reactors.insert(type_id, message_reactor(message_processor));
+        self
+    }
+
+    /// Notes that the agent handles messages of type `M`, for `AgentHandle::handled_types`,
+    /// and flags a handler added for a type that already has one.
+    fn record_handled<M: 'static>(&self) {
+        let type_name = std::any::type_name::<M>();
+        if self.handle.handled_types.insert(TypeId::of::<M>(), type_name).is_none() {
+            return;
+        }
+        if !self.strict_handlers {
+            warn!(actor = self.id.to_string(), "Replacing the handler already added for {}", type_name);
+        } else if cfg!(debug_assertions) {
+            panic!("agent {} already has a handler for {}", self.id, type_name);
+        } else {
+            error!(actor = self.id.to_string(), "Replacing the handler already added for {}", type_name);
+        }
+    }
+
+    /// Adds a message handler that runs for the first message of type `M` only, such as the
+    /// reply that completes an initialization handshake.
+    ///
+    /// Being `FnOnce`, the handler can consume what it captures instead of cloning it. Once
+    /// it has run it is removed, and later messages of type `M` go to the dead-letter agent
+    /// like any other message without a handler, unless a new handler is added for them.
+    #[instrument(skip(self, message_processor), level = "debug")]
+    pub fn act_once<M>(
+        &mut self,
+        message_processor: impl for<'a> FnOnce(
+            &'a mut ManagedAgent<Started, State>,
+            &'a mut MessageContext<M>,
+        ) -> FutureBox
+        + Send
+        + 'static,
+    ) -> &mut Self
+    where
+        M: ActonMessage + Clone + Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<M>();
+        trace!(type_name=std::any::type_name::<M>(),type_id=?type_id, " Adding one-shot message handler");
+        let message_processor = Mutex::new(Some(message_processor));
+        self.record_handled::<M>();
+        self.reactors.insert(
+            type_id,
+            message_reactor(move |agent: &mut ManagedAgent<Started, State>, context: &mut MessageContext<M>| {
+                // The map is still borrowed while the handler runs, so the agent removes it afterwards
+                agent.spent_reactors.push(type_id);
+                match message_processor.lock().unwrap().take() {
+                    Some(message_processor) => message_processor(agent, context),
+                    None => Box::pin(async {}),
                 }
-            },
+            }),
         );
+        self
+    }
 
-        // Insert the handler into the reactors map.
-        self.reactors.insert(type_id, ReactorItem::FutureReactor(handler_box));
+    /// Adds an asynchronous message handler that can fail.
+    ///
+    /// When the handler's future returns an error, the agent's `on_error` reactor is called
+    /// with it and the agent moves on to its next message.
+    ///
+    /// # Parameters
+    /// - `message_processor`: The function to handle the message.
+    #[instrument(skip(self, message_processor), level = "debug")]
+    pub fn act_on_fallible<M>(
+        &mut self,
+        message_processor: impl for<'a> Fn(
+            &'a mut ManagedAgent<Started, State>,
+            &'a mut MessageContext<M>,
+        ) -> FallibleFutureBox
+        + Send
+        + Sync
+        + 'static,
+    ) -> &mut Self
+    where
+        M: ActonMessage + Clone + Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<M>();
+        trace!(type_name=std::any::type_name::<M>(),type_id=?type_id, " Adding fallible message handler");
+        self.record_handled::<M>();
+        self.reactors.insert(type_id, fallible_reactor(message_processor));
+        self
+    }
+
+    /// Adds an asynchronous message handler that may run for at most `timeout`.
+    ///
+    /// A handler still running after `timeout` no longer holds up the agent: the timeout is
+    /// logged, the agent's `on_error` reactor is called with [`MessageError::HandlerTimeout`],
+    /// and the agent moves on to its next message. `on_timeout` decides whether the handler's
+    /// future is dropped or left to finish on its own. A handler that panics within its
+    /// timeout is a failing handler like any other, detached or not, and goes to the agent's
+    /// supervision.
+    ///
+    /// # Parameters
+    /// - `timeout`: How long the handler may run.
+    /// - `on_timeout`: What to do with the handler's future when it times out.
+    /// - `message_processor`: The function to handle the message.
+    #[instrument(skip(self, message_processor), level = "debug")]
+    pub fn act_on_with_timeout<M>(
+        &mut self,
+        timeout: Duration,
+        on_timeout: OnTimeout,
+        message_processor: impl for<'a> Fn(
+            &'a mut ManagedAgent<Started, State>,
+            &'a mut MessageContext<M>,
+        ) -> FutureBox
+        + Send
+        + Sync
+        + 'static,
+    ) -> &mut Self
+    where
+        M: ActonMessage + Clone + Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<M>();
+        trace!(type_name=std::any::type_name::<M>(),type_id=?type_id, ?timeout, " Adding message handler with timeout");
+        let reactor = fallible_reactor(move |agent: &mut ManagedAgent<Started, State>, event: &mut MessageContext<M>| -> FallibleFutureBox {
+            let handled = message_processor(agent, event);
+            let actor = agent.id.to_string();
+            let handle = agent.handle.clone();
+            Box::pin(async move {
+                let started = Instant::now();
+                let timed_out = match on_timeout {
+                    OnTimeout::Cancel => tokio::time::timeout(timeout, handled).await.is_err(),
+                    OnTimeout::Detach => {
+                        let (finished, outcome) = oneshot::channel();
+                        let detached_actor = actor.clone();
+                        handle.spawn(async move {
+                            let outcome = AssertUnwindSafe(handled).catch_unwind().await;
+                            // Nobody is waiting once the handler has timed out, so log a late panic here
+                            if let Err(Err(panic)) = finished.send(outcome) {
+                                error!(
+                                    actor = detached_actor,
+                                    "{} handler panicked after timing out: {}",
+                                    std::any::type_name::<M>(),
+                                    panic_message(&*panic)
+                                );
+                            }
+                        });
+                        match tokio::time::timeout(timeout, outcome).await {
+                            // Panic on the agent's task, so its supervision handles it as usual
+                            Ok(Ok(Err(panic))) => std::panic::resume_unwind(panic),
+                            Ok(_) => false,
+                            Err(_) => true,
+                        }
+                    }
+                };
+                if timed_out {
+                    warn!(actor, "{} handler timed out after {:?} ({:?})", std::any::type_name::<M>(), started.elapsed(), on_timeout);
+                    return Err(MessageError::HandlerTimeout.into());
+                }
+                Ok(())
+            })
+        });
+        self.record_handled::<M>();
+        self.reactors.insert(type_id, reactor);
+        self
+    }
+
+    /// Adds an asynchronous message handler that can fail, retrying it under `policy`.
+    ///
+    /// When the handler's future returns an error, the message is handled again after the
+    /// policy's backoff, scheduled on the agent, so other messages are handled in the meantime.
+    /// Each message keeps its own count of attempts. After the last attempt fails, the message
+    /// goes to the dead-letter agent and the error to the agent's `on_error` reactor.
+    ///
+    /// Retries still scheduled when the agent stops are dropped.
+    ///
+    /// # Parameters
+    /// - `policy`: How many attempts to make and how long to wait between them.
+    /// - `message_processor`: The function to handle the message.
+    #[instrument(skip(self, message_processor), level = "debug")]
+    pub fn act_on_with_retry<M>(
+        &mut self,
+        policy: RetryPolicy,
+        message_processor: impl for<'a> Fn(
+            &'a mut ManagedAgent<Started, State>,
+            &'a mut MessageContext<M>,
+        ) -> FallibleFutureBox
+        + Send
+        + Sync
+        + 'static,
+    ) -> &mut Self
+    where
+        M: ActonMessage + Clone + Send + Sync + 'static,
+    {
+        trace!(type_name = std::any::type_name::<M>(), ?policy, " Adding retrying message handler");
+        let message_processor = Arc::new(message_processor);
+        let first_attempt = message_processor.clone();
+        self.record_handled::<M>();
+        self.reactors.insert(
+            TypeId::of::<M>(),
+            ReactorItem::FallibleReactor(Box::new(move |agent, envelope| {
+                attempt_with_retry::<M, _, _>(&*first_attempt, policy, agent, envelope.clone(), 1)
+            })),
+        );
+        self.reactors.insert(
+            TypeId::of::<RetryAttempt<M>>(),
+            ReactorItem::FallibleReactor(Box::new(move |agent, envelope| {
+                match envelope.message_as::<RetryAttempt<M>>() {
+                    Some(retry) => attempt_with_retry::<M, _, _>(
+                        &*message_processor,
+                        policy,
+                        agent,
+                        retry.envelope.clone(),
+                        retry.attempt,
+                    ),
+                    None => Box::pin(async { Ok(()) }),
+                }
+            })),
+        );
         self
     }
 
@@ -164,6 +329,84 @@ impl<State: Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
         self
     }
 
+    /// Sets the reactor to be called when a message handler fails: when a handler added with
+    /// `act_on_fallible` returns an error, or when a handler panics while panic recovery or a
+    /// restart strategy is configured.
+    ///
+    /// # Parameters
+    /// - `life_cycle_event_reactor`: The function to be called with the agent, the error, and
+    ///   the envelope that was being handled, so the message can be retried or dead-lettered.
+    pub fn on_error<F, Fut>(&mut self, f: F) -> &mut Self
+    where
+        F: for<'b> Fn(&'b ManagedAgent<Started, State>, &'b anyhow::Error, &'b Envelope) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output=()> + Send + Sync + 'static,
+    {
+        self.on_error = Box::new(move |agent, error, envelope| Box::pin(f(agent, error, envelope)) as FutureBox);
+        self
+    }
+
+    /// Sets a function called when senders find the agent's bounded mailbox full, so they
+    /// would have to wait or, with `try_send`, are turned away.
+    ///
+    /// It is called with the agent's ERN and the number of messages waiting, once per
+    /// overload: not again until the agent has emptied its mailbox. It runs on the sender's
+    /// task, so keep it quick; use it to raise an alert or start shedding load.
+    pub fn on_mailbox_full(&mut self, hook: impl Fn(&Ern, usize) + Send + Sync + 'static) -> &mut Self {
+        let id = self.id.clone();
+        self.handle.outbox.on_full(Arc::new(move |depth| hook(&id, depth)));
+        self
+    }
+
+    /// Sets a check of the agent's model that decides whether it reports itself ready in
+    /// [`AgentHandle::health`], for agents that have to warm a cache or connect somewhere
+    /// before they are useful.
+    ///
+    /// The agent runs it after starting and after each message, so reading the result stays
+    /// a cheap load; keep it quick.
+    pub fn readiness_check(&mut self, check: impl Fn(&State) -> bool + Send + Sync + 'static) -> &mut Self {
+        // Not ready until the check has had its first look at the model
+        self.handle.activity.set_ready(false);
+        self.readiness = Some(Box::new(check));
+        self
+    }
+
+    /// Sets the hook that applies new configuration of type `C`, sent with
+    /// [`AgentHandle::reconfigure`], to the running agent, such as a changed threshold.
+    ///
+    /// The hook changes the agent in place, so its model and everything else it holds in
+    /// memory are kept. Unlike switching behavior, the handlers stay as they are; the hook
+    /// stays in effect whichever behavior is active. `Reconfigure` messages are handled in
+    /// turn with the agent's other messages.
+    pub fn on_reconfigure<C>(
+        &mut self,
+        hook: impl Fn(&mut ManagedAgent<Started, State>, &C) + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        C: Clone + Debug + Send + Sync + 'static,
+    {
+        let hook: ReconfigureHook<State> = Arc::new(move |agent, message| {
+            if let Some(Reconfigure(config)) = message.downcast_ref::<Reconfigure<C>>() {
+                hook(agent, config);
+            }
+        });
+        self.record_handled::<Reconfigure<C>>();
+        self.reconfigure_hooks.insert(TypeId::of::<Reconfigure<C>>(), hook);
+        self
+    }
+
+    /// Sets the hook that decides what this agent does when one of its children escalates a
+    /// failure, as described on [`ChildEscalation`]. The child is stopping either way.
+    ///
+    /// Without a hook the child stays stopped and this agent carries on, as it does when
+    /// the hook returns `EscalationAction::Ignore`.
+    pub fn on_child_failure(
+        &mut self,
+        hook: impl Fn(&mut ManagedAgent<Started, State>, &ChildEscalation) -> EscalationAction + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_child_failure = Some(Arc::new(hook));
+        self
+    }
+
     /// Creates and supervises a new actor with the given ID and state.
     ///
     /// # Parameters
@@ -171,8 +414,13 @@ impl<State: Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
     ///
     /// # Returns
     /// A new `Actor` instance in the idle state.
+    ///
+    /// # Errors
+    /// Returns `MessageError::ChildLimitReached` if this agent already has as many children
+    /// as its `with_max_children` limit allows.
     #[instrument(skip(self))]
     pub async fn create_child(&self, name: String) -> anyhow::Result<ManagedAgent<Idle, State>> {
+        self.handle.check_child_limit()?;
         let config = AgentConfig::new(Ern::with_root(name)?, Some(self.handle.clone()), Some(self.runtime.broker().clone()))?;
         Ok(ManagedAgent::new(&Some(self.runtime().clone()), Some(config)).await)
     }
@@ -184,15 +432,66 @@ impl<State: Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
         if let Some(app) = runtime {
             managed_actor.broker = app.0.broker.clone();
             managed_actor.handle.broker = Box::new(Some(app.0.broker.clone()));
+            managed_actor.handle.spawner = app.0.spawner.clone();
         }
 
         if let Some(config) = &config {
             managed_actor.handle.id = config.ern();
             managed_actor.parent = config.parent().clone();
+            managed_actor.handle.parent = config.parent().clone().map(Box::new);
             managed_actor.handle.broker = Box::new(config.get_broker().clone());
             if let Some(broker) = config.get_broker().clone() {
                 managed_actor.broker = broker;
             }
+            let mailbox = if config.unbounded_mailbox() {
+                Some(unbounded_mailbox())
+            } else {
+                config.mailbox_capacity().map(bounded_mailbox)
+            };
+            if let Some((outbox, inbox)) = mailbox {
+                managed_actor.handle.outbox = outbox;
+                managed_actor.inbox = inbox;
+            }
+            if config.priority_mailbox() {
+                let capacity = config.mailbox_capacity().unwrap_or(DEFAULT_MAILBOX_CAPACITY);
+                let (outbox, inbox) = match config.unbounded_mailbox() {
+                    true => unbounded_mailbox(),
+                    false => bounded_mailbox(capacity),
+                };
+                managed_actor.handle.priority_outbox = Some(outbox.sharing_count_with(&managed_actor.handle.outbox));
+                managed_actor.priority_inbox = Some(inbox);
+            }
+            managed_actor.supervision = config.supervision();
+            managed_actor.panic_recovery = config.panic_recovery();
+            managed_actor.strict_handlers = config.strict_handlers();
+            managed_actor.shutdown_timeout = config.shutdown_timeout().unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+            managed_actor.snapshot_interval = config.snapshot_interval().unwrap_or(DEFAULT_SNAPSHOT_INTERVAL);
+            managed_actor.dedup = config.dedup_window().map(DedupWindow::new);
+            managed_actor.idle_timeout = config.idle_timeout();
+            managed_actor.interceptors = config.interceptors().to_vec();
+            managed_actor.rate_limiter = config.rate_limiter();
+            managed_actor.terminate_policy = config.terminate_policy();
+            managed_actor.trace_level = config.trace_level();
+            if !config.message_weights().is_empty() {
+                managed_actor.fair_queue = Some(FairQueue::new(config.message_weights()));
+            }
+            if let Some(parallelism) = config.parallelism() {
+                managed_actor.parallelism = parallelism;
+                managed_actor.handler_slots = Arc::new(Semaphore::new(parallelism));
+            }
+            managed_actor.handle.max_children = config.max_children();
+            managed_actor.handle.output_capacity = config.output_capacity().unwrap_or(DEFAULT_OUTPUT_CAPACITY);
+            managed_actor.signals.set_interval(config.signal_check_interval().unwrap_or(DEFAULT_SIGNAL_CHECK_INTERVAL));
+            // The regular mailbox may have been replaced, so count signals with it again
+            managed_actor.handle.signal_outbox =
+                managed_actor.handle.signal_outbox.clone().sharing_count_with(&managed_actor.handle.outbox);
+            #[cfg(feature = "testing")]
+            if config.recording() {
+                managed_actor.handle.outbox.start_recording();
+            }
+            if config.metrics() {
+                managed_actor.handle.metrics = Some(Arc::default());
+            }
         }
 
         debug_assert!(
@@ -202,12 +501,18 @@ impl<State: Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
 
         trace!("NEW ACTOR: {}", &managed_actor.handle.id());
 
+        // Only the runtime's own broker and dead-letter agents are created before there is a
+        // runtime. The broker is its own broker; it mustn't get a detached stand-in that
+        // silently drops whatever is sent to it.
         managed_actor.runtime = runtime.clone().unwrap_or_else(|| AgentRuntime(ActonInner {
-            broker: managed_actor.handle.broker.clone().unwrap_or_default(),
+            broker: (*managed_actor.handle.broker).clone().unwrap_or_else(|| managed_actor.handle.clone()),
             ..Default::default()
         }));
 
         managed_actor.id = managed_actor.handle.id();
+        if let Some(config) = config.as_ref().filter(|config| !config.dependencies().is_empty()) {
+            managed_actor.runtime.0.dependencies.insert(managed_actor.id.clone(), config.dependencies().to_vec());
+        }
 
         managed_actor
     }
@@ -217,23 +522,101 @@ impl<State: Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
     pub async fn start(mut self) -> AgentHandle {
         trace!("The model is {:?}", self.model);
 
-        let reactors = mem::take(&mut self.reactors);
         let actor_ref = self.handle.clone();
         trace!("actor_ref before spawn: {:?}", actor_ref.id.root.to_string());
-        let active_actor: ManagedAgent<Started, State> = self.into();
-        let actor = Box::leak(Box::new(active_actor));
+        let mut actor: ManagedAgent<Started, State> = self.into();
 
         debug_assert!(
             !actor.inbox.is_closed(),
             "Actor mailbox is closed in activate"
         );
-        (actor.before_start)(actor).await;
-        actor_ref.tracker().spawn(actor.wake(reactors));
+        actor.recover();
+        (actor.before_start)(&actor).await;
+        actor.runtime.0.registry.insert(actor_ref.id.clone(), actor_ref.clone());
+        // The task owns the agent, which is dropped once it stops
+        let span = span_at!(actor.trace_level, "wake", actor = %actor.id);
+        actor_ref.spawn(
+            async move {
+                if let Err(panic) = AssertUnwindSafe(actor.wake()).catch_unwind().await {
+                    actor.stop_after_panic(panic_message(&*panic)).await;
+                }
+            }
+            .instrument(span),
+        );
         actor_ref.tracker().close();
         trace!("actor_ref after spawn: {:?}", actor_ref.id.root.to_string());
 
         actor_ref
     }
+
+    /// Sets the agent up to start the first time it is used, instead of now.
+    ///
+    /// Until then it has no task, so a large fleet of mostly idle agents costs little. The
+    /// returned handle starts the agent, running `before_start` and `after_start`, on its
+    /// first `send`; messages sent to the agent some other way wait in its mailbox until
+    /// then. A root agent that is never started is left out of `shutdown_all`.
+    pub fn prepare(self) -> LazyAgentHandle {
+        let id = self.id.clone();
+        // Stopping an agent with no task would wait for it forever
+        let root = self.runtime.0.roots.remove(&id);
+        let runtime = self.runtime.clone();
+        LazyAgentHandle::new(
+            id,
+            Box::pin(async move {
+                let handle = self.start().await;
+                if let Some((id, _)) = root {
+                    runtime.0.roots.insert(id, handle.clone());
+                }
+                handle
+            }),
+        )
+    }
+}
+
+impl<State: Persistent + Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
+    /// Makes the agent persistent: events it records with `ManagedAgent::persist` are
+    /// appended to `journal` under `persistence_id`, and replayed to rebuild its state
+    /// whenever it starts or restarts.
+    ///
+    /// The persistence id must stay the same across runs, so it can't be the agent's ERN.
+    pub fn with_journal(
+        &mut self,
+        persistence_id: impl Into<String>,
+        journal: Arc<dyn EventJournal<State::Event>>,
+    ) -> &mut Self {
+        self.journal = Some(Box::new(JournalBinding::<State> {
+            persistence_id: persistence_id.into(),
+            journal,
+            sequence: AtomicU64::new(0),
+            snapshots: None,
+        }));
+        self.recover = Some(recover_from_journal::<State>);
+        self
+    }
+}
+
+impl<State: Snapshot + Default + Send + Debug + 'static> ManagedAgent<Idle, State> {
+    /// Saves a snapshot of the state to `store` every time the agent has recorded another
+    /// `AgentConfig::with_snapshot_interval` events (100 unless configured).
+    ///
+    /// Recovery then starts from the latest snapshot and replays only the events after it.
+    /// Call after [`ManagedAgent::with_journal`]; without a journal there is nothing to
+    /// snapshot.
+    pub fn with_snapshots(&mut self, store: Arc<dyn SnapshotStore>) -> &mut Self {
+        let every = self.snapshot_interval;
+        match self.journal.as_mut().and_then(|journal| journal.downcast_mut::<JournalBinding<State>>()) {
+            Some(binding) => {
+                binding.snapshots = Some(SnapshotBinding {
+                    store,
+                    every,
+                    save: State::to_snapshot,
+                    load: State::from_snapshot,
+                });
+            }
+            None => error!(actor = self.id.to_string(), "with_snapshots needs with_journal first, ignoring"),
+        }
+        self
+    }
 }
 
 impl<State: Default + Send + Debug + 'static> From<ManagedAgent<Idle, State>>
@@ -244,12 +627,37 @@ for ManagedAgent<Started, State>
         let on_start = value.after_start;
         let on_stopped = value.after_stop;
         let on_before_stop = value.before_stop;
+        let on_error = value.on_error;
         let halt_signal = value.halt_signal;
         let parent = value.parent;
         let id = value.id;
         let tracker = value.tracker;
         let acton = value.runtime;
         let reactors = value.reactors;
+        let behaviors = value.behaviors;
+        let watchers = value.watchers;
+        let supervision = value.supervision;
+        let panic_recovery = value.panic_recovery;
+        let strict_handlers = value.strict_handlers;
+        let shutdown_timeout = value.shutdown_timeout;
+        let terminate_policy = value.terminate_policy;
+        let trace_level = value.trace_level;
+        let readiness = value.readiness;
+        let fair_queue = value.fair_queue;
+        let context_data = value.context_data;
+        let on_child_failure = value.on_child_failure;
+        let reconfigure_hooks = value.reconfigure_hooks;
+        let stash = value.stash;
+        let pending = value.pending;
+        let journal = value.journal;
+        let recover = value.recover;
+        let snapshot_interval = value.snapshot_interval;
+        let dedup = value.dedup;
+        let idle_timeout = value.idle_timeout;
+        let interceptors = value.interceptors;
+        let rate_limiter = value.rate_limiter;
+        let parallelism = value.parallelism;
+        let handler_slots = value.handler_slots;
 
 
         debug_assert!(
@@ -258,6 +666,8 @@ for ManagedAgent<Started, State>
         );
 
         let inbox = value.inbox;
+        let priority_inbox = value.priority_inbox;
+        let signals = value.signals;
         let handle = value.handle;
         let model = value.model;
         let broker = value.broker;
@@ -279,12 +689,41 @@ for ManagedAgent<Started, State>
             model,
             tracker,
             inbox,
+            priority_inbox,
+            signals,
             before_start: on_starting,
             after_start: on_start,
             before_stop: on_before_stop,
             after_stop: on_stopped,
+            on_error,
             broker,
             reactors,
+            behaviors,
+            watchers,
+            supervision,
+            panic_recovery,
+            strict_handlers,
+            shutdown_timeout,
+            terminate_policy,
+            trace_level,
+            readiness,
+            fair_queue,
+            context_data,
+            on_child_failure,
+            reconfigure_hooks,
+            stash,
+            pending,
+            journal,
+            recover,
+            snapshot_interval,
+            dedup,
+            idle_timeout,
+            interceptors,
+            rate_limiter,
+            parallelism,
+            handler_slots,
+            receiving: None,
+            spent_reactors: Vec::new(),
             _actor_state: Default::default(),
         }
     }
@@ -294,20 +733,25 @@ impl<State: Default + Send + Debug + 'static> Default
 for ManagedAgent<Idle, State>
 {
     fn default() -> Self {
-        let (outbox, inbox) = channel(255);
+        let (outbox, inbox) = bounded_mailbox(DEFAULT_MAILBOX_CAPACITY);
+        let (signal_outbox, signal_inbox) = bounded_mailbox(DEFAULT_MAILBOX_CAPACITY);
         let id: Ern = Default::default();
         let mut handle: AgentHandle = Default::default();
         handle.id = id.clone();
+        handle.signal_outbox = signal_outbox.sharing_count_with(&outbox);
         handle.outbox = outbox.clone();
 
         ManagedAgent::<Idle, State> {
             handle,
             id,
             inbox,
+            priority_inbox: None,
+            signals: SignalQueue::new(signal_inbox, DEFAULT_SIGNAL_CHECK_INTERVAL),
             before_start: Box::new(|a: &'_ ManagedAgent<Started, State>| default_handler(a)),
             after_start: Box::new(|a: &'_ ManagedAgent<Started, State>| default_handler(a)),
             before_stop: Box::new(|a: &'_ ManagedAgent<Started, State>| default_handler(a)),
             after_stop: Box::new(|a: &'_ ManagedAgent<Started, State>| default_handler(a)),
+            on_error: Box::new(|a: &'_ ManagedAgent<Started, State>, _, _| default_handler(a)),
             model: State::default(),
             broker: Default::default(),
             parent: Default::default(),
@@ -315,11 +759,193 @@ for ManagedAgent<Idle, State>
             halt_signal: Default::default(),
             tracker: Default::default(),
             reactors: Default::default(),
+            behaviors: Default::default(),
+            watchers: Default::default(),
+            supervision: Default::default(),
+            panic_recovery: false,
+            strict_handlers: false,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            terminate_policy: TerminatePolicy::default(),
+            trace_level: DEFAULT_TRACE_LEVEL,
+            readiness: None,
+            fair_queue: None,
+            context_data: TypeMap::default(),
+            on_child_failure: None,
+            reconfigure_hooks: Default::default(),
+            stash: Default::default(),
+            pending: Default::default(),
+            journal: None,
+            recover: None,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            dedup: None,
+            idle_timeout: None,
+            interceptors: Vec::new(),
+            rate_limiter: None,
+            parallelism: 1,
+            handler_slots: Arc::new(Semaphore::new(1)),
+            receiving: None,
+            spent_reactors: Vec::new(),
             _actor_state: Default::default(),
         }
     }
 }
 
+/// Wraps a typed message handler so it can be stored in a reactor map.
+pub(crate) fn message_reactor<M, State>(
+    message_processor: impl for<'a> Fn(
+        &'a mut ManagedAgent<Started, State>,
+        &'a mut MessageContext<M>,
+    ) -> FutureBox
+    + Send
+    + Sync
+    + 'static,
+) -> ReactorItem<State>
+where
+    M: ActonMessage + Clone + Send + Sync + 'static,
+    State: Default + Send + Debug + 'static,
+{
+    // Create a boxed handler for the message type.
+    let handler_box = Box::new(
+        move |actor: &mut ManagedAgent<Started, State>,
+              envelope: &mut Envelope|
+              -> FutureBox {
+            match message_context::<M>(envelope) {
+                // Call the user-provided function and get the future.
+                Some(mut event_record) => message_processor(actor, &mut event_record),
+                // Return an immediately resolving future if downcast fails.
+                None => Box::pin(async {}),
+            }
+        },
+    );
+    ReactorItem::FutureReactor(handler_box)
+}
+
+/// Wraps a typed message handler that can fail so it can be stored in a reactor map.
+pub(crate) fn fallible_reactor<M, State>(
+    message_processor: impl for<'a> Fn(
+        &'a mut ManagedAgent<Started, State>,
+        &'a mut MessageContext<M>,
+    ) -> FallibleFutureBox
+    + Send
+    + Sync
+    + 'static,
+) -> ReactorItem<State>
+where
+    M: ActonMessage + Clone + Send + Sync + 'static,
+    State: Default + Send + Debug + 'static,
+{
+    let handler_box = Box::new(
+        move |actor: &mut ManagedAgent<Started, State>,
+              envelope: &mut Envelope|
+              -> FallibleFutureBox {
+            match message_context::<M>(envelope) {
+                Some(mut event_record) => message_processor(actor, &mut event_record),
+                None => Box::pin(async { Ok(()) }),
+            }
+        },
+    );
+    ReactorItem::FallibleReactor(handler_box)
+}
+
+/// Runs one attempt of a retrying handler, scheduling the next attempt or dead-lettering the
+/// message if it fails.
+fn attempt_with_retry<M, State, F>(
+    message_processor: &F,
+    policy: RetryPolicy,
+    agent: &mut ManagedAgent<Started, State>,
+    envelope: Envelope,
+    attempt: usize,
+) -> FallibleFutureBox
+where
+    M: ActonMessage + Clone + Send + Sync + 'static,
+    State: Default + Send + Debug + 'static,
+    F: for<'a> Fn(&'a mut ManagedAgent<Started, State>, &'a mut MessageContext<M>) -> FallibleFutureBox,
+{
+    let Some(mut event_record) = message_context::<M>(&envelope) else {
+        return Box::pin(async { Ok(()) });
+    };
+    let outcome = message_processor(agent, &mut event_record);
+    let handle = agent.handle.clone();
+    let dead_letters = agent.runtime.dead_letters();
+    Box::pin(async move {
+        let Err(error) = outcome.await else {
+            return Ok(());
+        };
+        if attempt < policy.max_attempts {
+            let delay = policy.delay_after(attempt);
+            warn!(
+                actor = handle.id.to_string(),
+                "Attempt {} of {} failed, retrying in {:?}: {:#}", attempt, policy.max_attempts, delay, error
+            );
+            handle.schedule(delay, RetryAttempt::<M>::new(envelope, attempt + 1));
+            return Ok(());
+        }
+        if !dead_letters.outbox.is_closed() {
+            let mut envelope = envelope;
+            // Drop any ask reply or stream channel so the asker isn't left waiting forever
+            envelope.reply_channel = None;
+            envelope.stream_channel = None;
+            handle
+                .create_envelope(Some(dead_letters.reply_address()))
+                .send(DeadLetter { envelope, reason: DeadLetterReason::RetriesExhausted })
+                .await;
+        }
+        Err(error)
+    })
+}
+
+/// Builds the context a typed handler sees from the envelope it was sent in.
+fn message_context<M>(envelope: &Envelope) -> Option<MessageContext<M>>
+where
+    M: ActonMessage + Clone + Send + Sync + 'static,
+{
+    let type_id = TypeId::of::<M>();
+    trace!("Creating handler for message type: {:?}", std::any::type_name::<M>());
+
+    let envelope_type_id = (*envelope.message).as_any().type_id();
+    trace!(
+        "Attempting to downcast message: expected_type_id = {:?}, envelope_type_id = {:?}",
+        type_id, envelope_type_id
+    );
+    let Some(concrete_msg) = downcast_message::<M>(&*envelope.message) else {
+        error!(
+            type_name = std::any::type_name::<M>(),
+            "Should never get here, message failed to downcast"
+        );
+        return None;
+    };
+    trace!(
+        "Downcast message to name {} and concrete type: {:?}",
+        std::any::type_name::<M>(),
+        type_id
+    );
+
+    let message = concrete_msg.clone();
+    let sent_time = envelope.timestamp;
+    let msg_name = std::any::type_name::<M>();
+    let sender = envelope.reply_to.sender.root.to_string();
+    let recipient = envelope.recipient.sender.root.to_string();
+    let origin_envelope = OutboundEnvelope::new_with_recipient(envelope.reply_to.clone(), envelope.recipient.clone())
+        .caused_by(envelope);
+    let reply_envelope = OutboundEnvelope::new_with_recipient(envelope.recipient.clone(), envelope.reply_to.clone())
+        .caused_by(envelope);
+    trace!("sender {sender}::{msg_name}",);
+    trace!("recipient {recipient}::{msg_name}",);
+    Some(MessageContext {
+        message,
+        timestamp: sent_time,
+        origin_envelope,
+        reply_envelope,
+        reply_channel: envelope.reply_channel.clone(),
+        stream_channel: envelope.stream_channel.clone(),
+        message_id: envelope.message_id,
+        correlation_id: envelope.correlation_id,
+        causation_id: envelope.causation_id,
+        delivery: envelope.delivery.clone(),
+        queue_latency: envelope.queue_latency,
+    })
+}
+
 fn default_handler<State: Debug + Send + Default>(
     _actor: &'_ ManagedAgent<Started, State>,
 ) -> FutureBox {
@@ -14,18 +14,33 @@
  * limitations under that License.
  */
 
-use std::any::type_name_of_val;
+use std::any::{type_name_of_val, TypeId};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
-use std::time::Duration;
+use std::future::Future;
+use std::mem;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+use acton_ern::Ern;
+use anyhow::anyhow;
 use futures::future::join_all;
-use tokio::time::sleep;
-use tracing::{instrument, trace};
+use futures::FutureExt;
+use tokio::sync::oneshot;
+use tokio::time::error::Elapsed;
+use tokio::time::{sleep, timeout, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, instrument, trace, warn, Instrument};
 
-use crate::actor::ManagedAgent;
-use crate::common::{Envelope, OutboundEnvelope, ReactorItem, ReactorMap};
-use crate::message::{BrokerRequestEnvelope, MessageAddress, SystemSignal};
-use crate::traits::Actor;
+use crate::actor::persistent::JournalBinding;
+use crate::actor::{Behavior, EscalationAction, FairQueue, ManagedAgent, Persistent, RateLimitPolicy, SupervisionStrategy, TerminatePolicy};
+use crate::common::{abandon_ask, Envelope, FutureBox, Inbox, OutboundEnvelope, ReactorItem, TypeMap};
+use crate::message::{
+    BrokerRequest, BrokerRequestEnvelope, ChildEscalation, DeadLetter, DeadLetterReason, FailureKind, FailureReport, InterceptDecision, MessageAddress, MessageError, RecvError,
+    SystemEvent, SystemSignal, Terminated, TrySendError,
+};
+use crate::traits::{ActonMessage, Actor};
 
 /// The `Started` state of the actor.
 pub struct Started;
@@ -50,62 +65,916 @@ impl<Agent: Default + Send + Debug + 'static> ManagedAgent<Started, Agent> {
         self.parent.as_ref().map(|parent| parent.create_envelope(None).clone())
     }
 
-    #[instrument(skip(reactors, self))]
-    pub(crate) async fn wake(&mut self, reactors: ReactorMap<Agent>) {
+    /// Sends `message` to the agent's parent, with this agent as the sender.
+    ///
+    /// The returned future owns everything it needs, so it can be returned straight from a
+    /// handler.
+    ///
+    /// # Errors
+    ///
+    /// The future fails with `MessageError::NoParent` if this is a root agent.
+    pub fn send_to_parent(
+        &self,
+        message: impl ActonMessage + 'static,
+    ) -> impl Future<Output = Result<(), MessageError>> + Send + Sync + 'static {
+        let envelope = self
+            .parent
+            .as_ref()
+            .map(|parent| self.handle.create_envelope(Some(parent.reply_address())));
+        async move {
+            let envelope = envelope.ok_or(MessageError::NoParent)?;
+            envelope.send(message).await;
+            Ok(())
+        }
+    }
+
+    /// Returns a token that is cancelled when the agent is asked to stop, drain or suspend.
+    ///
+    /// A handler doing long work, such as reading a stream, can `select!` on
+    /// `cancelled()` to give up early instead of holding up the stop. Take the token before
+    /// the handler's future, as it can't borrow the agent. After `resume` handlers get a
+    /// new token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.handle.interrupt_token()
+    }
+
+    /// Runs `future` on the agent's task tracker and sends its output back to this agent
+    /// as a message.
+    ///
+    /// Use it to await external work, such as a database or HTTP call, without holding up
+    /// the handler. The future is dropped if the agent stops first.
+    pub fn pipe_to_self<M, F>(&self, future: F)
+    where
+        M: ActonMessage + 'static,
+        F: Future<Output = M> + Send + 'static,
+    {
+        let envelope = self.handle.create_envelope(None);
+        let stopped = self.handle.cancellation_token.clone();
+        self.handle.spawn(async move {
+            tokio::select! {
+                message = future => envelope.send(message).await,
+                _ = stopped.cancelled() => trace!("Agent stopped before the piped future completed"),
+            }
+        });
+    }
+
+    /// Like [`ManagedAgent::pipe_to_self`], for a future that can fail.
+    ///
+    /// `Ok` values are sent as they are; errors are turned into a message with `map_err`.
+    pub fn try_pipe_to_self<M, E, Err, F, MapErr>(&self, future: F, map_err: MapErr)
+    where
+        M: ActonMessage + 'static,
+        E: ActonMessage + 'static,
+        Err: Send + 'static,
+        F: Future<Output = Result<M, Err>> + Send + 'static,
+        MapErr: FnOnce(Err) -> E + Send + 'static,
+    {
+        let envelope = self.handle.create_envelope(None);
+        let stopped = self.handle.cancellation_token.clone();
+        self.handle.spawn(async move {
+            tokio::select! {
+                result = future => match result {
+                    Ok(message) => envelope.send(message).await,
+                    Err(error) => envelope.send(map_err(error)).await,
+                },
+                _ = stopped.cancelled() => trace!("Agent stopped before the piped future completed"),
+            }
+        });
+    }
+
+    /// Sends `message` to this agent, to be handled after the messages already waiting.
+    ///
+    /// Use it to advance a state machine from inside a handler. It never waits: a handler
+    /// that waited for room in its own full mailbox would wait forever, since only the agent
+    /// can empty it. When the mailbox is full, the message is handed to a task on the
+    /// agent's tracker that sends it once there is room, so messages arriving in the
+    /// meantime may be handled first. The message is dropped if the agent is stopping.
+    pub fn send_self(&self, message: impl ActonMessage + 'static) {
+        let envelope = self.handle.create_envelope(None);
+        match envelope.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(message)) => {
+                trace!(actor = self.id.to_string(), "Mailbox full, sending {:?} to self once there is room", message);
+                self.handle.spawn(async move { envelope.send(message).await });
+            }
+            Err(TrySendError::Closed) => {
+                trace!(actor = self.id.to_string(), "Agent is stopping, dropping message to self");
+            }
+        }
+    }
+
+    /// Sets a message aside to be handled later, after [`ManagedAgent::unstash_all`].
+    ///
+    /// Get the envelope for the message being handled with `MessageContext::envelope`.
+    pub fn stash(&mut self, envelope: Envelope) {
+        trace!(actor = self.id.to_string(), "Stashing {:?}", envelope.message);
+        self.stash.push_back(envelope);
+    }
+
+    /// Puts every stashed message back at the front of the mailbox, in the order they were stashed.
+    ///
+    /// They are handled before any message that has not been received yet.
+    pub fn unstash_all(&mut self) {
+        trace!(actor = self.id.to_string(), "Unstashing {} messages", self.stash.len());
+        // They were counted as handled when stashed, and are waiting again now
+        self.handle.outbox.count_sent(self.stash.len());
+        while let Some(envelope) = self.stash.pop_back() {
+            self.pending.push_front(envelope);
+        }
+    }
+
+    /// Waits for the next message of type `M`, setting aside any other messages that arrive
+    /// first.
+    ///
+    /// Call it from a handler and await the returned future in the handler's reply. While
+    /// the handler runs, the agent takes messages from its mailbox until one is an `M` and
+    /// hands that one to the future instead of a handler. The messages set aside are handled
+    /// after the handler, in the order they arrived. A handler waits for one message this
+    /// way; calling `receive` again replaces the earlier wait.
+    ///
+    /// # Errors
+    ///
+    /// The future fails with `RecvError::Timeout` if no `M` arrives within `timeout`, and with
+    /// `RecvError::Closed` if the agent stops taking messages first or the wait is replaced.
+    pub fn receive<M: ActonMessage + Clone + 'static>(
+        &mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<M, RecvError>> + Send + Sync + 'static {
+        let (reply, received) = oneshot::channel();
+        self.receiving = Some((TypeId::of::<M>(), reply));
+        async move {
+            let message = tokio::time::timeout(timeout, received)
+                .await
+                .map_err(|_| RecvError::Timeout)?
+                .map_err(|_| RecvError::Closed)?;
+            (*message).as_any().downcast_ref::<M>().cloned().ok_or(RecvError::Closed)
+        }
+    }
+
+    /// Replaces the agent's message handlers with `behavior`, starting with the next message.
+    ///
+    /// The current handlers are kept so [`ManagedAgent::unbecome`] can switch back to them.
+    pub fn become_behavior(&mut self, behavior: Behavior<Agent>) {
+        trace!(actor = self.id.to_string(), "Becoming {:?}", behavior);
+        let previous = mem::replace(&mut self.reactors, behavior.reactors);
+        self.behaviors.push(previous);
+    }
+
+    /// Switches back to the handlers that were active before the last
+    /// [`ManagedAgent::become_behavior`].
+    ///
+    /// Does nothing if the agent is still using the handlers it was started with.
+    pub fn unbecome(&mut self) {
+        match self.behaviors.pop() {
+            Some(previous) => self.reactors = previous,
+            None => trace!(actor = self.id.to_string(), "No previous behavior to restore"),
+        }
+    }
+
+    /// Replays the agent's journal into its model, if it is persistent.
+    pub(crate) fn recover(&mut self) {
+        let (Some(recover), Some(journal)) = (self.recover, &self.journal) else {
+            return;
+        };
+        match recover(journal.as_ref(), &mut self.model) {
+            Ok(()) => trace!(actor = self.id.to_string(), "Recovered {:?}", self.model),
+            Err(e) => error!(actor = self.id.to_string(), "Failed to replay the journal: {:#}", e),
+        }
+    }
+
+    /// Runs the agent until it stops, inside the span `start` opens at its trace level.
+    pub(crate) async fn wake(&mut self) {
+        self.handle.activity.mark_started();
         (self.after_start)(self).await;
+        self.check_readiness();
+        self.publish_event(SystemEvent::AgentStarted { id: self.id.clone(), at: SystemTime::now() }).await;
         let mut terminate_requested = false;
-        while let Some(incoming_envelope) = self.inbox.recv().await {
+        // When each child that failed under `SupervisionStrategy::Restart` was restarted
+        let mut child_restarts: HashMap<Ern, VecDeque<Instant>> = HashMap::new();
+        loop {
+            let next = if self.handle.activity.is_suspended() { None } else { self.pending.pop_front() };
+            let incoming_envelope = match next {
+                Some(envelope) => envelope,
+                None => match self.receive_until_idle(terminate_requested).await {
+                    Ok(Some(envelope)) => {
+                        self.handle.activity.mark_message();
+                        envelope
+                    }
+                    Ok(None) => break,
+                    Err(_) => {
+                        trace!(actor = self.id.to_string(), "Idle for {:?}, stopping", self.idle_timeout);
+                        terminate_requested = true;
+                        self.handle.activity.set_suspended(false);
+                        self.begin_stop().await;
+                        // Anything that arrived as the timeout ran out is still handled first
+                        if self.drained() {
+                            let span = span_at!(self.trace_level, "terminate", actor = %self.id);
+                            self.terminate().instrument(span).await;
+                            break;
+                        }
+                        continue;
+                    }
+                },
+            };
             let type_id;
             let mut envelope;
             trace!("envelope sender is {}", incoming_envelope.reply_to.sender.root);
             trace!("{}", type_name_of_val(&incoming_envelope.message));
             // Special case for BrokerRequestEnvelope
-            if let Some(broker_request_envelope) = incoming_envelope
-                .message
-                .as_any()
-                .downcast_ref::<BrokerRequestEnvelope>()
+            // Messages are held in an `Arc`, which is itself an `ActonMessage`; deref it so
+            // `as_any` sees the message and not the `Arc`.
+            if let Some(broker_request_envelope) =
+                (*incoming_envelope.message).as_any().downcast_ref::<BrokerRequestEnvelope>()
             {
+                // Replies go to the publisher; without one, the envelope names this agent as
+                // the sender, so there is no one to reply to
+                let reply_to = broker_request_envelope
+                    .publisher
+                    .clone()
+                    .unwrap_or_else(|| incoming_envelope.reply_to.clone());
                 envelope = Envelope::new(
                     broker_request_envelope.message.clone(),
-                    incoming_envelope.reply_to.clone(),
+                    reply_to,
                     incoming_envelope.recipient.clone(),
                 );
-                type_id = broker_request_envelope.message.as_any().type_id();
+                envelope.reply_channel = incoming_envelope.reply_channel.clone();
+                envelope.stream_channel = incoming_envelope.stream_channel.clone();
+                envelope.message_id = incoming_envelope.message_id;
+                envelope.correlation_id = incoming_envelope.correlation_id;
+                envelope.causation_id = incoming_envelope.causation_id;
+                envelope.delivery = incoming_envelope.delivery.clone();
+                envelope.enqueued_at = incoming_envelope.enqueued_at;
+                type_id = (*broker_request_envelope.message).as_any().type_id();
             } else {
                 envelope = incoming_envelope;
-                type_id = envelope.message.as_any().type_id();
+                type_id = (*envelope.message).as_any().type_id();
             }
 
-            if let Some(reactor) = reactors.get(&type_id) {
-                match reactor.value() {
-                    ReactorItem::FutureReactor(fut) => fut(self, &mut envelope).await,
+            envelope.queue_latency = envelope.enqueued_at.elapsed();
+
+            // Hold on to the current map: a handler may switch behavior while it runs
+            let reactors = self.reactors.clone();
+            // Set when the handler's future was handed off to run alongside others
+            let mut dispatched = false;
+            let duplicate = match (self.dedup.as_mut(), envelope.delivery_id()) {
+                (Some(dedup), Some(delivery_id)) => !dedup.record(delivery_id),
+                _ => false,
+            };
+            let decision = self.intercept(&envelope);
+            // Only messages on their way to a handler count against the rate limit
+            let admitted = duplicate || decision != InterceptDecision::Continue || self.admit(&envelope).await;
+            if duplicate {
+                trace!(actor = self.id.to_string(), "Skipping duplicate delivery {:?}", envelope.delivery_id());
+                envelope.ack();
+            } else if decision == InterceptDecision::Drop {
+                trace!(actor = self.id.to_string(), "Interceptor dropped {:?}", envelope.message);
+            } else if let InterceptDecision::Reject(reason) = decision {
+                trace!(actor = self.id.to_string(), "Interceptor rejected {:?}: {}", envelope.message, reason);
+                self.forward_dead_letter(envelope, DeadLetterReason::Rejected(reason)).await;
+            } else if !admitted {
+                trace!(actor = self.id.to_string(), "Over the rate limit, refusing {:?}", envelope.message);
+                self.forward_dead_letter(envelope, DeadLetterReason::RateLimited).await;
+            } else if let Some(reactor) = reactors.get(&type_id) {
+                if let Some(metrics) = &self.handle.metrics {
+                    metrics.record_queue_latency(type_id, (*envelope.message).type_name(), envelope.queue_latency);
                 }
-            } else if let Some(SystemSignal::Terminate) =
-                envelope.message.as_any().downcast_ref::<SystemSignal>()
-            {
-                // Set the termination flag
-                terminate_requested = true;
-                trace!("Termination signal received, waiting for remaining messages...");
-                (self.before_stop)(self).await;
-                //give the before_stop a chance to process the termination signal
-                sleep(Duration::from_millis(10)).await;
-                self.inbox.close();
-            }
-            if terminate_requested && self.inbox.is_empty() && self.inbox.is_closed() {
-                self.inbox.close();
-                self.terminate().await;
+                let span = span_at!(
+                    self.trace_level,
+                    "handle",
+                    message_id = %envelope.message_id,
+                    correlation_id = %envelope.correlation_id,
+                    causation_id = ?envelope.causation_id,
+                );
+                let handled = async {
+                    match reactor.value() {
+                        ReactorItem::FutureReactor(fut) => {
+                            let handler = fut(self, &mut envelope);
+                            if self.parallelism > 1 && self.receiving.is_none() {
+                                self.dispatch(handler, type_id, &envelope).await;
+                                dispatched = true;
+                            } else {
+                                self.run_handler(handler).await;
+                            }
+                            Ok(())
+                        }
+                        ReactorItem::FallibleReactor(fut) => {
+                            let handler = fut(self, &mut envelope);
+                            self.run_handler(handler).await
+                        }
+                    }
+                }
+                .instrument(span);
+                let started = Instant::now();
+                // Always caught, so even an agent that stops on a panic stops cleanly
+                let outcome = AssertUnwindSafe(handled).catch_unwind().await;
+                if let Some(metrics) = self.handle.metrics.as_ref().filter(|_| !dispatched) {
+                    metrics.record(type_id, (*envelope.message).type_name(), started.elapsed());
+                }
+                match outcome {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => {
+                        error!(actor = self.id.to_string(), "handler failed: {:#}", error);
+                        let kind = match error.downcast_ref::<MessageError>() {
+                            Some(MessageError::HandlerTimeout) => FailureKind::Timeout,
+                            _ => FailureKind::HandlerError,
+                        };
+                        self.report_failure(&error, kind).await;
+                        (self.on_error)(self, &error, &envelope).await;
+                    }
+                    Err(panic) => {
+                        let error = anyhow!("handler panicked: {}", panic_message(&*panic));
+                        error!(actor = self.id.to_string(), "{}", error);
+                        self.report_failure(&error, FailureKind::Panic).await;
+                        (self.on_error)(self, &error, &envelope).await;
+                        let recovers = self.panic_recovery && self.supervision == SupervisionStrategy::Stop;
+                        if !recovers && !terminate_requested {
+                            terminate_requested = self.fail(Arc::new(error)).await;
+                        }
+                    }
+                }
+            } else if let Some(hook) = self.reconfigure_hooks.get(&type_id).cloned() {
+                trace!(actor = self.id.to_string(), "Reconfiguring with {:?}", envelope.message);
+                hook(self, (*envelope.message).as_any());
+            } else if let Some(signal) = (*envelope.message).as_any().downcast_ref::<SystemSignal>() {
+                match signal {
+                    SystemSignal::Terminate => {
+                        // Set the termination flag
+                        terminate_requested = true;
+                        // Anything stashed while suspended still gets handled before stopping
+                        self.handle.activity.set_suspended(false);
+                        trace!("Termination signal received, waiting for remaining messages...");
+                        self.begin_stop().await;
+                    }
+                    SystemSignal::Suspend => {
+                        trace!(actor = self.id.to_string(), "Suspending");
+                        self.handle.activity.set_suspended(true);
+                        // Unless a resume is already on its way, in which case the asks can wait
+                        if self.handle.activity.is_suspend_requested() {
+                            self.handle.abandon_asks();
+                        }
+                    }
+                    SystemSignal::Resume => {
+                        trace!(actor = self.id.to_string(), "Resuming");
+                        if !terminate_requested {
+                            self.handle.renew_interrupt();
+                        }
+                        self.handle.activity.set_suspended(false);
+                    }
+                    SystemSignal::Watch(watcher) => {
+                        trace!(watcher = watcher.id.to_string(), "Adding watcher");
+                        if !self.watchers.contains(watcher) {
+                            self.watchers.push((**watcher).clone());
+                        }
+                    }
+                    SystemSignal::Unwatch(watcher_id) => {
+                        trace!(watcher = watcher_id.to_string(), "Removing watcher");
+                        self.watchers.retain(|watcher| &watcher.id != watcher_id);
+                    }
+                    SystemSignal::ChildFailed(escalation) => {
+                        terminate_requested = self.child_failed(escalation, terminate_requested).await;
+                    }
+                    SystemSignal::Failed { child, error, strategy } => {
+                        let restarts = child_restarts.entry(child.id.clone()).or_default();
+                        // A stopping supervisor stops the child along with its others
+                        if terminate_requested {
+                            trace!(actor = self.id.to_string(), child = %child.id, "Stopping anyway, not restarting");
+                        } else if self.may_restart(&child.id, *strategy, restarts) {
+                            self.handle
+                                .create_envelope(Some(child.signal_address()))
+                                .send(SystemSignal::Recreate)
+                                .await;
+                        } else {
+                            child_restarts.remove(&child.id);
+                            // Not waited on: the child may need this agent to finish stopping
+                            let _ = child.terminate();
+                            let escalation = ChildEscalation { ern: child.id.clone(), error: error.clone() };
+                            terminate_requested = self.child_failed(&escalation, terminate_requested).await;
+                        }
+                    }
+                    SystemSignal::Recreate => {
+                        if terminate_requested {
+                            trace!(actor = self.id.to_string(), "Stopping anyway, not restarting");
+                        } else {
+                            self.restart().await;
+                        }
+                    }
+                }
+            } else {
+                self.forward_dead_letter(envelope, DeadLetterReason::NoHandler).await;
+            }
+            for type_id in self.spent_reactors.drain(..) {
+                reactors.remove(&type_id);
+            }
+            // A dispatched handler counts its message once its future finishes
+            if !dispatched {
+                self.handle.outbox.count_handled();
+            }
+            self.check_readiness();
+            if terminate_requested && self.drained() {
+                self.close_inboxes();
+                let span = span_at!(self.trace_level, "terminate", actor = %self.id);
+                self.terminate().instrument(span).await;
                 break;
             }
         }
+        self.finish_stop().await;
+    }
+
+    /// Stops the agent after a panic outside its handlers, such as in a lifecycle hook,
+    /// with the cleanup of a normal stop. The messages still waiting are dropped rather than
+    /// handled by an agent in an unknown state.
+    pub(crate) async fn stop_after_panic(&mut self, panic: &str) {
+        let error = anyhow!("panicked: {}", panic);
+        error!(actor = self.id.to_string(), "{}", error);
+        self.report_failure(&error, FailureKind::Panic).await;
+        self.begin_stop().await;
+        self.escalate(Arc::new(error)).await;
+        self.drop_pending();
+        self.terminate().await;
+        self.finish_stop().await;
+    }
+
+    /// Runs once the agent has stopped handling messages: it leaves the runtime's lookups,
+    /// runs `after_stop` and announces the stop.
+    pub(crate) async fn finish_stop(&mut self) {
+        // Gone from lookups before anyone waiting on the stop hears it finished
+        self.runtime.0.registry.remove(&self.id);
+        self.runtime.0.roots.remove(&self.id);
+        self.runtime.0.dependencies.remove(&self.id);
 
         (self.after_stop)(self).await;
+        self.publish_event(SystemEvent::AgentStopped { id: self.id.clone(), at: SystemTime::now() }).await;
+    }
+
+    /// Handles a child's escalated failure with the `on_child_failure` hook, returning
+    /// whether this agent is now stopping.
+    async fn child_failed(&mut self, escalation: &ChildEscalation, terminate_requested: bool) -> bool {
+        warn!(actor = self.id.to_string(), child = %escalation.ern, "Child failed: {:#}", escalation.error);
+        let action = match self.on_child_failure.clone() {
+            Some(hook) => hook(self, escalation),
+            None => EscalationAction::Ignore,
+        };
+        // Already on its way out, so there is nothing more to decide
+        if terminate_requested {
+            trace!(actor = self.id.to_string(), "Stopping anyway, ignoring {:?}", action);
+            return true;
+        }
+        match action {
+            EscalationAction::Ignore => false,
+            EscalationAction::Restart => self.fail(escalation.error.clone()).await,
+            EscalationAction::Stop => {
+                self.begin_stop().await;
+                true
+            }
+            EscalationAction::Escalate => {
+                self.begin_stop().await;
+                self.escalate(escalation.error.clone()).await;
+                true
+            }
+        }
+    }
+
+    /// Ends the agent's current incarnation after `error`, returning whether it is now
+    /// stopping.
+    ///
+    /// Under `SupervisionStrategy::Restart`, the agent asks its parent whether to restart
+    /// and takes only signals until it hears back. Otherwise, or with no parent to ask, it
+    /// stops and escalates the failure.
+    async fn fail(&mut self, error: Arc<anyhow::Error>) -> bool {
+        if let (SupervisionStrategy::Restart { .. }, Some(parent)) = (self.supervision, &self.parent) {
+            let envelope = self.handle.create_envelope(Some(parent.signal_address()));
+            let failed = SystemSignal::Failed {
+                child: Box::new(self.handle.clone()),
+                error: error.clone(),
+                strategy: self.supervision,
+            };
+            // Messages wait in the mailbox until the parent decides
+            self.handle.activity.set_suspended(true);
+            if envelope.deliver(Arc::new(failed)).await.is_ok() {
+                return false;
+            }
+            self.handle.activity.set_suspended(false);
+            warn!(actor = self.id.to_string(), "Parent has stopped and can't restart it, stopping");
+        } else if self.supervision != SupervisionStrategy::Stop {
+            warn!(actor = self.id.to_string(), "No parent to restart it, stopping");
+        }
+        self.begin_stop().await;
+        self.escalate(error).await;
+        true
+    }
+
+    /// Decides whether `child` may restart under `strategy`, counting the restarts it already
+    /// had within the strategy's window.
+    fn may_restart(&self, child: &Ern, strategy: SupervisionStrategy, restarts: &mut VecDeque<Instant>) -> bool {
+        let SupervisionStrategy::Restart { max_retries, within } = strategy else {
+            return false;
+        };
+        let now = Instant::now();
+        while restarts.front().is_some_and(|restarted| now.duration_since(*restarted) > within) {
+            restarts.pop_front();
+        }
+        if restarts.len() >= max_retries {
+            error!(
+                actor = self.id.to_string(),
+                %child,
+                "Child exceeded {} restarts within {:?}, stopping it",
+                max_retries,
+                within
+            );
+            return false;
+        }
+        restarts.push_back(now);
+        warn!(
+            actor = self.id.to_string(),
+            %child,
+            "Restarting child after failure ({} of {})",
+            restarts.len(),
+            max_retries
+        );
+        true
+    }
+
+    /// Tells the agent's parent that it failed and is stopping, for the parent's
+    /// `on_child_failure` hook.
+    fn escalate(&self, error: Arc<anyhow::Error>) -> impl Future<Output = ()> + Send + 'static {
+        let envelope = self.parent.as_ref().map(|parent| self.handle.create_envelope(Some(parent.signal_address())));
+        if envelope.is_none() {
+            warn!(actor = %self.id, "No supervisor to escalate the failure to");
+        }
+        send_owned(envelope, SystemSignal::ChildFailed(ChildEscalation { ern: self.id.clone(), error }))
+    }
+
+    /// Returns an envelope to the agent's parent for a [`FailureReport`], if the parent
+    /// handles them.
+    fn failure_envelope(&self) -> Option<OutboundEnvelope> {
+        self.parent
+            .as_ref()
+            .filter(|parent| parent.handled_types.contains_key(&TypeId::of::<FailureReport>()))
+            .map(|parent| self.handle.create_envelope(Some(parent.reply_address())))
+    }
+
+    /// Sends the agent's parent a [`FailureReport`], if the parent handles them.
+    fn report_failure(&self, error: &anyhow::Error, kind: FailureKind) -> impl Future<Output = ()> + Send + 'static {
+        let report = FailureReport { child: self.id.clone(), error: format!("{:#}", error), kind };
+        send_owned(self.failure_envelope(), report)
+    }
+
+    /// Updates the readiness the handle reports, if the agent has a readiness check.
+    fn check_readiness(&self) {
+        if let Some(check) = &self.readiness {
+            self.handle.activity.set_ready(check(&self.model));
+        }
+    }
+
+    /// Broadcasts a lifecycle event through the broker.
+    ///
+    /// Skipped when there is no broker to send to: for the broker itself, and once the
+    /// broker has stopped during shutdown.
+    fn publish_event(&self, event: SystemEvent) -> impl Future<Output = ()> + Send + 'static {
+        let broker = &self.broker;
+        let envelope = if broker.outbox.is_closed() {
+            trace!("No broker for {:?}", event);
+            None
+        } else {
+            // From the broker itself, as `Broker::broadcast` on it sends, so there's no publisher
+            Some(broker.create_envelope(Some(broker.reply_address())))
+        };
+        send_owned(envelope, BrokerRequest::new(event))
+    }
+    /// Takes the next envelope, preferring the high-priority mailbox when there is one.
+    ///
+    /// System signals are taken in turn with the messages sent before them, or sooner if
+    /// the regular mailboxes hold a flood of messages; see `SignalQueue`.
+    /// Returns `None` once the mailboxes are closed and drained.
+    ///
+    /// A suspended agent takes only signals. Its messages wait in the mailboxes, so a bounded
+    /// mailbox fills up and holds its senders back until the agent resumes.
+    async fn next_envelope(&mut self) -> Option<Envelope> {
+        loop {
+            if self.handle.activity.is_suspended() {
+                if let Some(signal) = self.signals.next_due(false, false) {
+                    return Some(signal);
+                }
+                let signal = self.signals.recv().await?;
+                self.signals.hold(signal);
+                continue;
+            }
+            let messages_waiting = !self.inbox.is_empty()
+                || self.priority_inbox.as_ref().is_some_and(|inbox| !inbox.is_empty())
+                || self.fair_queue.as_ref().is_some_and(|queue| !queue.is_empty());
+            if let Some(signal) = self.signals.next_due(messages_waiting, self.drops_pending()) {
+                return Some(signal);
+            }
+            if let Some(envelope) = self.next_fair_envelope() {
+                return Some(envelope);
+            }
+            let priority = async {
+                match self.priority_inbox.as_mut() {
+                    Some(priority_inbox) => priority_inbox.recv().await,
+                    None => None,
+                }
+            };
+            let envelope = tokio::select! {
+                biased;
+                // Weighed against the messages sent before it on the next pass
+                Some(signal) = self.signals.recv(), if !self.signals.is_holding() => {
+                    self.signals.hold(signal);
+                    continue;
+                }
+                Some(envelope) = priority => Some(envelope),
+                envelope = self.inbox.recv() => envelope,
+            };
+            self.signals.record_message();
+            if self.inbox.is_empty() {
+                // Caught up, so a full mailbox from here on is a new overload
+                self.handle.outbox.clear_overflow();
+            }
+            return envelope;
+        }
+    }
+
+    /// With weighted message types, takes what has arrived in the inbox into the fair queue
+    /// and returns the next envelope from it, unless a high-priority one is waiting.
+    ///
+    /// The fair queue holds at most a mailbox's worth, so senders to a bounded mailbox still
+    /// wait once the agent falls that far behind.
+    fn next_fair_envelope(&mut self) -> Option<Envelope> {
+        let fair_queue = self.fair_queue.as_mut()?;
+        let capacity = self.handle.outbox.max_capacity();
+        while fair_queue.len() < capacity {
+            let Some(envelope) = self.inbox.try_recv() else { break };
+            self.signals.record_message();
+            fair_queue.push(carried_type(&envelope), envelope);
+        }
+        if fair_queue.is_empty() {
+            return None;
+        }
+        if self.inbox.is_empty() {
+            // Caught up, so a full mailbox from here on is a new overload
+            self.handle.outbox.clear_overflow();
+        }
+        if let Some(envelope) = self.priority_inbox.as_mut().and_then(Inbox::try_recv) {
+            self.signals.record_message();
+            return Some(envelope);
+        }
+        fair_queue.pop()
+    }
+
+    /// Runs a handler's future to completion. If the handler is waiting on
+    /// [`ManagedAgent::receive`], messages are taken meanwhile until one it wants arrives,
+    /// and the rest are put back to be handled next, in order.
+    async fn run_handler<T>(&mut self, handler: impl Future<Output = T>) -> T {
+        let Some((type_id, reply)) = self.receiving.take() else {
+            return handler.await;
+        };
+        tokio::pin!(handler);
+        // Messages already taken from the mailbox are ahead of the ones still in it
+        if let Some(position) = self.pending.iter().position(|envelope| carried_type(envelope) == type_id) {
+            if let Some(envelope) = self.pending.remove(position) {
+                self.handle.outbox.count_handled();
+                let _ = reply.send(carried_message(&envelope).clone());
+            }
+            return handler.await;
+        }
+        let mut waiting = Some(reply);
+        let mut set_aside = VecDeque::new();
+        let output = loop {
+            let Some(reply) = waiting.as_mut() else {
+                break handler.await;
+            };
+            tokio::select! {
+                biased;
+                output = &mut handler => break output,
+                // The handler gave up on the wait
+                () = reply.closed() => waiting = None,
+                envelope = self.next_envelope() => match envelope {
+                    Some(envelope) if carried_type(&envelope) == type_id => {
+                        self.handle.activity.mark_message();
+                        self.handle.outbox.count_handled();
+                        if let Some(reply) = waiting.take() {
+                            let _ = reply.send(carried_message(&envelope).clone());
+                        }
+                    }
+                    Some(envelope) => set_aside.push_back(envelope),
+                    // Dropping the sender tells the handler nothing more is coming
+                    None => waiting = None,
+                },
+            }
+        };
+        trace!(actor = self.id.to_string(), "Putting back {} messages set aside by receive", set_aside.len());
+        while let Some(envelope) = set_aside.pop_back() {
+            self.pending.push_front(envelope);
+        }
+        output
     }
-    #[instrument(skip(self))]
+
+    /// Runs a handler's future on the agent's task tracker, alongside any others already
+    /// running, once fewer than `parallelism` are. The message counts as handled when the
+    /// future finishes.
+    async fn dispatch(&mut self, handler: FutureBox, type_id: TypeId, envelope: &Envelope) {
+        let Ok(slot) = self.handler_slots.clone().acquire_owned().await else {
+            // The slots are never closed; run it here if they somehow are
+            return handler.await;
+        };
+        let id = self.id.clone();
+        let outbox = self.handle.outbox.clone();
+        let metrics = self.handle.metrics.clone();
+        let type_name = (*envelope.message).type_name();
+        let failure_envelope = self.failure_envelope();
+        self.handle.spawn(
+            async move {
+                let started = Instant::now();
+                if let Err(panic) = AssertUnwindSafe(handler).catch_unwind().await {
+                    let error = format!("handler panicked: {}", panic_message(&*panic));
+                    error!(actor = id.to_string(), "{}", error);
+                    if let Some(envelope) = failure_envelope {
+                        envelope.send(FailureReport { child: id.clone(), error, kind: FailureKind::Panic }).await;
+                    }
+                }
+                if let Some(metrics) = metrics {
+                    metrics.record(type_id, type_name, started.elapsed());
+                }
+                outbox.count_handled();
+                drop(slot);
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Waits for the next envelope like `next_envelope`, failing once the agent has gone its idle
+    /// timeout without one. There is no timeout while the agent is already stopping.
+    async fn receive_until_idle(&mut self, stopping: bool) -> Result<Option<Envelope>, Elapsed> {
+        match self.idle_timeout {
+            // Receiving is cancel safe, so a message racing the timeout stays in the inbox
+            Some(idle_timeout) if !stopping => timeout(idle_timeout, self.next_envelope()).await,
+            _ => Ok(self.next_envelope().await),
+        }
+    }
+
+    /// Whether a stopping agent has handled everything left in its inboxes.
+    fn drained(&self) -> bool {
+        self.pending.is_empty()
+            && self.fair_queue.as_ref().is_none_or(FairQueue::is_empty)
+            && self.inbox.is_empty()
+            && self.inbox.is_closed()
+            && self.signals.is_empty()
+            && self.priority_inbox.as_ref().is_none_or(|inbox| inbox.is_empty())
+    }
+
+    /// Whether the agent drops its waiting messages when told to stop, rather than handling
+    /// them. Draining always handles them.
+    fn drops_pending(&self) -> bool {
+        self.terminate_policy == TerminatePolicy::DropPending && !self.handle.outbox.is_draining()
+    }
+
+    /// Drops every message waiting to be handled, under `TerminatePolicy::DropPending`.
+    fn drop_pending(&mut self) {
+        let mut dropped = std::mem::take(&mut self.pending);
+        if let Some(fair_queue) = self.fair_queue.as_mut() {
+            dropped.extend(fair_queue.take_all());
+        }
+        while let Some(envelope) = self.inbox.try_recv() {
+            dropped.push_back(envelope);
+        }
+        if let Some(priority_inbox) = self.priority_inbox.as_mut() {
+            while let Some(envelope) = priority_inbox.try_recv() {
+                dropped.push_back(envelope);
+            }
+        }
+        if dropped.is_empty() {
+            return;
+        }
+        warn!(actor = self.id.to_string(), "Stopping with {} messages waiting, dropping them", dropped.len());
+        for envelope in dropped {
+            if let Some(reply_channel) = &envelope.reply_channel {
+                abandon_ask(reply_channel);
+            }
+            self.handle.outbox.count_handled();
+        }
+    }
+
+    fn close_inboxes(&mut self) {
+        self.inbox.close();
+        self.signals.close();
+        if let Some(priority_inbox) = self.priority_inbox.as_mut() {
+            priority_inbox.close();
+        }
+    }
+
+    /// Sends an envelope nobody could handle to the runtime's dead-letter agent.
+    async fn forward_dead_letter(&mut self, mut envelope: Envelope, reason: DeadLetterReason) {
+        let dead_letters = &self.runtime.0.dead_letters;
+        // A dead letter that can't be handled would only be dead-lettered again
+        if (*envelope.message).as_any().is::<DeadLetter>() || dead_letters.outbox.is_closed() {
+            trace!(actor = self.id.to_string(), "Dropping unhandled {:?}", envelope.message);
+            return;
+        }
+        // Drop any ask reply or stream channel so the asker isn't left waiting forever
+        envelope.reply_channel = None;
+        envelope.stream_channel = None;
+        trace!(actor = self.id.to_string(), "Forwarding dead letter {:?}", envelope.message);
+        self.handle
+            .create_envelope(Some(dead_letters.reply_address()))
+            .send(DeadLetter { envelope, reason })
+            .await;
+    }
+
+    /// Runs the interceptors over a message, returning the first decision that isn't
+    /// `Continue`. System signals always continue.
+    fn intercept(&mut self, envelope: &Envelope) -> InterceptDecision {
+        if (*envelope.message).as_any().is::<SystemSignal>() {
+            return InterceptDecision::Continue;
+        }
+        self.interceptors
+            .iter()
+            .map(|interceptor| interceptor.decide(envelope, &mut self.context_data))
+            .find(|decision| *decision != InterceptDecision::Continue)
+            .unwrap_or(InterceptDecision::Continue)
+    }
+
+    /// Waits until the agent's rate limit lets a message through. Returns `false` if the
+    /// message is refused instead, under `RateLimitPolicy::DeadLetter`. System signals are
+    /// always let through.
+    async fn admit(&mut self, envelope: &Envelope) -> bool {
+        if (*envelope.message).as_any().is::<SystemSignal>() {
+            return true;
+        }
+        let Some(limiter) = self.rate_limiter.as_mut() else {
+            return true;
+        };
+        loop {
+            match limiter.try_acquire() {
+                Ok(()) => return true,
+                Err(_) if limiter.policy() == RateLimitPolicy::DeadLetter => return false,
+                Err(wait) => sleep(wait).await,
+            }
+        }
+    }
+
+    /// Runs `before_stop` and closes the inbox so the remaining messages can drain.
+    async fn begin_stop(&mut self) {
+        // Stops that don't come through `stop`, such as an idle timeout, interrupt handlers here
+        self.handle.interrupt();
+        let started = Instant::now();
+        if timeout(self.shutdown_timeout, (self.before_stop)(self)).await.is_err() {
+            error!(
+                actor = self.id.to_string(),
+                "before_stop timed out after {:?}, stopping anyway",
+                started.elapsed()
+            );
+        }
+        //give the before_stop a chance to process the termination signal
+        sleep(Duration::from_millis(10)).await;
+        self.close_inboxes();
+        if self.drops_pending() {
+            self.drop_pending();
+        }
+        // Stop any timers still scheduled for this actor
+        self.handle.cancellation_token.cancel();
+    }
+
+    /// Starts the agent over once its parent has agreed to restart it, keeping its handle
+    /// and the messages waiting in its mailbox.
+    ///
+    /// The restarted agent starts over as it was started: a default model, its original
+    /// handlers, empty context data, and no stashed messages.
+    async fn restart(&mut self) {
+        warn!(actor = self.id.to_string(), "Restarting");
+        self.model = Agent::default();
+        // Nothing the failed incarnation set aside carries over to the fresh one
+        if !self.stash.is_empty() || !self.pending.is_empty() {
+            warn!(
+                actor = self.id.to_string(),
+                "Restarting with {} set-aside messages, dropping them",
+                self.stash.len() + self.pending.len()
+            );
+        }
+        self.stash.clear();
+        self.pending.clear();
+        self.context_data = TypeMap::default();
+        self.recover();
+        // Go back to the handlers the agent was started with
+        if !self.behaviors.is_empty() {
+            self.reactors = self.behaviors.swap_remove(0);
+            self.behaviors.clear();
+        }
+        self.handle.activity.set_suspended(false);
+        (self.after_start)(self).await;
+        self.publish_event(SystemEvent::AgentRestarted { id: self.id.clone(), at: SystemTime::now() }).await;
+    }
+
+    /// Stops the agent's children, tells its watchers and closes its mailbox.
     async fn terminate(&mut self) {
+        // Handlers still running alongside each other finish before anything stops
+        if self.parallelism > 1 {
+            let _ = self.handler_slots.acquire_many(self.parallelism as u32).await;
+        }
+        if !self.stash.is_empty() {
+            warn!(actor = self.id.to_string(), "Stopping with {} stashed messages, dropping them", self.stash.len());
+        }
 
         // Collect suspend futures for all children
-        let suspend_futures: Vec<_> = self.handle.children().iter().map(|item| {
-            let child_ref = item.value().clone(); // Clone to take ownership
+        let suspend_futures: Vec<_> = self.handle.children().into_iter().map(|(_, child_ref)| {
             async move {
                 let _ = child_ref.stop().await;
             }
@@ -115,10 +984,78 @@ impl<Agent: Default + Send + Debug + 'static> ManagedAgent<Started, Agent> {
         join_all(suspend_futures).await;
 
         trace!(
-        actor = self.id.to_string(),
-        "All subordinates terminated. Closing mailbox for"
-    );
+            actor = self.id.to_string(),
+            "All subordinates terminated. Closing mailbox for"
+        );
 
-        self.inbox.close();
+        // Let watchers know this actor is gone
+        let notify_futures: Vec<_> = self.watchers.iter().map(|watcher| {
+            let envelope = self.handle.create_envelope(Some(watcher.reply_address()));
+            let who = self.id.clone();
+            async move {
+                envelope.send(Terminated { who }).await;
+            }
+        }).collect();
+        join_all(notify_futures).await;
+
+        // Free this agent's place under its parent's child limit
+        if let Some(parent) = &self.parent {
+            parent.remove_child(&self.id);
+        }
+
+        self.close_inboxes();
+        // Anything still holding an ask's reply channel, such as a task a handler spawned,
+        // won't get the chance to answer it
+        self.handle.abandon_asks();
+    }
+}
+
+impl<Agent: Persistent + Default + Send + Debug + 'static> ManagedAgent<Started, Agent> {
+    /// Records an event in the agent's journal, then applies it to the model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the model unchanged, if the agent has no journal or the
+    /// journal fails to record the event.
+    pub fn persist(&mut self, event: Agent::Event) -> anyhow::Result<()> {
+        let binding = self
+            .journal
+            .as_ref()
+            .and_then(|journal| journal.downcast_ref::<JournalBinding<Agent>>())
+            .ok_or_else(|| anyhow!("{} has no journal; add one with with_journal", self.id))?;
+        binding.persist(&mut self.model, event)
+    }
+}
+
+/// Returns the message an envelope carries, looking inside a broker broadcast.
+fn carried_message(envelope: &Envelope) -> &Arc<dyn ActonMessage + Send + Sync + 'static> {
+    match (*envelope.message).as_any().downcast_ref::<BrokerRequestEnvelope>() {
+        Some(broadcast) => &broadcast.message,
+        None => &envelope.message,
+    }
+}
+
+/// Returns the type of the message an envelope carries, looking inside a broker broadcast.
+fn carried_type(envelope: &Envelope) -> TypeId {
+    (**carried_message(envelope)).as_any().type_id()
+}
+
+/// Sends `message` with `envelope`, if there is one.
+///
+/// The future owns both, so an agent that starts a send this way isn't held across it.
+async fn send_owned(envelope: Option<OutboundEnvelope>, message: impl ActonMessage + 'static) {
+    if let Some(envelope) = envelope {
+        envelope.send(message).await;
+    }
+}
+
+/// Extracts the message from a panic payload for logging.
+pub(crate) fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
     }
 }
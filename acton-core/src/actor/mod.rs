@@ -15,10 +15,41 @@
  */
 
 pub use agent_config::AgentConfig;
+pub use behavior::Behavior;
+pub use escalation_action::EscalationAction;
+pub(crate) use dedup_window::DedupWindow;
+pub(crate) use fair_queue::FairQueue;
+pub(crate) use signal_queue::SignalQueue;
+pub(crate) use agent_config::{
+    DEFAULT_MAILBOX_CAPACITY, DEFAULT_OUTPUT_CAPACITY, DEFAULT_SHUTDOWN_TIMEOUT, DEFAULT_SIGNAL_CHECK_INTERVAL,
+    DEFAULT_SNAPSHOT_INTERVAL, DEFAULT_TRACE_LEVEL,
+};
 pub use managed_agent::started::Started;
 pub use managed_agent::Idle;
 pub use managed_agent::ManagedAgent;
+pub use on_timeout::OnTimeout;
+pub use rate_limiter::RateLimitPolicy;
+pub(crate) use rate_limiter::RateLimiter;
+#[cfg(feature = "serde")]
+pub use file_journal::FileJournal;
+pub use persistent::{EventJournal, MemoryJournal, MemorySnapshotStore, Persistent, Snapshot, SnapshotStore};
+pub use retry_policy::{Backoff, RetryPolicy};
+pub use supervision_strategy::SupervisionStrategy;
+pub use terminate_policy::TerminatePolicy;
 
 mod managed_agent;
 
 mod agent_config;
+mod behavior;
+mod dedup_window;
+mod escalation_action;
+mod fair_queue;
+mod on_timeout;
+#[cfg(feature = "serde")]
+mod file_journal;
+pub(crate) mod persistent;
+mod rate_limiter;
+mod retry_policy;
+mod signal_queue;
+mod supervision_strategy;
+mod terminate_policy;
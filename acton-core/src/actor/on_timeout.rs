@@ -0,0 +1,29 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+/// What happens to a handler's future when it runs past the timeout given to
+/// `act_on_with_timeout`.
+///
+/// Either way the agent moves on to its next message.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnTimeout {
+    /// The future is dropped, so its work stops at its current `.await`. This is the default.
+    #[default]
+    Cancel,
+    /// The future keeps running on its own task. It no longer holds up the agent's messages,
+    /// but the agent still waits for it when stopping.
+    Detach,
+}
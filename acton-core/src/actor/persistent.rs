@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tracing::{error, trace};
+
+/// State that is rebuilt from the events it has recorded, rather than stored directly.
+///
+/// Give the agent a journal with `ManagedAgent::with_journal`, and record events from its
+/// handlers with `ManagedAgent::persist`. When the agent starts, or restarts after a panic,
+/// its journaled events are replayed through [`Persistent::apply_event`].
+pub trait Persistent {
+    /// The events the state records.
+    type Event: Clone + Send + Sync + Debug + 'static;
+
+    /// Updates the state with an event, whether newly recorded or replayed.
+    fn apply_event(&mut self, event: &Self::Event);
+}
+
+/// A [`Persistent`] state that can be saved whole, so recovery can start from a snapshot
+/// instead of replaying every event.
+///
+/// Give the agent a store with `ManagedAgent::with_snapshots`.
+pub trait Snapshot: Persistent + Sized {
+    /// Serializes the state.
+    fn to_snapshot(&self) -> anyhow::Result<Vec<u8>>;
+
+    /// Rebuilds the state from the bytes of [`Snapshot::to_snapshot`].
+    fn from_snapshot(bytes: &[u8]) -> anyhow::Result<Self>;
+}
+
+/// Where a [`Persistent`] agent's events are recorded, keyed by persistence id.
+///
+/// Events are numbered from 1 in the order they were appended.
+///
+/// Journals are called from within handlers, so they should be quick; slow storage is best
+/// put behind a buffer.
+pub trait EventJournal<E>: Send + Sync + Debug {
+    /// Records an event at the end of the journal for `persistence_id`.
+    fn append(&self, persistence_id: &str, event: &E) -> anyhow::Result<()>;
+
+    /// Returns every event recorded for `persistence_id`, oldest first.
+    fn replay(&self, persistence_id: &str) -> anyhow::Result<Vec<E>>;
+
+    /// Returns the events recorded for `persistence_id` after the first `sequence`, oldest
+    /// first.
+    ///
+    /// The default reads the whole journal and drops the start; override it when the
+    /// storage can skip ahead.
+    fn replay_after(&self, persistence_id: &str, sequence: u64) -> anyhow::Result<Vec<E>> {
+        Ok(self.replay(persistence_id)?.into_iter().skip(sequence as usize).collect())
+    }
+}
+
+/// Where a [`Snapshot`] agent's snapshots are kept, keyed by persistence id.
+pub trait SnapshotStore: Send + Sync + Debug {
+    /// Saves a snapshot of the state as of event number `sequence`.
+    fn save(&self, persistence_id: &str, state: Vec<u8>, sequence: u64) -> anyhow::Result<()>;
+
+    /// Returns the most recent snapshot for `persistence_id` and its sequence number, if
+    /// there is one.
+    fn load_latest(&self, persistence_id: &str) -> anyhow::Result<Option<(Vec<u8>, u64)>>;
+}
+
+/// An [`EventJournal`] kept in memory, for tests and for state that only needs to survive
+/// a restart of the agent, not of the process.
+pub struct MemoryJournal<E> {
+    events: Mutex<HashMap<String, Vec<E>>>,
+}
+
+impl<E> MemoryJournal<E> {
+    /// Creates an empty journal.
+    pub fn new() -> Self {
+        MemoryJournal { events: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<E> Default for MemoryJournal<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Debug for MemoryJournal<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryJournal").finish_non_exhaustive()
+    }
+}
+
+impl<E: Clone + Send + Sync> EventJournal<E> for MemoryJournal<E> {
+    fn append(&self, persistence_id: &str, event: &E) -> anyhow::Result<()> {
+        let mut events = self.events.lock().map_err(|_| anyhow::anyhow!("journal lock poisoned"))?;
+        events.entry(persistence_id.to_string()).or_default().push(event.clone());
+        Ok(())
+    }
+
+    fn replay(&self, persistence_id: &str) -> anyhow::Result<Vec<E>> {
+        self.replay_after(persistence_id, 0)
+    }
+
+    fn replay_after(&self, persistence_id: &str, sequence: u64) -> anyhow::Result<Vec<E>> {
+        let events = self.events.lock().map_err(|_| anyhow::anyhow!("journal lock poisoned"))?;
+        let recorded = events.get(persistence_id).map(Vec::as_slice).unwrap_or_default();
+        Ok(recorded.iter().skip(sequence as usize).cloned().collect())
+    }
+}
+
+/// A [`SnapshotStore`] kept in memory, holding only the latest snapshot for each id.
+#[derive(Debug, Default)]
+pub struct MemorySnapshotStore {
+    snapshots: Mutex<HashMap<String, (Vec<u8>, u64)>>,
+}
+
+impl MemorySnapshotStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SnapshotStore for MemorySnapshotStore {
+    fn save(&self, persistence_id: &str, state: Vec<u8>, sequence: u64) -> anyhow::Result<()> {
+        let mut snapshots = self.snapshots.lock().map_err(|_| anyhow::anyhow!("snapshot lock poisoned"))?;
+        snapshots.insert(persistence_id.to_string(), (state, sequence));
+        Ok(())
+    }
+
+    fn load_latest(&self, persistence_id: &str) -> anyhow::Result<Option<(Vec<u8>, u64)>> {
+        let snapshots = self.snapshots.lock().map_err(|_| anyhow::anyhow!("snapshot lock poisoned"))?;
+        Ok(snapshots.get(persistence_id).cloned())
+    }
+}
+
+/// An agent's journal and the id its events are recorded under.
+pub(crate) struct JournalBinding<State: Persistent> {
+    pub(crate) persistence_id: String,
+    pub(crate) journal: Arc<dyn EventJournal<State::Event>>,
+    /// The number of events recorded so far.
+    pub(crate) sequence: AtomicU64,
+    pub(crate) snapshots: Option<SnapshotBinding<State>>,
+}
+
+/// Where an agent's snapshots go, and how to make them.
+pub(crate) struct SnapshotBinding<State> {
+    pub(crate) store: Arc<dyn SnapshotStore>,
+    /// Save a snapshot every time this many more events have been recorded.
+    pub(crate) every: u64,
+    pub(crate) save: fn(&State) -> anyhow::Result<Vec<u8>>,
+    pub(crate) load: fn(&[u8]) -> anyhow::Result<State>,
+}
+
+impl<State: Persistent> JournalBinding<State> {
+    /// Rebuilds `model` from the latest snapshot, if any, and the events recorded after it.
+    pub(crate) fn recover(&self, model: &mut State) -> anyhow::Result<()> {
+        let mut sequence = 0;
+        if let Some(snapshots) = &self.snapshots {
+            if let Some((bytes, snapshot_sequence)) = snapshots.store.load_latest(&self.persistence_id)? {
+                *model = (snapshots.load)(&bytes)?;
+                sequence = snapshot_sequence;
+            }
+        }
+        let events = self.journal.replay_after(&self.persistence_id, sequence)?;
+        trace!(persistence_id = self.persistence_id, "Replaying {} events after {}", events.len(), sequence);
+        for event in &events {
+            model.apply_event(event);
+        }
+        self.sequence.store(sequence + events.len() as u64, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Records an event, applies it to `model`, and takes a snapshot when one is due.
+    pub(crate) fn persist(&self, model: &mut State, event: State::Event) -> anyhow::Result<()> {
+        self.journal.append(&self.persistence_id, &event)?;
+        model.apply_event(&event);
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(snapshots) = self.snapshots.as_ref().filter(|snapshots| sequence.is_multiple_of(snapshots.every)) {
+            // The event is already recorded, so a failed snapshot only makes recovery slower
+            if let Err(e) = (snapshots.save)(model)
+                .and_then(|state| snapshots.store.save(&self.persistence_id, state, sequence))
+            {
+                error!(persistence_id = self.persistence_id, "Failed to save a snapshot: {:#}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replays the agent's `JournalBinding`, kept type-erased on the agent, into its model.
+pub(crate) fn recover_from_journal<State: Persistent + 'static>(
+    journal: &(dyn Any + Send + Sync),
+    model: &mut State,
+) -> anyhow::Result<()> {
+    journal
+        .downcast_ref::<JournalBinding<State>>()
+        .ok_or_else(|| anyhow::anyhow!("the journal doesn't match the agent's state"))?
+        .recover(model)
+}
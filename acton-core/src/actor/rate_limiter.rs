@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// What an agent configured with `AgentConfig::with_rate_limit` does with a message that
+/// arrives faster than its rate allows.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitPolicy {
+    /// The agent waits until the message is within the rate, then handles it. This is the
+    /// default. Only this agent waits; the rest of the runtime carries on.
+    #[default]
+    Delay,
+    /// The message goes to the dead-letter agent with `DeadLetterReason::RateLimited`.
+    DeadLetter,
+}
+
+/// A token bucket that lets an agent handle a steady rate of messages, with bursts.
+///
+/// The bucket holds up to `burst` tokens and refills at `rate` per second, going by the time
+/// that has passed since it was last used.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    refilled: Instant,
+    policy: RateLimitPolicy,
+}
+
+impl RateLimiter {
+    /// Creates a full bucket; a rate or burst of zero is treated as one.
+    pub(crate) fn new(permits_per_sec: u32, burst: u32, policy: RateLimitPolicy) -> Self {
+        let burst = f64::from(burst.max(1));
+        RateLimiter {
+            rate: f64::from(permits_per_sec.max(1)),
+            burst,
+            tokens: burst,
+            refilled: Instant::now(),
+            policy,
+        }
+    }
+
+    pub(crate) fn policy(&self) -> RateLimitPolicy {
+        self.policy
+    }
+
+    /// Takes a token if there is one, or returns how long until there will be.
+    pub(crate) fn try_acquire(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let refill = now.duration_since(self.refilled).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.burst);
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
@@ -0,0 +1,83 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::time::Duration;
+
+/// How a handler added with `act_on_with_retry` is retried when it returns an error.
+///
+/// Each message is retried on its own schedule; once `max_attempts` attempts have failed,
+/// the message is sent to the dead-letter agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The total number of times the handler is run for a message, including the first.
+    pub max_attempts: usize,
+    /// How long to wait before each retry.
+    pub backoff: Backoff,
+}
+
+/// The wait between attempts of a [`RetryPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Waits the same time before every retry.
+    Fixed(Duration),
+    /// Doubles the wait after every failed attempt, starting at `initial` and never
+    /// exceeding `max`.
+    Exponential {
+        /// The wait before the first retry.
+        initial: Duration,
+        /// The longest wait between attempts.
+        max: Duration,
+    },
+}
+
+impl RetryPolicy {
+    /// Retries up to `max_attempts` attempts in total, waiting `delay` before each retry.
+    pub fn fixed(max_attempts: usize, delay: Duration) -> Self {
+        RetryPolicy { max_attempts, backoff: Backoff::Fixed(delay) }
+    }
+
+    /// Retries up to `max_attempts` attempts in total, doubling the wait from `initial` up
+    /// to `max`.
+    pub fn exponential(max_attempts: usize, initial: Duration, max: Duration) -> Self {
+        RetryPolicy { max_attempts, backoff: Backoff::Exponential { initial, max } }
+    }
+
+    /// Returns how long to wait after the given failed attempt, counting from 1.
+    pub(crate) fn delay_after(&self, attempt: usize) -> Duration {
+        match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let doublings = attempt.saturating_sub(1).min(u32::MAX as usize) as u32;
+                initial
+                    .checked_mul(2u32.saturating_pow(doublings))
+                    .map_or(max, |delay| delay.min(max))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_delay() {
+        let policy = RetryPolicy::exponential(10, Duration::from_millis(10), Duration::from_millis(50));
+        let delays: Vec<_> = (1..=5).map(|attempt| policy.delay_after(attempt).as_millis()).collect();
+        assert_eq!(delays, vec![10, 20, 40, 50, 50]);
+        assert_eq!(policy.delay_after(usize::MAX), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,94 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use crate::common::{Envelope, Inbox};
+use crate::message::SystemSignal;
+
+/// The system signals sent to an agent, which travel apart from its regular messages.
+///
+/// A signal waits its turn behind the messages sent before it, but no longer than it takes
+/// the agent to handle `interval` of them, so a flood of messages can't hold up `stop` or
+/// `suspend`.
+#[derive(Debug)]
+pub(crate) struct SignalQueue {
+    inbox: Inbox,
+    /// The next signal, out of the inbox and waiting its turn.
+    held: Option<Envelope>,
+    /// How many envelopes the agent has taken from all of its mailboxes.
+    taken: u64,
+    /// How many regular messages the agent has taken since `held` was.
+    passed: usize,
+    interval: usize,
+}
+
+impl SignalQueue {
+    pub(crate) fn new(inbox: Inbox, interval: usize) -> Self {
+        SignalQueue { inbox, held: None, taken: 0, passed: 0, interval }
+    }
+
+    pub(crate) fn set_interval(&mut self, interval: usize) {
+        self.interval = interval;
+    }
+
+    /// Returns the next signal if its turn has come: every envelope sent to the agent before
+    /// it has been taken, no regular message is waiting, or `interval` have gone ahead of it.
+    /// With `terminate_first`, a `Terminate` signal doesn't wait its turn.
+    pub(crate) fn next_due(&mut self, messages_waiting: bool, terminate_first: bool) -> Option<Envelope> {
+        if self.held.is_none() {
+            self.held = self.inbox.try_recv();
+            self.passed = 0;
+        }
+        let signal = self.held.as_ref()?;
+        let jumps_queue = terminate_first
+            && matches!((*signal.message).as_any().downcast_ref::<SystemSignal>(), Some(SystemSignal::Terminate));
+        if self.taken >= signal.sequence || !messages_waiting || self.passed >= self.interval || jumps_queue {
+            self.taken += 1;
+            return self.held.take();
+        }
+        None
+    }
+
+    /// Waits for a signal to arrive, or returns `None` once the inbox is closed and empty.
+    pub(crate) async fn recv(&mut self) -> Option<Envelope> {
+        self.inbox.recv().await
+    }
+
+    /// Holds a signal that arrived while the agent was waiting, until its turn comes.
+    pub(crate) fn hold(&mut self, signal: Envelope) {
+        self.held = Some(signal);
+        self.passed = 0;
+    }
+
+    pub(crate) fn is_holding(&self) -> bool {
+        self.held.is_some()
+    }
+
+    /// Counts a regular message the agent took.
+    pub(crate) fn record_message(&mut self) {
+        self.taken += 1;
+        if self.held.is_some() {
+            self.passed += 1;
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.held.is_none() && self.inbox.is_empty()
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.inbox.close();
+    }
+}
@@ -0,0 +1,43 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::time::Duration;
+
+/// Decides what happens to an agent when one of its message handlers panics.
+///
+/// Set it with [`AgentConfig::with_supervision`](crate::actor::AgentConfig::with_supervision).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisionStrategy {
+    /// The agent stops, running its normal stop sequence, and escalates the failure to its
+    /// parent's `on_child_failure` hook, unless panic recovery is enabled with
+    /// `AgentConfig::with_panic_recovery`. This is the default.
+    #[default]
+    Stop,
+    /// The agent's parent restarts it. Until the parent decides, the agent takes no
+    /// messages; they wait in its mailbox and are handled after the restart. The restarted
+    /// agent keeps its handle, its model is reset to `Default::default()`, it goes back to
+    /// the reactors it was started with, and `after_start` runs again.
+    ///
+    /// The parent counts each child's restarts. If more than `max_retries` are needed
+    /// within `within`, it stops the child instead, as with `Stop`. A root agent has no
+    /// parent to restart it, so it stops.
+    Restart {
+        /// The number of restarts allowed within the window.
+        max_retries: usize,
+        /// The length of the window restarts are counted in.
+        within: Duration,
+    },
+}
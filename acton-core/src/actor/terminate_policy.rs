@@ -0,0 +1,30 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+/// What an agent configured with `AgentConfig::with_terminate_policy` does with the messages
+/// still waiting in its mailboxes when it is told to stop.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminatePolicy {
+    /// The agent handles every message sent before the stop, then stops. This is the default.
+    #[default]
+    DrainPending,
+    /// The agent stops as soon as it finishes the message it is handling, and drops the
+    /// messages still waiting, including any held while it was suspended. Callers waiting
+    /// on an `ask` among them get `MessageError::AgentStopped`.
+    ///
+    /// `AgentHandle::drain` still handles every waiting message, since that is what it is for.
+    DropPending,
+}
@@ -14,7 +14,7 @@
  * limitations under that License.
  */
 
-use crate::common::AgentRuntime;
+use crate::common::{AgentRuntime, BroadcastPolicy, Spawner, SpawnerRef};
 
 /// Represents the Acton system.
 ///
@@ -22,7 +22,12 @@ use crate::common::AgentRuntime;
 /// managing the initialization and coordination of various system components.
 /// It provides functionality to launch and prepare the system for operation.
 #[derive(Default, Debug, Clone)]
-pub struct ActonApp;
+pub struct ActonApp {
+    /// Runs the tasks of every agent in the runtime.
+    pub(crate) spawner: SpawnerRef,
+    /// What the broker does when a subscriber's mailbox is full.
+    pub(crate) broadcast_policy: BroadcastPolicy,
+}
 
 impl ActonApp {
     /// Launches the Acton system.
@@ -38,4 +43,21 @@ impl ActonApp {
         let system: ActonApp = Default::default();
         system.into()
     }
+
+    /// Launches the Acton system with a custom [`Spawner`] for its tasks.
+    ///
+    /// Every task the runtime starts, including the message loops of the broker and of
+    /// each agent, goes through `spawner` instead of straight to tokio.
+    pub fn launch_with_spawner(spawner: impl Spawner) -> AgentRuntime {
+        ActonApp { spawner: SpawnerRef::new(spawner), ..Default::default() }.into()
+    }
+
+    /// Launches the Acton system with a broker that handles full subscriber mailboxes
+    /// according to `policy`.
+    ///
+    /// With [`BroadcastPolicy::RetryThenDrop`], a subscriber that can't keep up loses the
+    /// broadcasts it has no room for, as dead letters, instead of holding up the broker.
+    pub fn launch_with_broadcast_policy(policy: BroadcastPolicy) -> AgentRuntime {
+        ActonApp { broadcast_policy: policy, ..Default::default() }.into()
+    }
 }
@@ -14,13 +14,26 @@
  * limitations under that License.
  */
 
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+
 use acton_ern::{Ern};
 use dashmap::DashMap;
 
-use crate::common::{AgentHandle, BrokerRef};
+use crate::common::{AgentHandle, BrokerRef, SpawnerRef};
 
 #[derive(Debug, Clone, Default)]
 pub(crate) struct ActonInner {
     pub(crate) broker: BrokerRef,
-    pub(crate) roots: DashMap<Ern, AgentHandle>,
+    pub(crate) dead_letters: AgentHandle,
+    /// The agents started without a parent; shared by every clone of the runtime.
+    pub(crate) roots: Arc<DashMap<Ern, AgentHandle>>,
+    /// Every started agent, including children, until it stops.
+    pub(crate) registry: Arc<DashMap<Ern, AgentHandle>>,
+    /// The agents each agent declared with `AgentConfig::depends_on`, until it stops.
+    pub(crate) dependencies: Arc<DashMap<Ern, Vec<Ern>>>,
+    /// Shared with the broker, which reads it for every broadcast.
+    pub(crate) broadcast_concurrency: Arc<AtomicUsize>,
+    /// Runs the tasks of every agent in the runtime.
+    pub(crate) spawner: SpawnerRef,
 }
@@ -0,0 +1,111 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Marks a timestamp that hasn't been recorded yet.
+const UNSET: u64 = u64::MAX;
+
+/// When an agent started and when it last took a message, and whether it is suspended or
+/// ready, shared by every clone of its handle so they can be read without asking the agent.
+/// Timestamps are kept as nanoseconds since the handle was created, so the agent can
+/// update them with plain atomic stores.
+#[derive(Debug)]
+pub(crate) struct AgentActivity {
+    origin: Instant,
+    started: AtomicU64,
+    last_message: AtomicU64,
+    suspended: AtomicBool,
+    /// Whether `suspend` was called more recently than `resume`, which the agent itself may
+    /// not have caught up with yet.
+    suspend_requested: AtomicBool,
+    /// What the agent's readiness check last returned; true if it has none.
+    ready: AtomicBool,
+}
+
+impl Default for AgentActivity {
+    fn default() -> Self {
+        AgentActivity {
+            origin: Instant::now(),
+            started: AtomicU64::new(UNSET),
+            last_message: AtomicU64::new(UNSET),
+            suspended: AtomicBool::new(false),
+            suspend_requested: AtomicBool::new(false),
+            ready: AtomicBool::new(true),
+        }
+    }
+}
+
+impl AgentActivity {
+    /// Records that the agent started handling messages.
+    pub(crate) fn mark_started(&self) {
+        self.started.store(self.now(), Ordering::Relaxed);
+    }
+
+    /// Records that the agent just took a message from its mailbox.
+    pub(crate) fn mark_message(&self) {
+        self.last_message.store(self.now(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_suspended(&self, suspended: bool) {
+        self.suspended.store(suspended, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_suspended(&self) -> bool {
+        self.suspended.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn request_suspend(&self, suspend: bool) {
+        self.suspend_requested.store(suspend, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_suspend_requested(&self) -> bool {
+        self.suspend_requested.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Returns how long ago the agent started, or zero if it hasn't.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.at(&self.started).map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    /// Returns when the agent last took a message, if it has taken any.
+    pub(crate) fn last_message_at(&self) -> Option<Instant> {
+        self.at(&self.last_message)
+    }
+
+    fn now(&self) -> u64 {
+        // Saturates centuries from now, well short of `UNSET`
+        u64::try_from(self.origin.elapsed().as_nanos()).unwrap_or(UNSET - 1)
+    }
+
+    fn at(&self, offset: &AtomicU64) -> Option<Instant> {
+        match offset.load(Ordering::Relaxed) {
+            UNSET => None,
+            nanos => Some(self.origin + Duration::from_nanos(nanos)),
+        }
+    }
+}
@@ -15,18 +15,22 @@
  */
 
 use std::any::TypeId;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use acton_ern::{Ern};
 use dashmap::DashMap;
-use futures::future::join_all;
+use tokio::sync::{mpsc, Notify};
 use tracing::*;
 
 use crate::actor::{AgentConfig, Idle, ManagedAgent};
-use crate::common::{AgentHandle, BrokerRef};
-use crate::message::{BrokerRequest, BrokerRequestEnvelope, SubscribeBroker};
+use crate::common::{AgentHandle, AgentReply, BroadcastPolicy, BrokerRef, Envelope, MessageFilter, SpawnerRef};
+use crate::message::{
+    BrokerRequest, BrokerRequestEnvelope, DeadLetter, DeadLetterReason, MessageAddress, SubscribeBroker, TrySendError,
+    UnsubscribeBroker,
+};
 use crate::traits::Actor;
 
 /// A broker that manages subscriptions and broadcasts messages to subscribers.
@@ -35,16 +39,69 @@ use crate::traits::Actor;
 /// and broadcasting messages to the appropriate subscribers.
 #[derive(Default, Debug, Clone)]
 pub struct AgentBroker {
-    /// A thread-safe map of subscribers, keyed by message type ID.
+    /// A thread-safe map of subscribers, keyed by topic and message type ID.
     ///
-    /// Each entry in the map contains a set of tuples, where each tuple consists of:
-    /// - An `Ern<UnixTime>`: The unique identifier of the subscriber.
-    /// - An `ActorRef`: A reference to the subscriber actor.
+    /// Each entry in the map is keyed by the subscriber's `Ern` and holds:
+    /// - An `AgentHandle`: A reference to the subscriber agent.
+    /// - An optional `MessageFilter`: Broadcasts it rejects are not sent to that subscriber.
     subscribers: Subscribers,
     agent_handle: AgentHandle,
+    /// Each subscriber's queue of broadcasts still to be delivered.
+    forwarders: Forwarders,
+    /// Limits how many subscribers are delivered to at once.
+    slots: Arc<DeliverySlots>,
+    /// What to do when a subscriber's mailbox is full.
+    policy: BroadcastPolicy,
+    /// Where deliveries given up on under `BroadcastPolicy::RetryThenDrop` go; set once the
+    /// dead-letter agent has started.
+    dead_letters: Arc<OnceLock<AgentHandle>>,
 }
 
-type Subscribers = Arc<DashMap<TypeId, HashSet<(Ern, AgentHandle)>>>; // Type alias for the subscribers map.
+/// How many subscribers broadcasts are delivered to at once unless the runtime says otherwise.
+pub(crate) const DEFAULT_BROADCAST_CONCURRENCY: usize = 64;
+
+type Subscribers = Arc<DashMap<(String, TypeId), HashMap<Ern, (AgentHandle, Option<MessageFilter>)>>>; // Type alias for the subscribers map.
+/// Queues keyed by subscriber, each drained in order by a forwarding task of its own.
+type Forwarders = Arc<DashMap<Ern, mpsc::UnboundedSender<BrokerRequestEnvelope>>>;
+
+/// Counts the deliveries in flight across all forwarders, against a limit the runtime can
+/// change at any time.
+#[derive(Debug, Default)]
+struct DeliverySlots {
+    limit: Arc<AtomicUsize>,
+    in_flight: AtomicUsize,
+    released: Notify,
+}
+
+impl DeliverySlots {
+    /// Waits for a free slot, which is given back when the returned guard drops.
+    async fn acquire(&self) -> DeliverySlot<'_> {
+        loop {
+            // Created before checking, so a slot released after the check still wakes us
+            let released = self.released.notified();
+            let limit = self.limit.load(Ordering::Relaxed).max(1);
+            let taken = self
+                .in_flight
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |in_flight| {
+                    (in_flight < limit).then_some(in_flight + 1)
+                })
+                .is_ok();
+            if taken {
+                return DeliverySlot(self);
+            }
+            released.await;
+        }
+    }
+}
+
+struct DeliverySlot<'a>(&'a DeliverySlots);
+
+impl Drop for DeliverySlot<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+        self.0.released.notify_waiters();
+    }
+}
 // Implement Deref and DerefMut to access AgentHandle's methods directly
 impl Deref for AgentBroker {
     type Target = AgentHandle;
@@ -62,38 +119,69 @@ impl DerefMut for AgentBroker {
 
 impl AgentBroker {
     #[instrument]
-    pub(crate) async fn initialize() -> BrokerRef {
+    pub(crate) async fn initialize(
+        concurrency: Arc<AtomicUsize>,
+        policy: BroadcastPolicy,
+        dead_letters: Arc<OnceLock<AgentHandle>>,
+        spawner: SpawnerRef,
+    ) -> BrokerRef {
         let actor_config = AgentConfig::new(Ern::with_root("broker_main").unwrap(), None, None)
             .expect("Couldn't create initial broker config");
 
         let mut broker: ManagedAgent<Idle, AgentBroker> =
             ManagedAgent::new(&None, Some(actor_config)).await;
+        broker.model.slots = Arc::new(DeliverySlots { limit: concurrency, ..Default::default() });
+        broker.model.policy = policy;
+        broker.model.dead_letters = dead_letters;
+        broker.handle.spawner = spawner;
 
         broker
             .act_on::<BrokerRequest>(|actor, event| {
                 trace!( "broadcasting request: {:?}", event.message);
-                let subscribers = actor.model.subscribers.clone();
+                // A broadcast straight from the broker has no publisher to reply to
+                let publisher = Some(event.origin_envelope().return_address)
+                    .filter(|publisher| publisher.sender != actor.id);
+                actor.model.broadcast(&actor.handle, event.message.clone(), publisher);
+                AgentReply::immediate()
+            })
+            .act_on::<SubscribeBroker>(|actor, event| {
                 let message = event.message.clone();
 
+                let topic = message.topic.clone();
+                let message_type_ids = message.message_type_ids.clone();
+                let subscriber_context = message.subscriber_context.clone();
+                let subscriber_id = message.subscriber_id.clone();
+                let filter = message.filter.clone();
+                trace!("subscribe from {} for {}", subscriber_id.root.to_string(), actor.handle.name());
 
+                let subscribers = actor.model.subscribers.clone();
                 Box::pin(async move {
-                    AgentBroker::broadcast(subscribers, message).await;
+                    // All in one handler, so no broadcast sees only some of them
+                    for message_type_id in message_type_ids {
+                        subscribers
+                            .entry((topic.clone(), message_type_id))
+                            .or_default()
+                            .insert(subscriber_id.clone(), (subscriber_context.clone(), filter.clone()));
+                    }
                 })
             })
-            .act_on::<SubscribeBroker>(|actor, event| {
+            .act_on::<UnsubscribeBroker>(|actor, event| {
                 let message = event.message.clone();
 
-                let message_type_id = message.message_type_id;
-                let subscriber_context = message.subscriber_context.clone();
+                let topic = message.topic.clone();
+                let message_type_ids = message.message_type_ids.clone();
                 let subscriber_id = message.subscriber_id.clone();
-                trace!("subscribe from {} for {}", subscriber_id.root.to_string(), actor.handle.name());
+                trace!("unsubscribe from {} for {}", subscriber_id.root.to_string(), actor.handle.name());
 
                 let subscribers = actor.model.subscribers.clone();
                 Box::pin(async move {
-                    subscribers
-                        .entry(message_type_id)
-                        .or_default()
-                        .insert((subscriber_id.clone(), subscriber_context.clone()));
+                    for message_type_id in message_type_ids {
+                        let key = (topic.clone(), message_type_id);
+                        if let Some(mut entry) = subscribers.get_mut(&key) {
+                            entry.remove(&subscriber_id);
+                        }
+                        subscribers.remove_if(&key, |_, set| set.is_empty());
+                    }
                 })
             });
 
@@ -103,33 +191,127 @@ impl AgentBroker {
         handle
     }
 
-    /// Broadcasts a message to all subscribers of a specific message type.
+    /// Broadcasts a message to all subscribers of its type on its topic.
+    ///
+    /// Each subscriber's copy joins that subscriber's own queue, which a forwarding task
+    /// spawned on `broker` delivers one at a time. The broker never waits on a delivery, so a
+    /// subscriber whose mailbox is full holds up only its own queue, while other subscribers
+    /// and later broadcasts, subscribes and unsubscribes go ahead. Under
+    /// `BroadcastPolicy::RetryThenDrop`, a delivery that finds the mailbox full through every
+    /// retry goes to the dead-letter agent instead.
     ///
-    /// This function iterates through all subscribers for the given message type and emits
-    /// the message to each subscriber asynchronously.
+    /// A subscriber gets broadcasts in the order the broker received them, so one publisher's
+    /// broadcasts arrive in the order it published them. There is no order between different
+    /// publishers' broadcasts, which the broker takes as they reach it.
     ///
     /// # Arguments
     ///
-    /// * `subscribers` - An `Arc<DashMap>` containing the subscribers for different message types.
+    /// * `broker` - The broker's handle, whose task tracker runs the forwarding tasks.
     /// * `request` - The `BrokerRequest` containing the message to be broadcast.
-    /// ```
-    pub async fn broadcast(
-        subscribers: Subscribers,
-        request: BrokerRequest,
+    /// * `publisher` - The agent that published the message, which subscribers reply to.
+    pub fn broadcast(&self, broker: &AgentHandle, request: BrokerRequest, publisher: Option<MessageAddress>) {
+        let key = (request.topic.clone(), request.message.as_ref().type_id());
+        trace!(" Subscriber count for {:?} is {:?}", key, self.subscribers.get(&key).map(|x| x.len()));
+        let Some(subscribers) = self.subscribers.get(&key) else {
+            return;
+        };
+        for (subscriber_context, filter) in subscribers.value().values() {
+            // A filtered-out broadcast simply isn't for this subscriber
+            if filter.as_ref().is_some_and(|filter| !filter.matches(&*request.message)) {
+                trace!("Filter skipped subscriber: {:?}", subscriber_context.name());
+                continue;
+            }
+            let mut message: BrokerRequestEnvelope = request.clone().into();
+            message.publisher = publisher.clone();
+            trace!("Queueing message for subscriber: {:?}", subscriber_context.name());
+            self.forward(broker, subscriber_context, message);
+        }
+    }
+
+    /// Adds `message` to `subscriber`'s queue, starting a forwarding task if none is running.
+    fn forward(&self, broker: &AgentHandle, subscriber: &AgentHandle, message: BrokerRequestEnvelope) {
+        if subscriber.outbox.is_closed() {
+            trace!("Subscriber {:?} takes no new messages, skipping it", subscriber.name());
+            self.forwarders.remove(&subscriber.id);
+            return;
+        }
+        let mut queue = self
+            .forwarders
+            .entry(subscriber.id.clone())
+            .or_insert_with(|| self.start_forwarder(broker, subscriber));
+        // The last forwarder quit when its subscriber stopped, and a new one took its name
+        if let Err(mpsc::error::SendError(message)) = queue.send(message) {
+            *queue = self.start_forwarder(broker, subscriber);
+            let _ = queue.send(message);
+        }
+    }
+
+    /// Spawns the task that delivers `subscriber`'s queue in order, and returns the queue.
+    fn start_forwarder(
+        &self,
+        broker: &AgentHandle,
+        subscriber: &AgentHandle,
+    ) -> mpsc::UnboundedSender<BrokerRequestEnvelope> {
+        let (queue, mut queued) = mpsc::unbounded_channel();
+        let subscriber = subscriber.clone();
+        let slots = self.slots.clone();
+        let policy = self.policy;
+        let dead_letters = self.dead_letters.clone();
+        broker.spawn(async move {
+            while let Some(message) = queued.recv().await {
+                if subscriber.outbox.is_closed() {
+                    break;
+                }
+                let _slot = slots.acquire().await;
+                AgentBroker::deliver(&subscriber, message, policy, dead_letters.get()).await;
+            }
+        });
+        queue
+    }
+
+    /// Sends one subscriber its copy of a broadcast, following `policy` if its mailbox is full.
+    async fn deliver(
+        subscriber: &AgentHandle,
+        message: BrokerRequestEnvelope,
+        policy: BroadcastPolicy,
+        dead_letters: Option<&AgentHandle>,
     ) {
-        let message_type_id = &request.message.as_ref().type_id();
-        trace!(" Subscriber count for message type: {:?} is {:?}", message_type_id, subscribers.get(message_type_id).map(|x| x.len()));
-        if let Some(subscribers) = subscribers.get(message_type_id) {
-            let futures = subscribers.value().clone().into_iter().map(|(_, subscriber_context)| {
-                let subscriber_context = subscriber_context.clone();
-                let message: BrokerRequestEnvelope = request.clone().into();
-                async move {
-                    trace!("Broadcasting message to subscriber: {:?}", subscriber_context.name());
-                    subscriber_context.send(message).await;
+        let retry = match policy {
+            BroadcastPolicy::BlockForever => {
+                subscriber.send(message).await;
+                return;
+            }
+            BroadcastPolicy::RetryThenDrop(retry) => retry,
+        };
+        let mut message = message;
+        let max_attempts = retry.max_attempts.max(1);
+        for attempt in 1..=max_attempts {
+            match subscriber.try_send(message) {
+                Ok(()) => return,
+                Err(TrySendError::Closed) => {
+                    trace!("Subscriber {:?} takes no new messages, skipping it", subscriber.name());
+                    return;
                 }
-            });
-            // Await all futures concurrently
-            join_all(futures).await;
+                Err(TrySendError::Full(returned)) => message = returned,
+            }
+            if attempt < max_attempts {
+                tokio::time::sleep(retry.delay_after(attempt)).await;
+            }
+        }
+
+        warn!(
+            subscriber = subscriber.id.to_string(),
+            "Mailbox still full after {} attempts, dropping broadcast {:?}", max_attempts, message.message
+        );
+        let Some(dead_letters) = dead_letters else {
+            return;
+        };
+        // A dead letter that can't be delivered would only be dead-lettered again
+        if (*message.message).as_any().is::<DeadLetter>() || dead_letters.outbox.is_closed() {
+            return;
         }
+        let reply_to = message.publisher.clone().unwrap_or_else(|| dead_letters.reply_address());
+        let envelope = Envelope::new(message.message, reply_to, subscriber.reply_address());
+        dead_letters.send(DeadLetter { envelope, reason: DeadLetterReason::SubscriberFull }).await;
     }
 }
@@ -13,23 +13,41 @@
  * See the applicable License for the specific language governing permissions and
  * limitations under that License.
  */
+use std::any::TypeId;
 use std::fmt::Debug;
 use std::future::Future;
 use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::Duration;
 
 use acton_ern::Ern;
 use async_trait::async_trait;
 use dashmap::DashMap;
-use tokio::sync::mpsc;
+use futures::future::join_all;
+use futures::Stream;
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
 use tokio_util::task::TaskTracker;
 use tracing::{error, instrument, trace, warn};
+use uuid::Uuid;
 
-use crate::actor::{Idle, ManagedAgent};
-use crate::common::{BrokerRef, OutboundEnvelope, Outbox, ParentRef};
-use crate::message::{BrokerRequest, MessageAddress, SystemSignal};
+use crate::actor::{Idle, ManagedAgent, DEFAULT_OUTPUT_CAPACITY};
+use crate::common::{
+    bounded_mailbox, unbounded_mailbox, AgentActivity, AgentMetrics, BrokerRef, DeliveryHandle, HealthState, HealthStatus, IntervalHandle, MetricsRecorder, OutboundEnvelope, Outbox,
+    OutputSender, OutstandingAsks, ParentRef, ReplySlot, ScheduledHandle, SpawnerRef, Subscriptions, TypedAgentHandle,
+};
+use crate::message::{
+    BrokerRequest, MessageAddress, MessageError, Reconfigure, StreamEnd, SystemSignal, Terminated, TrySendError,
+};
 use crate::prelude::ActonMessage;
 use crate::traits::{Actor, Broker, Subscriber};
 
+/// How many streamed replies an `ask_stream` caller may fall behind before the replying
+/// handler has to wait.
+const STREAM_REPLY_CAPACITY: usize = 32;
+
 /// Represents the context in which an actor operates.
 #[derive(Debug, Clone)]
 pub struct AgentHandle {
@@ -37,25 +55,71 @@ pub struct AgentHandle {
     pub(crate) id: Ern,
     /// The outbound channel for sending messages.
     pub(crate) outbox: Outbox,
+    /// The high-priority channel, if the agent has one.
+    pub(crate) priority_outbox: Option<Outbox>,
+    /// The channel for system signals, so they don't wait behind regular messages.
+    pub(crate) signal_outbox: Outbox,
     /// The task tracker for the actor.
     tracker: TaskTracker,
     /// The actor's optional parent context.
     pub parent: Option<Box<ParentRef>>,
     /// The system broker for the actor.
     pub broker: Box<Option<BrokerRef>>,
-    children: DashMap<String, AgentHandle>,
+    /// Shared by every clone of the handle, so children added through any of them are stopped
+    /// with the agent.
+    children: Arc<DashMap<Ern, AgentHandle>>,
+    /// The most children the agent may supervise at once, if limited.
+    pub(crate) max_children: Option<usize>,
+    /// Cancelled when the actor stops, ending its timers.
+    pub(crate) cancellation_token: CancellationToken,
+    /// Cancelled when the agent is asked to stop, drain or suspend, so handlers can cut
+    /// long work short; replaced with a fresh one on resume.
+    interrupt: Arc<Mutex<CancellationToken>>,
+    /// Set when the agent was configured with `AgentConfig::with_metrics`.
+    pub(crate) metrics: Option<Arc<MetricsRecorder>>,
+    /// When the agent started and last took a message.
+    pub(crate) activity: Arc<AgentActivity>,
+    /// The agent's output stream, created the first time it's asked for.
+    output: Arc<OnceLock<OutputSender>>,
+    /// How many messages the output stream keeps for listeners that fall behind.
+    pub(crate) output_capacity: usize,
+    /// Reply channels of `ask` requests sent to the agent, failed if it suspends or stops
+    /// without answering them.
+    asks: OutstandingAsks,
+    /// Runs the agent's tasks; the runtime's spawner once the agent belongs to one.
+    pub(crate) spawner: SpawnerRef,
+    /// The names of the message types the agent was given handlers for while it was set up.
+    pub(crate) handled_types: Arc<DashMap<TypeId, &'static str>>,
 }
 
+/// Sent in place of a reply to an `ask` the agent won't answer, because it suspended or
+/// stopped.
+#[derive(Clone, Debug)]
+struct AskAbandoned;
+
 impl Default for AgentHandle {
     fn default() -> Self {
-        let (outbox, _) = mpsc::channel(1);
+        let (outbox, _) = bounded_mailbox(1);
+        let (signal_outbox, _) = unbounded_mailbox();
         AgentHandle {
             id: Ern::default(),
             outbox,
+            priority_outbox: None,
+            signal_outbox,
             tracker: TaskTracker::new(),
             parent: None,
             broker: Box::new(None),
-            children: DashMap::new(),
+            children: Default::default(),
+            max_children: None,
+            cancellation_token: CancellationToken::new(),
+            interrupt: Default::default(),
+            metrics: None,
+            activity: Default::default(),
+            output: Default::default(),
+            output_capacity: DEFAULT_OUTPUT_CAPACITY,
+            asks: Default::default(),
+            handled_types: Default::default(),
+            spawner: Default::default(),
         }
     }
 }
@@ -113,28 +177,632 @@ impl AgentHandle {
     /// This method will return an error if:
     /// - The child actor fails to activate.
     /// - Inserting the child context into the `children` map fails.
+    /// - The agent already has as many children as its `with_max_children` limit allows, in
+    ///   which case the child isn't started and the error is
+    ///   `MessageError::ChildLimitReached`.
     #[instrument(skip(self))]
     pub async fn supervise<State: Default + Send + Debug>(
         &self,
         child: ManagedAgent<Idle, State>,
     ) -> anyhow::Result<AgentHandle> {
         trace!("Adding child actor with id: {}", child.id);
+        self.check_child_limit()?;
+        let mut child = child;
+        if child.parent.is_none() {
+            child.parent = Some(self.clone());
+            child.handle.parent = Some(Box::new(self.clone()));
+        }
         let handle = child.start().await;
         let id = handle.id.clone();
         trace!("Now have child id in context: {}", id);
-        self.children.insert(id.to_string(), handle.clone());
+        self.children.insert(id, handle.clone());
 
         Ok(handle)
     }
-}
 
-impl Broker for AgentHandle {
-    #[instrument(skip(self), name = "broadcast")]
-    fn broadcast(&self, message: impl ActonMessage) -> impl Future<Output = ()> + Send + Sync + '_ {
+    /// Runs `task` with the runtime's spawner, tracked so stopping the agent waits for it.
+    pub(crate) fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+        self.spawner.spawn(&self.tracker, task);
+    }
+
+    /// Fails with `MessageError::ChildLimitReached` if the agent can't take another child.
+    pub(crate) fn check_child_limit(&self) -> Result<(), MessageError> {
+        match self.max_children {
+            Some(limit) if self.children.len() >= limit => Err(MessageError::ChildLimitReached(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Forgets a child that has stopped, freeing its place under the child limit.
+    pub(crate) fn remove_child(&self, id: &Ern) {
+        self.children.remove(id);
+    }
+
+    /// Returns the agent's parent, or `None` for a root agent.
+    ///
+    /// An agent has a parent when its config named one, or once it is started with
+    /// [`AgentHandle::supervise`].
+    pub fn parent(&self) -> Option<AgentHandle> {
+        self.parent.as_deref().cloned()
+    }
+
+    /// Returns the number of messages waiting in the agent's mailbox.
+    ///
+    /// This is a best-effort snapshot: other tasks may be sending or the agent receiving
+    /// while it is read. Messages already taken by a running handler are not counted.
+    ///
+    /// Always 0 for an unbounded mailbox, whose length isn't visible to senders.
+    pub fn mailbox_len(&self) -> usize {
+        self.outbox.len()
+    }
+
+    /// Returns the number of messages the agent's mailbox can hold, or `usize::MAX` if it
+    /// is unbounded.
+    pub fn mailbox_capacity(&self) -> usize {
+        self.outbox.max_capacity()
+    }
+
+    /// Returns a snapshot of the agent's metrics, or `None` if it wasn't configured with
+    /// `AgentConfig::with_metrics`.
+    pub fn metrics(&self) -> Option<AgentMetrics> {
+        let mailbox_depth = self.mailbox_len() + self.priority_outbox.as_ref().map_or(0, Outbox::len);
+        self.metrics.as_ref().map(|metrics| metrics.snapshot(mailbox_depth))
+    }
+
+    /// Starts a set of message types to subscribe this agent to, or unsubscribe it from, in
+    /// one request to the broker rather than one per type.
+    pub fn subscriptions(&self) -> Subscriptions {
+        Subscriptions::new(self.clone())
+    }
+
+    /// Returns how long the agent has been running, or zero if it hasn't started.
+    pub fn uptime(&self) -> Duration {
+        self.activity.uptime()
+    }
+
+    /// Returns when the agent last took a message from its mailbox, or `None` if it hasn't
+    /// taken one yet.
+    ///
+    /// Its `elapsed()` is how long the agent has been idle, for policies that stop agents
+    /// nobody is talking to.
+    pub fn last_message_at(&self) -> Option<Instant> {
+        self.activity.last_message_at()
+    }
+
+    /// Returns the agent's health, for liveness and readiness probes.
+    ///
+    /// It is read from flags the agent keeps up to date rather than asked of the agent, so
+    /// it is cheap and answers even while the agent is busy. The agent is reported as
+    /// stopped once its mailbox is closed, draining or suspended when told to, and not
+    /// ready while the check set with `ManagedAgent::readiness_check` fails.
+    pub fn health(&self) -> HealthStatus {
+        let state = if self.outbox.is_closed() {
+            HealthState::Stopped
+        } else if self.outbox.is_draining() {
+            HealthState::Draining
+        } else if self.activity.is_suspended() {
+            HealthState::Suspended
+        } else if !self.activity.is_ready() {
+            HealthState::NotReady
+        } else {
+            HealthState::Healthy
+        };
+        HealthStatus { state, idle_for: self.last_message_at().map(|at| at.elapsed()) }
+    }
+
+    /// Sends a message to the agent's high-priority mailbox, to be handled ahead of anything
+    /// waiting in its regular one.
+    ///
+    /// Falls back to a regular send if the agent wasn't configured with
+    /// `AgentConfig::with_priority_mailbox`.
+    pub async fn send_priority(&self, message: impl ActonMessage + 'static) {
+        match &self.priority_outbox {
+            Some(priority_outbox) => {
+                let recipient = MessageAddress::new(priority_outbox.clone(), self.id.clone());
+                self.create_envelope(Some(recipient)).send(message).await;
+            }
+            None => {
+                warn!(actor = self.id.to_string(), "No priority mailbox, sending normally");
+                self.send(message).await;
+            }
+        }
+    }
+
+    /// Sends the agent new configuration, for the hook it set with
+    /// `ManagedAgent::on_reconfigure` to apply without restarting it.
+    ///
+    /// The configuration goes through the mailbox, so messages sent before it are handled
+    /// with the old settings and messages sent after it with the new ones.
+    pub async fn reconfigure<C: Clone + Debug + Send + Sync + 'static>(&self, config: C) {
+        self.send(Reconfigure(config)).await;
+    }
+
+    /// Sends a message without waiting, for producers that must never block.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TrySendError::Full`], handing the message back, if the agent's mailbox is
+    /// full, so the caller can drop it, buffer it, or shed load. Returns
+    /// [`TrySendError::Closed`] if the agent has stopped or is draining.
+    pub fn try_send<M: ActonMessage + 'static>(&self, message: M) -> Result<(), TrySendError<M>> {
+        self.create_envelope(None).try_send(message)
+    }
+
+    /// Sends a message from synchronous code, such as a `Drop` impl or a signal handler,
+    /// where there is nothing to `.await`.
+    ///
+    /// If the mailbox has room, the message goes straight in. If it is full, what happens
+    /// depends on the calling thread:
+    ///
+    /// * On a plain thread, or a `spawn_blocking` task on a multi-threaded runtime, the call
+    ///   blocks until there is room.
+    /// * On a multi-threaded runtime's worker, it blocks in place: tokio hands the worker's
+    ///   other tasks to another thread first, so the agent can still make room.
+    /// * On a current-thread runtime, the thread may be the one the agent runs on, so the
+    ///   call fails rather than wait for room that would never come. So do its
+    ///   `spawn_blocking` tasks, which can't be told apart from it.
+    ///
+    /// Messages sent from one thread arrive in the order they were sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MessageError::AgentStopped`] if the agent has stopped,
+    /// [`MessageError::Draining`] if it is draining, and [`MessageError::MailboxFull`] if the
+    /// mailbox is full on a current-thread runtime.
+    pub fn send_blocking(&self, message: impl ActonMessage + 'static) -> Result<(), MessageError> {
+        let message_type = message.type_name();
+        let stopped = || MessageError::AgentStopped { id: Box::new(self.id.clone()), message_type };
+        if self.outbox.is_closed() {
+            return Err(stopped());
+        }
+        let message = match self.try_send(message) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(message)) => message,
+            // It may have stopped since the check above
+            Err(TrySendError::Closed) if self.outbox.is_closed() => return Err(stopped()),
+            Err(TrySendError::Closed) => return Err(MessageError::Draining),
+        };
+        match Handle::try_current().map(|runtime| runtime.runtime_flavor()) {
+            Err(_) => self.create_envelope(None).send_blocking(message),
+            Ok(RuntimeFlavor::CurrentThread) => {
+                trace!(actor = self.id.to_string(), "Mailbox full, can't wait for room for {:?}", message);
+                Err(MessageError::MailboxFull)
+            }
+            // Plain blocking on a `spawn_blocking` task
+            Ok(_) => tokio::task::block_in_place(|| self.create_envelope(None).send_blocking(message)),
+        }
+    }
+
+    /// Sends a batch of messages, in order, reserving mailbox room for the whole batch (or as
+    /// much of it as the mailbox can hold) before sending any of it.
+    ///
+    /// This saves a wait per message, and keeps the batch together when this is the agent's
+    /// only sender. The mailbox takes messages from many senders, though: another sender can
+    /// still get a message in between the batch's messages, especially when the batch is
+    /// larger than the mailbox.
+    pub async fn send_batch(&self, messages: Vec<Box<dyn ActonMessage>>) {
+        self.create_envelope(None).send_batch(messages).await;
+    }
+
+    /// Stops one of this agent's direct children, along with its own children, and stops
+    /// supervising it. Its siblings keep running.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `id` isn't a direct child of this agent, or if the child fails
+    /// to stop.
+    #[instrument(skip(self))]
+    pub async fn terminate_child(&self, id: &Ern) -> anyhow::Result<()> {
+        let child = self.find_child(id).ok_or_else(|| {
+            anyhow::anyhow!("{} is not a child of {}", id, self.id)
+        })?;
+        child.stop().await?;
+        self.children.remove(id);
+        trace!(child = id.to_string(), "Child terminated");
+        Ok(())
+    }
+
+    /// Sends a clone of the message to each of this agent's direct children.
+    ///
+    /// Unlike a broker broadcast, this reaches exactly the agent's children, whatever they
+    /// are subscribed to. The sends happen together, each waiting for room in its child's
+    /// mailbox, and a failed send doesn't stop the others; the ERN of each child that
+    /// couldn't be sent to is returned along with its error.
+    #[instrument(skip(self))]
+    pub async fn broadcast_to_children(
+        &self,
+        message: impl ActonMessage + Clone + 'static,
+    ) -> Vec<(Ern, MessageError)> {
+        let sends = self.children().into_iter().map(|(id, child)| {
+            let envelope = self.create_envelope(Some(child.reply_address()));
+            let message: Arc<dyn ActonMessage + Send + Sync> = Arc::new(message.clone());
+            async move { envelope.deliver(message).await.err().map(|e| (id, e)) }
+        });
+        join_all(sends).await.into_iter().flatten().collect()
+    }
+
+    /// Returns a handle to this agent that only accepts messages of type `M`.
+    pub fn typed<M: ActonMessage + 'static>(&self) -> TypedAgentHandle<M> {
+        TypedAgentHandle::new(self.clone())
+    }
+
+    /// Suspends the agent.
+    ///
+    /// The signal goes through the mailbox, so messages sent before it are still handled.
+    /// Messages arriving afterwards wait in the mailbox until [`AgentHandle::resume`], so a
+    /// bounded mailbox fills and holds senders back as it would for a busy agent. Suspending
+    /// never stops the agent; use [`AgentHandle::drain`] to finish the queued work and then
+    /// stop.
+    ///
+    /// Callers waiting on [`AgentHandle::ask`] aren't kept waiting for the resume: once the
+    /// agent suspends, their asks fail with `MessageError::AgentStopped`, as do asks sent
+    /// while it is suspended. The messages are still handled after the resume.
+    #[instrument(skip(self))]
+    pub async fn suspend(&self) {
+        trace!(actor = self.id.to_string(), "Sending Suspend to");
+        self.activity.request_suspend(true);
+        self.interrupt();
+        self.create_envelope(Some(self.signal_address())).send(SystemSignal::Suspend).await;
+    }
+
+    /// Stops the agent once it has handled every message already in its mailbox.
+    ///
+    /// Unlike [`AgentHandle::suspend`], which only pauses the agent and holds on to what
+    /// arrives meanwhile, draining is the first step of stopping: from the moment it is
+    /// called, new messages are refused with `MessageError::Draining` (system signals still
+    /// get through), the queued ones are handled, and then the stop hooks run as they do for
+    /// [`Actor::stop`]. Returns once the agent and its children have stopped.
+    #[instrument(skip(self))]
+    pub async fn drain(&self) -> anyhow::Result<()> {
+        trace!(actor = self.id.to_string(), "Draining");
+        self.outbox.start_draining();
+        if let Some(priority_outbox) = &self.priority_outbox {
+            priority_outbox.start_draining();
+        }
+        self.stop().await
+    }
+
+    /// Tells the agent to stop, without waiting for it to.
+    ///
+    /// The agent stops just as it does for [`Actor::stop`]: it handles the messages already
+    /// in its mailbox, runs its stop hooks and stops its children. The difference is that
+    /// `stop` waits for all of that to finish, while `terminate` puts the signal in the
+    /// agent's signal lane without waiting, so it suits synchronous code, even off the
+    /// runtime, and handlers that mustn't wait on another agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::AgentStopped` if the agent has already stopped, and
+    /// `MessageError::MailboxFull` if its signal lane is full.
+    #[instrument(skip(self))]
+    pub fn terminate(&self) -> Result<(), MessageError> {
+        // Let a handler that's running cut its work short
+        self.interrupt();
+        trace!(actor = self.id.to_string(), "Sending Terminate to");
+        match self.create_envelope(Some(self.signal_address())).try_send(SystemSignal::Terminate) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(MessageError::MailboxFull),
+            Err(TrySendError::Closed) => Err(MessageError::AgentStopped {
+                id: Box::new(self.id.clone()),
+                message_type: std::any::type_name::<SystemSignal>(),
+            }),
+        }
+    }
+
+    /// Resumes a suspended agent, which then handles its held messages in order.
+    #[instrument(skip(self))]
+    pub async fn resume(&self) {
+        trace!(actor = self.id.to_string(), "Sending Resume to");
+        self.activity.request_suspend(false);
+        self.create_envelope(Some(self.signal_address())).send(SystemSignal::Resume).await;
+    }
+
+    /// Registers this agent as a watcher of `target`.
+    ///
+    /// When `target` terminates, this agent receives a [`Terminated`] message carrying
+    /// the target's id.
+    #[instrument(skip(self, target))]
+    pub async fn watch(&self, target: &AgentHandle) {
+        trace!(watcher = self.id.to_string(), target = target.id.to_string(), "Watching");
+        self.create_envelope(Some(target.signal_address()))
+            .send(SystemSignal::Watch(Box::new(self.clone())))
+            .await;
+    }
+
+    /// Removes this agent from `target`'s watchers, so no [`Terminated`] message is sent.
+    #[instrument(skip(self, target))]
+    pub async fn unwatch(&self, target: &AgentHandle) {
+        trace!(watcher = self.id.to_string(), target = target.id.to_string(), "Unwatching");
+        self.create_envelope(Some(target.signal_address()))
+            .send(SystemSignal::Unwatch(self.id.clone()))
+            .await;
+    }
+
+    /// Sends `message` to this agent after `delay`.
+    ///
+    /// The delivery runs on the agent's task tracker and is dropped if the agent stops first.
+    /// Use the returned [`ScheduledHandle`] to cancel it.
+    #[instrument(skip(self, message))]
+    pub fn schedule(&self, delay: Duration, message: impl ActonMessage + 'static) -> ScheduledHandle {
+        let scheduled = ScheduledHandle::new(self.cancellation_token.child_token());
+        let handle = scheduled.clone();
+        let envelope = self.create_envelope(None);
+        // Measured from now rather than from when the task first runs
+        let deadline = tokio::time::Instant::now() + delay;
+        trace!(actor = self.id.to_string(), "Scheduling {:?} in {:?}", message, delay);
+        self.spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => {
+                    if handle.fire() {
+                        envelope.send(message).await;
+                    }
+                }
+                _ = handle.cancellation_token().cancelled() => {
+                    trace!("Scheduled message cancelled");
+                }
+            }
+        });
+        scheduled
+    }
+
+    /// Sends `message` to this agent at least once, redelivering it until the receiver
+    /// acknowledges it.
+    ///
+    /// The handler acknowledges with `MessageContext::ack` (or `Envelope::ack`). If no ack
+    /// arrives within `ack_timeout`, the message is sent again, up to `max_attempts` sends in
+    /// total. Every attempt carries the same delivery id, so the receiver can tell a
+    /// redelivery from a new message. The returned [`DeliveryHandle`] reports the outcome.
+    #[instrument(skip(self, message))]
+    pub fn send_reliable(
+        &self,
+        message: impl ActonMessage + 'static,
+        ack_timeout: Duration,
+        max_attempts: usize,
+    ) -> DeliveryHandle {
+        let delivery_id = Uuid::new_v4();
+        let ack = Arc::new(Notify::new());
+        let envelope = self.create_envelope(None).with_delivery((delivery_id, ack.clone()));
+        let message: Arc<dyn ActonMessage + Send + Sync> = Arc::new(message);
+        let (sender, receiver) = oneshot::channel();
+        trace!(actor = self.id.to_string(), delivery = %delivery_id, "Sending {:?} reliably", message);
+        self.spawn(async move {
+            let mut attempts = 0;
+            let outcome = loop {
+                attempts += 1;
+                envelope.send_message_inner(message.clone()).await;
+                // An ack for an earlier attempt leaves a permit, so it is seen here too
+                if tokio::time::timeout(ack_timeout, ack.notified()).await.is_ok() {
+                    break Ok(());
+                }
+                if attempts >= max_attempts {
+                    warn!(delivery = %delivery_id, "No ack after {} attempts", attempts);
+                    break Err(MessageError::NotAcknowledged(attempts));
+                }
+                trace!(delivery = %delivery_id, "No ack after attempt {}, redelivering", attempts);
+            };
+            let _ = sender.send(outcome);
+        });
+        DeliveryHandle::new(delivery_id, receiver)
+    }
+
+    /// Sends a message built by `factory` to this agent every `period`, starting one period from now.
+    ///
+    /// Ticks missed while the agent is busy are skipped rather than queued. The interval runs on
+    /// the agent's task tracker until the returned [`IntervalHandle`] is stopped or dropped, or
+    /// the agent stops.
+    #[instrument(skip(self, factory))]
+    pub fn schedule_interval<M, F>(&self, period: Duration, factory: F) -> IntervalHandle
+    where
+        M: ActonMessage + 'static,
+        F: Fn() -> M + Send + Sync + 'static,
+    {
+        let cancellation_token = self.cancellation_token.child_token();
+        let stopped = cancellation_token.clone();
+        let envelope = self.create_envelope(None);
+        let first_tick = tokio::time::Instant::now() + period;
+        trace!(actor = self.id.to_string(), "Scheduling {} every {:?}", std::any::type_name::<M>(), period);
+        self.spawn(async move {
+            let mut interval = tokio::time::interval_at(first_tick, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => envelope.send(factory()).await,
+                    _ = stopped.cancelled() => break,
+                }
+            }
+            trace!("Interval stopped");
+        });
+        IntervalHandle::new(cancellation_token)
+    }
+
+    /// Returns copies of every message the agent has sent, oldest first, when it was
+    /// configured with `AgentConfig::with_recording`. Empty otherwise.
+    ///
+    /// That includes messages sent through this handle, which name the agent as the sender.
+    /// Only available with the `testing` feature.
+    #[cfg(feature = "testing")]
+    pub fn recorded(&self) -> Vec<Box<dyn ActonMessage>> {
+        self.outbox.recorded()
+    }
+
+    /// Returns the recorded messages of type `M`, oldest first.
+    ///
+    /// See [`AgentHandle::recorded`]. Only available with the `testing` feature.
+    #[cfg(feature = "testing")]
+    pub fn recorded_as<M: ActonMessage + Clone + 'static>(&self) -> Vec<M> {
+        self.recorded()
+            .iter()
+            .filter_map(|message| (**message).as_any().downcast_ref::<M>().cloned())
+            .collect()
+    }
+
+    /// Sends a message to the agent and waits for the handler to answer it.
+    ///
+    /// The handler answers with `MessageContext::reply_with`. The reply is downcast to `R`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::NoReply` if the handler finished without replying,
+    /// `MessageError::AgentStopped` if the agent suspended or stopped before answering, or
+    /// `MessageError::OtherError` if the reply was not of type `R`.
+    #[instrument(skip(self, message))]
+    pub async fn ask<M, R>(&self, message: M) -> Result<R, MessageError>
+    where
+        M: ActonMessage + 'static,
+        R: ActonMessage + Clone + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let reply_channel = Arc::new(Mutex::new(Some(sender)));
+        {
+            let mut asks = self.asks.lock().unwrap();
+            asks.retain(|ask| ask.strong_count() > 0);
+            asks.push(Arc::downgrade(&reply_channel));
+        }
+        let envelope = self.create_envelope(None).with_reply_channel(reply_channel.clone());
+        trace!(actor = self.id.to_string(), "Asking {}", std::any::type_name::<M>());
+        envelope.send(message).await;
+        // A suspended agent leaves the message in its mailbox until it resumes. Registered
+        // first, so an ask sent as the agent suspends is abandoned either here or by the agent
+        if self.activity.is_suspend_requested() {
+            abandon_ask(&reply_channel);
+        }
+
+        let reply = receiver.await.map_err(|_| MessageError::NoReply)?;
+        if (*reply).as_any().is::<AskAbandoned>() {
+            return Err(MessageError::AgentStopped {
+                id: Box::new(self.id.clone()),
+                message_type: std::any::type_name::<M>(),
+            });
+        }
+        (*reply).as_any().downcast_ref::<R>().cloned().ok_or_else(|| {
+            MessageError::OtherError(format!(
+                "expected a reply of type {}",
+                std::any::type_name::<R>()
+            ))
+        })
+    }
+
+    /// Same as [`AgentHandle::ask`], but gives up after `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::Timeout` if no reply arrives in time, otherwise the errors of `ask`.
+    pub async fn ask_timeout<M, R>(&self, message: M, timeout: Duration) -> Result<R, MessageError>
+    where
+        M: ActonMessage + 'static,
+        R: ActonMessage + Clone + 'static,
+    {
+        tokio::time::timeout(timeout, self.ask(message))
+            .await
+            .map_err(|_| MessageError::Timeout)?
+    }
+
+    /// Sends a message to the agent and returns the stream of replies its handler sends with
+    /// `MessageContext::reply_stream`.
+    ///
+    /// Items arrive in the order the handler produced them and are downcast to `Item`; any
+    /// that aren't of that type are skipped. The stream ends after the handler's last item,
+    /// or early if the handler finishes without streaming or the agent stops. Dropping the
+    /// stream tells the handler to stop producing items.
+    #[instrument(skip(self, message))]
+    pub async fn ask_stream<M, Item>(&self, message: M) -> impl Stream<Item = Item> + Send + 'static
+    where
+        M: ActonMessage + 'static,
+        Item: ActonMessage + Clone + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(STREAM_REPLY_CAPACITY);
+        let envelope = self.create_envelope(None).with_stream_channel(sender);
+        trace!(actor = self.id.to_string(), "Asking {} for a stream", std::any::type_name::<M>());
+        envelope.send(message).await;
+
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                let reply = receiver.recv().await?;
+                if (*reply).as_any().is::<StreamEnd>() {
+                    return None;
+                }
+                match (*reply).as_any().downcast_ref::<Item>() {
+                    Some(item) => return Some((item.clone(), receiver)),
+                    None => warn!(
+                        "Skipping streamed reply {:?}, expected {}",
+                        reply,
+                        std::any::type_name::<Item>()
+                    ),
+                }
+            }
+        })
+    }
+
+    /// Returns the ids of the message types the agent has handlers for.
+    ///
+    /// The list is taken from the handlers added while the agent was set up, so it doesn't
+    /// follow `become_behavior`, and still includes an `act_once` type after its handler
+    /// has run. A router can check it to avoid sending messages the agent would only
+    /// dead-letter.
+    pub fn handled_types(&self) -> Vec<TypeId> {
+        self.handled_types.iter().map(|entry| *entry.key()).collect()
+    }
+
+    /// Like [`AgentHandle::handled_types`], with the types' names, sorted.
+    pub fn handled_type_names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.handled_types.iter().map(|entry| *entry.value()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the sender of the agent's output stream, which any number of short-lived
+    /// listeners can follow with `subscribe`.
+    ///
+    /// Unlike [`AgentHandle::broadcast_to_children`] or the broker, delivery is best effort:
+    /// sending never waits, and a listener that falls more than the stream's capacity behind
+    /// (see `AgentConfig::with_output_capacity`) skips the oldest messages, learning how many
+    /// from `RecvError::Lagged`. Sending fails only when no one is listening.
+    pub fn broadcast_handle(&self) -> OutputSender {
+        self.output
+            .get_or_init(|| tokio::sync::broadcast::channel(self.output_capacity.max(1)).0)
+            .clone()
+    }
+
+    /// Returns the token handlers watch to learn the agent is stopping or suspending.
+    pub(crate) fn interrupt_token(&self) -> CancellationToken {
+        self.interrupt.lock().unwrap().clone()
+    }
+
+    /// Cancels the token from `interrupt_token`, telling running handlers to wrap up.
+    pub(crate) fn interrupt(&self) {
+        self.interrupt.lock().unwrap().cancel();
+    }
+
+    /// Fails every `ask` sent to the agent that is still waiting for an answer, so its caller
+    /// gets `MessageError::AgentStopped` instead of waiting on an agent that has suspended or
+    /// stopped.
+    pub(crate) fn abandon_asks(&self) {
+        let asks = std::mem::take(&mut *self.asks.lock().unwrap());
+        for ask in asks.iter().filter_map(Weak::upgrade) {
+            abandon_ask(&ask);
+        }
+    }
+
+    /// Gives handlers a fresh token after the agent resumes.
+    pub(crate) fn renew_interrupt(&self) {
+        let mut interrupt = self.interrupt.lock().unwrap();
+        if interrupt.is_cancelled() {
+            *interrupt = CancellationToken::new();
+        }
+    }
+
+    /// Returns the address system signals for this agent are sent to.
+    pub(crate) fn signal_address(&self) -> MessageAddress {
+        MessageAddress::new(self.signal_outbox.clone(), self.id.clone())
+    }
+
+    /// Hands `request` to this agent's broker for delivery to its subscribers.
+    fn request_broadcast(&self, request: BrokerRequest) -> impl Future<Output = ()> + Send + Sync + '_ {
         trace!("Looking for a broker to broadcast message.");
         async move {
             if let Some(broker) = self.broker.as_ref() {
-                broker.send(BrokerRequest::new(message)).await;
+                // Sent from this agent, so subscribers can reply to it
+                self.create_envelope(Some(broker.reply_address())).send(request).await;
             } else {
                 error!("No broker found to broadcast message.");
             }
@@ -142,6 +810,18 @@ impl Broker for AgentHandle {
     }
 }
 
+impl Broker for AgentHandle {
+    #[instrument(skip(self), name = "broadcast", level = "trace")]
+    fn broadcast(&self, message: impl ActonMessage) -> impl Future<Output = ()> + Send + Sync + '_ {
+        self.request_broadcast(BrokerRequest::new(message))
+    }
+
+    #[instrument(skip(self, message), name = "broadcast_topic", level = "trace")]
+    fn broadcast_topic(&self, topic: &str, message: impl ActonMessage) -> impl Future<Output = ()> + Send + Sync + '_ {
+        self.request_broadcast(BrokerRequest::new_with_topic(topic, message))
+    }
+}
+
 #[async_trait]
 impl Actor for AgentHandle {
     /// Returns the message address for this agent.
@@ -150,7 +830,7 @@ impl Actor for AgentHandle {
     }
 
     /// Returns an envelope for the specified recipient and message, ready to send.
-    #[instrument(skip(self))]
+    #[instrument(skip(self), level = "trace")]
     fn create_envelope(&self, recipient_address: Option<MessageAddress>) -> OutboundEnvelope {
         trace!("self id is {}", self.id);
         let return_address = self.reply_address();
@@ -162,15 +842,18 @@ impl Actor for AgentHandle {
         }
     }
 
-    fn children(&self) -> DashMap<String, AgentHandle> {
-        self.children.clone()
+    fn children(&self) -> Vec<(Ern, AgentHandle)> {
+        self.children
+            .iter()
+            .map(|item| (item.key().clone(), item.value().clone()))
+            .collect()
     }
 
     #[instrument(skip(self))]
     fn find_child(&self, arn: &Ern) -> Option<AgentHandle> {
         trace!("Searching for child with ARN: {}", arn);
         self.children
-            .get(&arn.to_string())
+            .get(arn)
             .map(|item| item.value().clone())
     }
 
@@ -197,13 +880,11 @@ impl Actor for AgentHandle {
         async move {
             let tracker = self.tracker();
 
-            let actor = self.create_envelope(None).clone();
-
-            // Event: Sending Terminate Signal
-            // Description: Sending a terminate signal to the actor.
-            // Context: Target actor key.
-            trace!(actor = self.id.to_string(), "Sending Terminate to");
-            actor.reply(SystemSignal::Terminate)?;
+            match self.terminate() {
+                // Stopping an agent that already stopped is not an error
+                Err(MessageError::AgentStopped { .. }) => trace!(actor = self.id.to_string(), "Already stopped"),
+                result => result?,
+            }
 
             // Event: Waiting for Actor Tasks
             // Description: Waiting for all actor tasks to complete.
@@ -222,3 +903,11 @@ impl Actor for AgentHandle {
         }
     }
 }
+
+/// Answers an `ask` with `AskAbandoned`, unless it was answered already, so the caller gets
+/// `MessageError::AgentStopped`.
+pub(crate) fn abandon_ask(reply_channel: &ReplySlot) {
+    if let Some(sender) = reply_channel.lock().unwrap().take() {
+        let _ = sender.send(Box::new(AskAbandoned));
+    }
+}
@@ -0,0 +1,206 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::any::TypeId;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use dashmap::DashMap;
+
+/// Upper bounds of the latency histograms' buckets. Anything slower than the last bound is
+/// only counted in the total.
+pub const LATENCY_BUCKETS: [Duration; 7] = [
+    Duration::from_micros(10),
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+];
+
+/// A snapshot of an agent's metrics, from `AgentHandle::metrics`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgentMetrics {
+    /// Messages handled since the agent started.
+    pub messages_handled: u64,
+    /// How long the agent's handlers took.
+    pub handler_latency: LatencyHistogram,
+    /// How long messages waited in the agent's mailboxes before their handlers ran.
+    pub queue_latency: LatencyHistogram,
+    /// The same figures for each type of message the agent has handled.
+    pub by_message_type: Vec<MessageTypeMetrics>,
+    /// Messages waiting in the agent's mailboxes when the snapshot was taken. Always 0 for
+    /// an unbounded mailbox, whose length isn't visible to senders.
+    pub mailbox_depth: usize,
+}
+
+/// An agent's metrics for one type of message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTypeMetrics {
+    /// The name of the message type.
+    pub message_type: &'static str,
+    /// Messages of this type handled since the agent started.
+    pub messages_handled: u64,
+    /// How long the handler for this type took.
+    pub handler_latency: LatencyHistogram,
+    /// How long messages of this type waited in the mailbox before their handler ran.
+    pub queue_latency: LatencyHistogram,
+}
+
+/// A coarse histogram of durations, such as how long handlers took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// For each of [`LATENCY_BUCKETS`], the number of durations that were at most that long.
+    /// Counts are cumulative, so each includes the ones before it.
+    pub buckets: Vec<(Duration, u64)>,
+    /// The number of durations recorded.
+    pub count: u64,
+    /// Their combined duration.
+    pub sum: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            buckets: LATENCY_BUCKETS.iter().map(|bound| (*bound, 0)).collect(),
+            count: 0,
+            sum: Duration::ZERO,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Adds another histogram's counts to this one.
+    fn merge(&mut self, other: &LatencyHistogram) {
+        for ((_, count), (_, other_count)) in self.buckets.iter_mut().zip(&other.buckets) {
+            *count += other_count;
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+    }
+}
+
+/// Records an agent's metrics as it handles messages. Shared by the agent and its handles.
+#[derive(Debug, Default)]
+pub(crate) struct MetricsRecorder {
+    by_type: DashMap<TypeId, TypeRecorder>,
+}
+
+/// The counters for one message type.
+#[derive(Debug)]
+struct TypeRecorder {
+    message_type: &'static str,
+    handler: HistogramRecorder,
+    queue: HistogramRecorder,
+}
+
+/// The counters behind one `LatencyHistogram`.
+#[derive(Debug, Default)]
+struct HistogramRecorder {
+    /// Per-bucket counts, not cumulative, so recording touches a single bucket.
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl MetricsRecorder {
+    /// Records one handled message of the given type that took `elapsed`.
+    pub(crate) fn record(&self, type_id: TypeId, message_type: &'static str, elapsed: Duration) {
+        self.for_type(type_id, message_type, |recorder| recorder.handler.record(elapsed));
+    }
+
+    /// Records how long a message of the given type waited in the mailbox.
+    pub(crate) fn record_queue_latency(&self, type_id: TypeId, message_type: &'static str, waited: Duration) {
+        self.for_type(type_id, message_type, |recorder| recorder.queue.record(waited));
+    }
+
+    fn for_type(&self, type_id: TypeId, message_type: &'static str, record: impl FnOnce(&TypeRecorder)) {
+        // Only the first message of a type needs the write lock
+        match self.by_type.get(&type_id) {
+            Some(recorder) => record(&recorder),
+            None => record(&self.by_type.entry(type_id).or_insert_with(|| TypeRecorder::new(message_type))),
+        }
+    }
+
+    /// Takes a snapshot of the recorded metrics.
+    pub(crate) fn snapshot(&self, mailbox_depth: usize) -> AgentMetrics {
+        let mut by_message_type: Vec<MessageTypeMetrics> =
+            self.by_type.iter().map(|recorder| recorder.snapshot()).collect();
+        by_message_type.sort_by_key(|metrics| metrics.message_type);
+        let mut handler_latency = LatencyHistogram::default();
+        let mut queue_latency = LatencyHistogram::default();
+        for metrics in &by_message_type {
+            handler_latency.merge(&metrics.handler_latency);
+            queue_latency.merge(&metrics.queue_latency);
+        }
+        AgentMetrics {
+            messages_handled: handler_latency.count,
+            handler_latency,
+            queue_latency,
+            by_message_type,
+            mailbox_depth,
+        }
+    }
+}
+
+impl TypeRecorder {
+    fn new(message_type: &'static str) -> Self {
+        TypeRecorder {
+            message_type,
+            handler: HistogramRecorder::default(),
+            queue: HistogramRecorder::default(),
+        }
+    }
+
+    fn snapshot(&self) -> MessageTypeMetrics {
+        let handler_latency = self.handler.snapshot();
+        MessageTypeMetrics {
+            message_type: self.message_type,
+            messages_handled: handler_latency.count,
+            handler_latency,
+            queue_latency: self.queue.snapshot(),
+        }
+    }
+}
+
+impl HistogramRecorder {
+    fn record(&self, elapsed: Duration) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| elapsed <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn snapshot(&self) -> LatencyHistogram {
+        let count = self.count.load(Ordering::Relaxed);
+        let mut total = 0;
+        let buckets = LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(bound, bucket)| {
+                total += bucket.load(Ordering::Relaxed);
+                (*bound, total)
+            })
+            .collect();
+        LatencyHistogram {
+            buckets,
+            count,
+            sum: Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
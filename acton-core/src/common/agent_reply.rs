@@ -54,4 +54,19 @@ impl AgentReply {
     {
         Box::pin(future)
     }
+
+    /// Creates a future that succeeds immediately, for handlers added with `act_on_fallible`.
+    pub fn ok() -> Pin<Box<impl Future<Output=anyhow::Result<()>> + Sized>> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    /// Wraps a fallible future in a pinned box, for handlers added with `act_on_fallible`.
+    ///
+    /// An error it returns is passed to the agent's `on_error` reactor.
+    pub fn from_fallible<F>(future: F) -> Pin<Box<F>>
+    where
+        F: Future<Output=anyhow::Result<()>> + Sized,
+    {
+        Box::pin(future)
+    }
 }
\ No newline at end of file
@@ -14,19 +14,28 @@
  * limitations under that License.
  */
 
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
 use acton_ern::Ern;
+use anyhow::anyhow;
 use futures::future::join_all;
+use tokio::runtime::{Handle, RuntimeFlavor};
 use tokio::sync::oneshot;
-use tracing::trace;
+use tokio::time::{timeout_at, Instant};
+use dashmap::DashMap;
+use tracing::{error, trace, warn};
 
 use crate::actor::{AgentConfig, Idle, ManagedAgent};
-use crate::common::{ActonApp, AgentBroker, AgentHandle, BrokerRef};
+use crate::common::{ActonApp, AgentBroker, AgentHandle, BrokerRef, DeadLetterOffice, DEFAULT_BROADCAST_CONCURRENCY};
 use crate::common::acton_inner::ActonInner;
-use crate::traits::Actor;
+use crate::message::MessageError;
+use crate::traits::{ActonMessage, Actor};
 
 /// Represents a ready state of the Acton system.
 ///
@@ -133,6 +142,93 @@ impl AgentRuntime {
         self.0.broker.clone()
     }
 
+    /// Looks up a started agent, root or child, by its ERN.
+    ///
+    /// Agents are removed once they stop.
+    pub fn find(&self, ern: &Ern) -> Option<AgentHandle> {
+        self.0.registry.get(ern).map(|entry| entry.value().clone())
+    }
+
+    /// Finds every started agent whose ERN, in its string form, starts with `prefix`.
+    ///
+    /// A child's ERN extends its parent's, so passing an agent's ERN finds the agent and
+    /// all of its descendants.
+    pub fn find_prefix(&self, prefix: &str) -> Vec<AgentHandle> {
+        self.0
+            .registry
+            .iter()
+            .filter(|entry| entry.key().to_string().starts_with(prefix))
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Finds every started agent whose ERN matches `pattern`, segment by segment.
+    ///
+    /// The pattern is a path of ERN segments, root first, such as `tenant/*`. A segment
+    /// matches by its full text or by its name without the unique suffix an ERN adds, so
+    /// `tenant` matches the root of `Ern::with_root("tenant")`. `*` matches any one segment
+    /// and `**` any number of them, including none. Unlike [`AgentRuntime::find_prefix`],
+    /// `tenant/*` finds the children of `tenant` but not `tenant` itself, nor `tenants`.
+    pub fn find_matching(&self, pattern: &str) -> Vec<AgentHandle> {
+        let pattern: Vec<&str> = pattern.split('/').filter(|segment| !segment.is_empty()).collect();
+        self.0
+            .registry
+            .iter()
+            .filter(|entry| ern_matches(&pattern, entry.key()))
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Sends a clone of the message to every started agent whose ERN matches `pattern`; see
+    /// [`AgentRuntime::find_matching`] for the pattern syntax.
+    ///
+    /// A failed send doesn't stop the others; the ERN of each agent that couldn't be sent
+    /// to is returned along with its error.
+    pub fn send_matching(&self, pattern: &str, message: impl ActonMessage + Clone + 'static) -> Vec<(Ern, MessageError)> {
+        self.find_matching(pattern)
+            .into_iter()
+            .filter_map(|agent| {
+                trace!(actor = agent.id.to_string(), "Sending to agent matching {}", pattern);
+                agent.create_envelope(None).reply(message.clone()).err().map(|e| (agent.id(), e))
+            })
+            .collect()
+    }
+
+    /// Renders the metrics of every started agent configured with
+    /// `AgentConfig::with_metrics`, in the Prometheus text exposition format.
+    ///
+    /// Serve the result from your own HTTP endpoint for Prometheus to scrape.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics_text(&self) -> String {
+        // Copy the handles out first, so snapshots are taken without holding the registry
+        let handles: Vec<AgentHandle> = self.0.registry.iter().map(|entry| entry.value().clone()).collect();
+        let mut agents: Vec<_> = handles
+            .iter()
+            .filter_map(|handle| handle.metrics().map(|metrics| (handle.id(), metrics)))
+            .collect();
+        agents.sort_by_key(|(id, _)| id.to_string());
+        crate::common::prometheus::render(&agents)
+    }
+
+    /// Sets how many subscribers the broker delivers broadcasts to at once.
+    ///
+    /// Each subscriber's broadcasts are queued and delivered in order, separately from every
+    /// other subscriber's, as described on [`AgentBroker::broadcast`]. The limit keeps large
+    /// subscriber sets from flooding the runtime with sends. A delivery waiting on a full
+    /// mailbox keeps its slot, so once that many subscribers are full, the rest wait too.
+    /// Defaults to 64, and zero is treated as one.
+    pub fn set_broadcast_concurrency(&self, limit: usize) {
+        self.0.broadcast_concurrency.store(limit, Ordering::Relaxed);
+    }
+
+    /// Retrieves the dead-letter agent for the system.
+    ///
+    /// Messages an agent has no handler for are forwarded here as a [`DeadLetter`](crate::message::DeadLetter),
+    /// and the dead-letter agent broadcasts them through the broker.
+    pub fn dead_letters(&self) -> AgentHandle {
+        self.0.dead_letters.clone()
+    }
+
     /// Spawns an actor with a custom setup function and configuration.
     ///
     /// # Type Parameters
@@ -185,11 +281,64 @@ impl AgentRuntime {
         for result in results {
             result?;
         }
+        self.0.dead_letters.stop().await?;
         self.0.broker.stop().await?;
 
         Ok(())
     }
 
+    /// Shuts down like [`AgentRuntime::shutdown_all`], but stops each root agent before the
+    /// agents it depends on, as declared with `AgentConfig::depends_on`.
+    ///
+    /// Agents that don't depend on one another stop concurrently. Dependencies on agents
+    /// that aren't roots are ignored, since those stop along with their parents. If the
+    /// dependencies form a cycle, a warning is logged and every agent is stopped at once.
+    pub async fn shutdown_ordered(&mut self) -> anyhow::Result<()> {
+        let roots: Vec<AgentHandle> = self.0.roots.iter().map(|item| item.value().clone()).collect();
+        let Some(waves) = shutdown_waves(roots, &self.0.dependencies) else {
+            warn!("Agent dependencies form a cycle, shutting down in no particular order");
+            return self.shutdown_all().await;
+        };
+        for wave in waves {
+            let results = join_all(wave.iter().map(|agent| agent.stop())).await;
+            for result in results {
+                result?;
+            }
+        }
+        self.0.dead_letters.stop().await?;
+        self.0.broker.stop().await?;
+
+        Ok(())
+    }
+
+    /// Stops every root agent, then the dead-letter agent and the broker, waiting up to ten
+    /// seconds in total.
+    ///
+    /// See [`AgentRuntime::shutdown_with_timeout`].
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.shutdown_with_timeout(RUNTIME_SHUTDOWN_TIMEOUT).await
+    }
+
+    /// Stops every root agent, then the dead-letter agent and the broker, and waits for all
+    /// of their tasks to finish.
+    ///
+    /// Each agent stops its own children first. Returns an error naming every agent that had
+    /// not stopped when `timeout` ran out.
+    pub async fn shutdown_with_timeout(self, timeout: Duration) -> anyhow::Result<()> {
+        let deadline = Instant::now() + timeout;
+        let roots: Vec<AgentHandle> = self.0.roots.iter().map(|item| item.value().clone()).collect();
+        let mut stuck = stop_all(&roots, deadline).await;
+        // The broker goes last so agents can still broadcast while they stop
+        stuck.extend(stop_all(std::slice::from_ref(&self.0.dead_letters), deadline).await);
+        stuck.extend(stop_all(std::slice::from_ref(&self.0.broker), deadline).await);
+
+        if stuck.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Agents failed to stop within {:?}: {}", timeout, stuck.join(", ")))
+        }
+    }
+
     /// Spawns an actor with a custom setup function and default configuration.
     ///
     /// # Type Parameters
@@ -226,19 +375,129 @@ impl AgentRuntime {
 }
 
 impl From<ActonApp> for AgentRuntime {
-    fn from(_acton: ActonApp) -> Self {
-        let (sender, receiver) = oneshot::channel();
+    fn from(acton: ActonApp) -> Self {
+        let broadcast_concurrency = Arc::new(AtomicUsize::new(DEFAULT_BROADCAST_CONCURRENCY));
+        let broker_concurrency = broadcast_concurrency.clone();
+        let spawner = acton.spawner.clone();
+        let policy = acton.broadcast_policy;
+        let initialize = async move {
+            // The dead-letter agent needs the broker, so the broker learns of it afterwards
+            let broker_dead_letters = Arc::new(OnceLock::new());
+            let broker =
+                AgentBroker::initialize(broker_concurrency, policy, broker_dead_letters.clone(), spawner.clone())
+                    .await;
+            let dead_letters = DeadLetterOffice::initialize(broker.clone(), spawner).await;
+            let _ = broker_dead_letters.set(dead_letters.clone());
+            (broker, dead_letters)
+        };
 
-        tokio::spawn(async move {
-            let broker = AgentBroker::initialize().await;
-            let _ = sender.send(broker);
-        });
+        let (broker, dead_letters) = match Handle::current().runtime_flavor() {
+            // A current-thread runtime can't block in place, as tests with paused time need.
+            // Starting the two agents only spawns their tasks, so it finishes without them.
+            RuntimeFlavor::CurrentThread => futures::executor::block_on(initialize),
+            _ => {
+                let (sender, receiver) = oneshot::channel();
+                tokio::spawn(async move {
+                    let _ = sender.send(initialize.await);
+                });
+                tokio::task::block_in_place(|| {
+                    Handle::current().block_on(async { receiver.await.expect("Broker initialization failed") })
+                })
+            }
+        };
 
-        let broker = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current()
-                .block_on(async { receiver.await.expect("Broker initialization failed") })
-        });
+        AgentRuntime(ActonInner {
+            broker,
+            dead_letters,
+            broadcast_concurrency,
+            spawner: acton.spawner,
+            ..Default::default()
+        })
+    }
+}
+
+/// How long [`AgentRuntime::shutdown`] waits for every agent to stop.
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
-        AgentRuntime(ActonInner { broker: broker.clone(), ..Default::default() })
+/// Groups `roots` into waves that can each stop concurrently, with every agent in an
+/// earlier wave than the agents it depends on. Returns `None` if the dependencies form a
+/// cycle.
+fn shutdown_waves(
+    roots: Vec<AgentHandle>,
+    dependencies: &DashMap<Ern, Vec<Ern>>,
+) -> Option<Vec<Vec<AgentHandle>>> {
+    // How many of the agents still running depend on each agent
+    let mut dependents: HashMap<Ern, usize> = roots.iter().map(|root| (root.id.clone(), 0)).collect();
+    let mut depends_on: HashMap<Ern, Vec<Ern>> = HashMap::new();
+    for root in &roots {
+        let among_roots: Vec<Ern> = dependencies
+            .get(&root.id)
+            .map(|entry| entry.iter().filter(|id| dependents.contains_key(*id)).cloned().collect())
+            .unwrap_or_default();
+        for id in &among_roots {
+            *dependents.entry(id.clone()).or_default() += 1;
+        }
+        depends_on.insert(root.id.clone(), among_roots);
     }
+
+    let mut waves = Vec::new();
+    let mut remaining = roots;
+    while !remaining.is_empty() {
+        let (wave, rest): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|agent| dependents[&agent.id] == 0);
+        if wave.is_empty() {
+            return None;
+        }
+        for id in wave.iter().flat_map(|agent| &depends_on[&agent.id]) {
+            *dependents.entry(id.clone()).or_default() -= 1;
+        }
+        waves.push(wave);
+        remaining = rest;
+    }
+    Some(waves)
+}
+
+/// Whether an ERN's segments, its root then its parts, match the pattern's.
+fn ern_matches(pattern: &[&str], ern: &Ern) -> bool {
+    let root = ern.root.to_string();
+    let parts = ern.parts.to_string();
+    let segments: Vec<&str> =
+        std::iter::once(root.as_str()).chain(parts.split('/').filter(|part| !part.is_empty())).collect();
+    segments_match(pattern, &segments)
+}
+
+fn segments_match(pattern: &[&str], segments: &[&str]) -> bool {
+    match (pattern.split_first(), segments.split_first()) {
+        (None, None) => true,
+        // Either `**` matches nothing more, or it takes this segment and maybe others
+        (Some((&"**", rest)), _) => {
+            segments_match(rest, segments) || (!segments.is_empty() && segments_match(pattern, &segments[1..]))
+        }
+        (Some((wanted, rest)), Some((segment, remaining))) => {
+            segment_matches(wanted, segment) && segments_match(rest, remaining)
+        }
+        _ => false,
+    }
+}
+
+/// Whether one pattern segment matches one ERN segment, with or without its unique suffix.
+fn segment_matches(wanted: &str, segment: &str) -> bool {
+    wanted == "*" || wanted == segment || segment.rsplit_once('_').is_some_and(|(name, _)| name == wanted)
+}
+
+/// Stops `agents` concurrently, returning the ids of those that didn't stop by `deadline`.
+async fn stop_all(agents: &[AgentHandle], deadline: Instant) -> Vec<String> {
+    let stops = agents.iter().map(|agent| async move {
+        match timeout_at(deadline, agent.stop()).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => {
+                error!(actor = agent.id.to_string(), "Failed to stop: {}", e);
+                Some(agent.id.to_string())
+            }
+            Err(_) => {
+                error!(actor = agent.id.to_string(), "Timed out waiting to stop");
+                Some(agent.id.to_string())
+            }
+        }
+    });
+    join_all(stops).await.into_iter().flatten().collect()
 }
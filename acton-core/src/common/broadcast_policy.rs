@@ -0,0 +1,34 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use crate::actor::RetryPolicy;
+
+/// What the broker does when a subscriber's mailbox is full during a broadcast.
+///
+/// Set it when launching the runtime with [`ActonApp::launch_with_broadcast_policy`].
+///
+/// [`ActonApp::launch_with_broadcast_policy`]: crate::common::ActonApp::launch_with_broadcast_policy
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastPolicy {
+    /// The broker waits for room in the subscriber's mailbox, however long it takes. This is
+    /// the default. Other subscribers are still served while it waits, up to the broadcast
+    /// concurrency, but the broker takes no new broadcast until every delivery is done.
+    #[default]
+    BlockForever,
+    /// The broker retries the delivery on the policy's schedule, then gives up and sends
+    /// the message to the dead-letter agent with `DeadLetterReason::SubscriberFull`.
+    RetryThenDrop(RetryPolicy),
+}
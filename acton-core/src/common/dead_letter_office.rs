@@ -0,0 +1,57 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use acton_ern::Ern;
+use tracing::*;
+
+use crate::actor::{AgentConfig, Idle, ManagedAgent};
+use crate::common::{AgentHandle, BrokerRef, SpawnerRef};
+use crate::message::{BrokerRequest, DeadLetter};
+use crate::traits::Actor;
+
+/// The agent that receives messages no handler could take and republishes them on the broker.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct DeadLetterOffice;
+
+impl DeadLetterOffice {
+    #[instrument(skip(broker))]
+    pub(crate) async fn initialize(broker: BrokerRef, spawner: SpawnerRef) -> AgentHandle {
+        let actor_config = AgentConfig::new(Ern::with_root("dead_letters").unwrap(), None, Some(broker))
+            .expect("Couldn't create dead letter config");
+
+        let mut office: ManagedAgent<Idle, DeadLetterOffice> =
+            ManagedAgent::new(&None, Some(actor_config)).await;
+        office.handle.spawner = spawner;
+
+        office.act_on::<DeadLetter>(|agent, context| {
+            let dead_letter = context.message().clone();
+            warn!(
+                sender = dead_letter.envelope.reply_to.sender.to_string(),
+                recipient = dead_letter.envelope.recipient.sender.to_string(),
+                reason = ?dead_letter.reason,
+                "Dead letter: {:?}",
+                dead_letter.envelope.message
+            );
+            let broker = agent.broker.clone();
+            Box::pin(async move {
+                broker.send(BrokerRequest::new(dead_letter)).await;
+            })
+        });
+
+        trace!("Activating the dead letter office.");
+        office.start().await
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::message::MessageError;
+
+/// A handle to a message sent with
+/// [`AgentHandle::send_reliable`](crate::common::AgentHandle::send_reliable).
+///
+/// Dropping the handle does not stop the redeliveries.
+#[derive(Debug)]
+pub struct DeliveryHandle {
+    delivery_id: Uuid,
+    outcome: oneshot::Receiver<Result<(), MessageError>>,
+}
+
+impl DeliveryHandle {
+    pub(crate) fn new(delivery_id: Uuid, outcome: oneshot::Receiver<Result<(), MessageError>>) -> Self {
+        DeliveryHandle { delivery_id, outcome }
+    }
+
+    /// Returns the delivery id every attempt carries.
+    pub fn delivery_id(&self) -> Uuid {
+        self.delivery_id
+    }
+
+    /// Waits until the message is acknowledged or the sender gives up.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::NotAcknowledged` if no attempt was acknowledged in time.
+    pub async fn outcome(self) -> Result<(), MessageError> {
+        self.outcome
+            .await
+            .unwrap_or_else(|_| Err(MessageError::SendFailed("the delivery was abandoned".into())))
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::time::Duration;
+
+/// An agent's health, from `AgentHandle::health`, for liveness and readiness probes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthStatus {
+    /// Whether the agent is taking messages, and if not, why.
+    pub state: HealthState,
+    /// How long since the agent last took a message, or `None` if it hasn't taken one.
+    pub idle_for: Option<Duration>,
+}
+
+impl HealthStatus {
+    /// Returns whether the agent is still running, whatever it is doing.
+    pub fn is_live(&self) -> bool {
+        self.state != HealthState::Stopped
+    }
+
+    /// Returns whether the agent is running, ready, and taking messages.
+    pub fn is_ready(&self) -> bool {
+        self.state == HealthState::Healthy
+    }
+}
+
+/// What an agent is doing, as far as a health probe is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthState {
+    /// The agent is taking messages, and its readiness check, if it has one, passes.
+    Healthy,
+    /// The agent is taking messages, but its readiness check fails.
+    NotReady,
+    /// The agent was suspended with `AgentHandle::suspend`, and holds new messages until it
+    /// resumes.
+    Suspended,
+    /// The agent was told to drain with `AgentHandle::drain`: it is finishing the messages
+    /// already queued and refuses new ones.
+    Draining,
+    /// The agent's mailbox is closed because it has stopped or is stopping.
+    Stopped,
+}
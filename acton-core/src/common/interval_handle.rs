@@ -0,0 +1,49 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use tokio_util::sync::CancellationToken;
+
+/// A handle to a recurring message started with
+/// [`AgentHandle::schedule_interval`](crate::common::AgentHandle::schedule_interval).
+///
+/// The interval runs until [`IntervalHandle::stop`] is called, the handle is dropped,
+/// or the agent stops.
+#[derive(Debug)]
+pub struct IntervalHandle {
+    cancellation_token: CancellationToken,
+}
+
+impl IntervalHandle {
+    pub(crate) fn new(cancellation_token: CancellationToken) -> Self {
+        IntervalHandle { cancellation_token }
+    }
+
+    /// Stops the interval. No further messages are sent.
+    pub fn stop(&self) {
+        self.cancellation_token.cancel();
+    }
+
+    /// Returns `true` if the interval is no longer sending messages.
+    pub fn is_stopped(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+}
+
+impl Drop for IntervalHandle {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+
+use acton_ern::Ern;
+use futures::future::{FutureExt, Shared};
+
+use crate::common::AgentHandle;
+use crate::traits::{ActonMessage, Actor};
+
+/// Starts a prepared agent, returning its handle.
+type PendingStart = Pin<Box<dyn Future<Output = AgentHandle> + Send + 'static>>;
+
+/// A handle to an agent that starts the first time it is used.
+///
+/// Get one with [`ManagedAgent::prepare`](crate::actor::ManagedAgent::prepare). Until
+/// then the agent has no task and its `before_start` and `after_start` hooks haven't run.
+/// The first `send` or `handle` starts it; callers racing to be first all wait for the same
+/// start, which happens only once. A start left unfinished by a cancelled caller carries on
+/// with the next one. Clones share the agent.
+#[derive(Clone)]
+pub struct LazyAgentHandle {
+    id: Ern,
+    start: Shared<PendingStart>,
+}
+
+impl LazyAgentHandle {
+    pub(crate) fn new(id: Ern, start: PendingStart) -> Self {
+        LazyAgentHandle { id, start: start.shared() }
+    }
+
+    /// Returns the agent's ERN, without starting it.
+    pub fn id(&self) -> &Ern {
+        &self.id
+    }
+
+    /// Returns whether the agent has been started.
+    pub fn is_started(&self) -> bool {
+        self.start.peek().is_some()
+    }
+
+    /// Starts the agent if it hasn't started yet, and returns its handle.
+    pub async fn handle(&self) -> AgentHandle {
+        self.start.clone().await
+    }
+
+    /// Starts the agent if it hasn't started yet, then sends it a message.
+    pub async fn send(&self, message: impl ActonMessage + 'static) {
+        self.handle().await.send(message).await;
+    }
+}
+
+impl Debug for LazyAgentHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyAgentHandle")
+            .field("id", &self.id)
+            .field("started", &self.is_started())
+            .finish()
+    }
+}
@@ -0,0 +1,353 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::error::{SendError, TrySendError};
+use tokio::sync::mpsc::{channel, unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use tokio::time::Instant;
+
+use crate::common::MailboxFullHook;
+#[cfg(feature = "testing")]
+use crate::common::Recording;
+use crate::message::{Envelope, MessageError};
+#[cfg(feature = "testing")]
+use crate::traits::ActonMessage;
+
+/// The sending half of an agent's mailbox.
+#[derive(Clone, Debug)]
+pub(crate) struct Outbox {
+    channel: OutboxChannel,
+    /// Set by `AgentHandle::drain`; shared by every clone of the outbox.
+    draining: Arc<AtomicBool>,
+    /// Envelopes sent to the agent that it hasn't finished handling, counted by senders and
+    /// settled by the agent after each one.
+    unhandled: Arc<AtomicUsize>,
+    /// How many envelopes have been sent to the agent, which stamps each envelope with its
+    /// place in line.
+    sent: Arc<AtomicU64>,
+    /// Tells the agent's `on_mailbox_full` hook when senders find the mailbox full.
+    overflow: Arc<OverflowAlarm>,
+    /// Every message sent with this outbox's agent as the sender, when recording is on.
+    #[cfg(feature = "testing")]
+    recording: Option<Recording>,
+}
+
+#[derive(Clone, Debug)]
+enum OutboxChannel {
+    /// A mailbox with a fixed capacity; senders wait while it is full.
+    Bounded(Sender<Envelope>),
+    /// A mailbox that grows as needed; sending never waits.
+    Unbounded(UnboundedSender<Envelope>),
+}
+
+/// The receiving half of an agent's mailbox.
+#[derive(Debug)]
+pub(crate) enum Inbox {
+    Bounded(Receiver<Envelope>),
+    Unbounded(UnboundedReceiver<Envelope>),
+}
+
+/// Creates a mailbox holding up to `capacity` envelopes.
+pub(crate) fn bounded_mailbox(capacity: usize) -> (Outbox, Inbox) {
+    let (outbox, inbox) = channel(capacity);
+    (Outbox::new(OutboxChannel::Bounded(outbox)), Inbox::Bounded(inbox))
+}
+
+/// Creates a mailbox with no capacity limit.
+pub(crate) fn unbounded_mailbox() -> (Outbox, Inbox) {
+    let (outbox, inbox) = unbounded_channel();
+    (Outbox::new(OutboxChannel::Unbounded(outbox)), Inbox::Unbounded(inbox))
+}
+
+impl Outbox {
+    fn new(channel: OutboxChannel) -> Self {
+        Outbox {
+            channel,
+            draining: Arc::default(),
+            unhandled: Arc::default(),
+            sent: Arc::default(),
+            overflow: Arc::default(),
+            #[cfg(feature = "testing")]
+            recording: None,
+        }
+    }
+
+    /// Starts recording the messages the agent sends. Clones made afterwards share the record.
+    #[cfg(feature = "testing")]
+    pub(crate) fn start_recording(&mut self) {
+        self.recording = Some(Arc::default());
+    }
+
+    /// Adds a message the agent sent to the record, if it is recording.
+    #[cfg(feature = "testing")]
+    pub(crate) fn record(&self, message: &dyn ActonMessage) {
+        if let Some(recording) = &self.recording {
+            recording.lock().unwrap().push(dyn_clone::clone_box(message));
+        }
+    }
+
+    /// Returns copies of the recorded messages, oldest first.
+    #[cfg(feature = "testing")]
+    pub(crate) fn recorded(&self) -> Vec<Box<dyn ActonMessage>> {
+        self.recording.as_ref().map_or_else(Vec::new, |recording| {
+            recording.lock().unwrap().iter().map(|message| dyn_clone::clone_box(&**message)).collect()
+        })
+    }
+
+    /// Shares `other`'s counts of sent and unhandled envelopes, so that envelopes sent
+    /// through either outbox are counted together.
+    pub(crate) fn sharing_count_with(mut self, other: &Outbox) -> Self {
+        self.unhandled = other.unhandled.clone();
+        self.sent = other.sent.clone();
+        self
+    }
+
+    /// Stamps `envelope` with how many envelopes were sent to the agent before it, and when.
+    fn stamp(&self, mut envelope: Envelope) -> Envelope {
+        envelope.sequence = self.sent.fetch_add(1, Ordering::SeqCst);
+        envelope.enqueued_at = Instant::now();
+        envelope
+    }
+
+    /// Counts `envelopes` more envelopes as waiting to be handled.
+    pub(crate) fn count_sent(&self, envelopes: usize) {
+        self.unhandled.fetch_add(envelopes, Ordering::SeqCst);
+    }
+
+    /// Marks one envelope as handled.
+    pub(crate) fn count_handled(&self) {
+        let _ = self.unhandled.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+
+    /// Returns how many envelopes were sent and not yet handled, counting the one being
+    /// handled.
+    #[cfg(feature = "testing")]
+    pub(crate) fn unhandled(&self) -> usize {
+        self.unhandled.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the mailbox has no capacity limit, so sending never waits.
+    pub(crate) fn is_unbounded(&self) -> bool {
+        matches!(self.channel, OutboxChannel::Unbounded(_))
+    }
+
+    /// Sets the hook called when a sender finds the mailbox full.
+    pub(crate) fn on_full(&self, hook: MailboxFullHook) {
+        *self.overflow.hook.lock().unwrap() = Some(hook);
+    }
+
+    /// Ends an overload, so the hook is called again the next time the mailbox fills up.
+    pub(crate) fn clear_overflow(&self) {
+        self.overflow.raised.store(false, Ordering::SeqCst);
+    }
+
+    /// Sends an envelope without waiting, failing if a bounded mailbox is full. The rejected
+    /// envelope is dropped.
+    pub(crate) fn try_send(&self, envelope: Envelope) -> Result<(), TrySendError<()>> {
+        // Counted first, so the agent can never settle it before it is counted
+        self.count_sent(1);
+        let envelope = self.stamp(envelope);
+        let sent = match &self.channel {
+            OutboxChannel::Bounded(sender) => sender.try_send(envelope).map_err(|e| match e {
+                TrySendError::Full(_) => {
+                    self.overflow.raise(self.len());
+                    TrySendError::Full(())
+                }
+                TrySendError::Closed(_) => TrySendError::Closed(()),
+            }),
+            OutboxChannel::Unbounded(sender) => sender.send(envelope).map_err(|_| TrySendError::Closed(())),
+        };
+        if sent.is_err() {
+            self.count_handled();
+        }
+        sent
+    }
+
+    /// Stops the mailbox from taking new messages from senders, while the agent finishes
+    /// the ones already in it.
+    pub(crate) fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the agent is draining and refuses new messages.
+    pub(crate) fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Sends an envelope, waiting for room if the mailbox is bounded and full.
+    pub(crate) async fn send(&self, envelope: Envelope) -> Result<(), SendError<Envelope>> {
+        self.count_sent(1);
+        let envelope = self.stamp(envelope);
+        let sent = match &self.channel {
+            OutboxChannel::Bounded(sender) => {
+                if sender.capacity() == 0 {
+                    self.overflow.raise(self.len());
+                }
+                sender.send(envelope).await
+            }
+            OutboxChannel::Unbounded(sender) => sender.send(envelope),
+        };
+        if sent.is_err() {
+            self.count_handled();
+        }
+        sent
+    }
+
+    /// Sends an envelope, blocking the thread until a bounded mailbox has room. Panics if
+    /// called from within the tokio runtime.
+    pub(crate) fn blocking_send(&self, envelope: Envelope) -> Result<(), SendError<()>> {
+        self.count_sent(1);
+        let envelope = self.stamp(envelope);
+        let sent = match &self.channel {
+            OutboxChannel::Bounded(sender) => {
+                if sender.capacity() == 0 {
+                    self.overflow.raise(self.len());
+                }
+                sender.blocking_send(envelope).map_err(|_| SendError(()))
+            }
+            OutboxChannel::Unbounded(sender) => sender.send(envelope).map_err(|_| SendError(())),
+        };
+        if sent.is_err() {
+            self.count_handled();
+        }
+        sent
+    }
+
+    /// Sends envelopes in order, reserving room for as many as the mailbox can hold at once
+    /// before sending any of them.
+    pub(crate) async fn send_all(&self, envelopes: Vec<Envelope>) -> Result<(), SendError<()>> {
+        match &self.channel {
+            OutboxChannel::Bounded(sender) => {
+                let mut envelopes = envelopes.into_iter();
+                while envelopes.len() > 0 {
+                    if sender.capacity() == 0 {
+                        self.overflow.raise(self.len());
+                    }
+                    let permits = sender.reserve_many(envelopes.len().min(sender.max_capacity())).await?;
+                    for (permit, envelope) in permits.zip(envelopes.by_ref()) {
+                        self.count_sent(1);
+                        permit.send(self.stamp(envelope));
+                    }
+                }
+                Ok(())
+            }
+            OutboxChannel::Unbounded(sender) => {
+                for envelope in envelopes {
+                    self.count_sent(1);
+                    sender.send(self.stamp(envelope)).map_err(|_| {
+                        self.count_handled();
+                        SendError(())
+                    })?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns whether the receiving agent has stopped listening.
+    pub(crate) fn is_closed(&self) -> bool {
+        match &self.channel {
+            OutboxChannel::Bounded(sender) => sender.is_closed(),
+            OutboxChannel::Unbounded(sender) => sender.is_closed(),
+        }
+    }
+
+    /// Returns the number of envelopes waiting, or 0 for an unbounded mailbox, whose length
+    /// isn't visible to senders.
+    pub(crate) fn len(&self) -> usize {
+        match &self.channel {
+            OutboxChannel::Bounded(sender) => sender.max_capacity() - sender.capacity(),
+            OutboxChannel::Unbounded(_) => 0,
+        }
+    }
+
+    /// Returns the mailbox capacity, or `usize::MAX` for an unbounded mailbox.
+    pub(crate) fn max_capacity(&self) -> usize {
+        match &self.channel {
+            OutboxChannel::Bounded(sender) => sender.max_capacity(),
+            OutboxChannel::Unbounded(_) => usize::MAX,
+        }
+    }
+}
+
+/// Calls an agent's `on_mailbox_full` hook once each time its mailbox fills up, rather than
+/// for every sender that finds it full.
+#[derive(Default)]
+struct OverflowAlarm {
+    hook: Mutex<Option<MailboxFullHook>>,
+    /// Set from when the hook is called until the agent empties its mailbox.
+    raised: AtomicBool,
+}
+
+impl OverflowAlarm {
+    fn raise(&self, depth: usize) {
+        if self.raised.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Cloned out, so the lock isn't held while the hook runs
+        let hook = self.hook.lock().unwrap().clone();
+        if let Some(hook) = hook {
+            hook(depth);
+        }
+    }
+}
+
+impl Debug for OverflowAlarm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OverflowAlarm").field("raised", &self.raised).finish_non_exhaustive()
+    }
+}
+
+impl Inbox {
+    pub(crate) async fn recv(&mut self) -> Option<Envelope> {
+        match self {
+            Inbox::Bounded(receiver) => receiver.recv().await,
+            Inbox::Unbounded(receiver) => receiver.recv().await,
+        }
+    }
+
+    /// Takes the next envelope if one is waiting, without waiting for one.
+    pub(crate) fn try_recv(&mut self) -> Option<Envelope> {
+        match self {
+            Inbox::Bounded(receiver) => receiver.try_recv().ok(),
+            Inbox::Unbounded(receiver) => receiver.try_recv().ok(),
+        }
+    }
+
+    pub(crate) fn close(&mut self) {
+        match self {
+            Inbox::Bounded(receiver) => receiver.close(),
+            Inbox::Unbounded(receiver) => receiver.close(),
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        match self {
+            Inbox::Bounded(receiver) => receiver.is_closed(),
+            Inbox::Unbounded(receiver) => receiver.is_closed(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        match self {
+            Inbox::Bounded(receiver) => receiver.is_empty(),
+            Inbox::Unbounded(receiver) => receiver.is_empty(),
+        }
+    }
+}
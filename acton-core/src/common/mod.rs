@@ -15,10 +15,32 @@
  */
 pub use acton::ActonApp;
 pub(crate) use acton_inner::ActonInner;
+pub(crate) use agent_activity::AgentActivity;
 pub use agent_broker::AgentBroker;
+pub(crate) use agent_broker::DEFAULT_BROADCAST_CONCURRENCY;
+pub use broadcast_policy::BroadcastPolicy;
 pub use agent_handle::AgentHandle;
+pub(crate) use agent_handle::abandon_ask;
+pub use agent_metrics::{AgentMetrics, LatencyHistogram, MessageTypeMetrics, LATENCY_BUCKETS};
+pub(crate) use agent_metrics::MetricsRecorder;
 pub use agent_reply::AgentReply;
 pub use agent_runtime::AgentRuntime;
+pub(crate) use dead_letter_office::DeadLetterOffice;
+pub use delivery_handle::DeliveryHandle;
+pub use health_status::{HealthState, HealthStatus};
+pub use interval_handle::IntervalHandle;
+pub use lazy_agent_handle::LazyAgentHandle;
+pub(crate) use mailbox::{bounded_mailbox, unbounded_mailbox, Inbox, Outbox};
+pub use scheduled_handle::ScheduledHandle;
+pub use spawner::{SpawnedTask, Spawner, TokioSpawner};
+pub(crate) use spawner::SpawnerRef;
+pub use subscriptions::Subscriptions;
+#[cfg(feature = "testing")]
+pub use test_clock::TestClock;
+#[cfg(feature = "testing")]
+pub use test_probe::TestProbe;
+pub use type_map::TypeMap;
+pub use typed_agent_handle::TypedAgentHandle;
 pub(crate) use types::*;
 
 pub(crate) use crate::message::{Envelope, MessageError, OutboundEnvelope};
@@ -29,7 +51,27 @@ mod types;
 
 mod acton;
 mod acton_inner;
+mod agent_activity;
 mod agent_handle;
+mod agent_metrics;
 mod agent_broker;
 mod agent_runtime;
 mod agent_reply;
+mod broadcast_policy;
+mod dead_letter_office;
+mod health_status;
+mod delivery_handle;
+mod lazy_agent_handle;
+mod interval_handle;
+mod mailbox;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod scheduled_handle;
+mod spawner;
+mod subscriptions;
+#[cfg(feature = "testing")]
+mod test_clock;
+#[cfg(feature = "testing")]
+mod test_probe;
+mod type_map;
+mod typed_agent_handle;
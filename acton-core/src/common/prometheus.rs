@@ -0,0 +1,83 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::fmt::Write;
+
+use acton_ern::Ern;
+
+use crate::common::{AgentMetrics, LatencyHistogram};
+
+/// Renders agents' metrics in the Prometheus text exposition format.
+pub(crate) fn render(agents: &[(Ern, AgentMetrics)]) -> String {
+    let mut text = String::new();
+
+    header(&mut text, "acton_messages_total", "counter", "Messages handled by an agent.");
+    for (id, metrics) in agents {
+        for by_type in &metrics.by_message_type {
+            let labels = labels(id, by_type.message_type);
+            let _ = writeln!(text, "acton_messages_total{{{labels}}} {}", by_type.messages_handled);
+        }
+    }
+
+    header(&mut text, "acton_handler_seconds", "histogram", "How long an agent's handlers took.");
+    for (id, metrics) in agents {
+        for by_type in &metrics.by_message_type {
+            histogram(&mut text, "acton_handler_seconds", &labels(id, by_type.message_type), &by_type.handler_latency);
+        }
+    }
+
+    header(
+        &mut text,
+        "acton_queue_seconds",
+        "histogram",
+        "How long messages waited in an agent's mailboxes before their handlers ran.",
+    );
+    for (id, metrics) in agents {
+        for by_type in &metrics.by_message_type {
+            histogram(&mut text, "acton_queue_seconds", &labels(id, by_type.message_type), &by_type.queue_latency);
+        }
+    }
+
+    header(&mut text, "acton_mailbox_depth", "gauge", "Messages waiting in an agent's mailboxes.");
+    for (id, metrics) in agents {
+        let _ = writeln!(text, "acton_mailbox_depth{{agent=\"{}\"}} {}", escape(&id.to_string()), metrics.mailbox_depth);
+    }
+
+    text
+}
+
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {name} {help}");
+    let _ = writeln!(text, "# TYPE {name} {kind}");
+}
+
+fn histogram(text: &mut String, name: &str, labels: &str, latency: &LatencyHistogram) {
+    for (bound, count) in &latency.buckets {
+        let _ = writeln!(text, "{name}_bucket{{{labels},le=\"{}\"}} {count}", bound.as_secs_f64());
+    }
+    let _ = writeln!(text, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", latency.count);
+    let _ = writeln!(text, "{name}_sum{{{labels}}} {}", latency.sum.as_secs_f64());
+    let _ = writeln!(text, "{name}_count{{{labels}}} {}", latency.count);
+}
+
+fn labels(id: &Ern, message_type: &str) -> String {
+    format!("agent=\"{}\",message_type=\"{}\"", escape(&id.to_string()), escape(message_type))
+}
+
+/// Escapes a label value as the format requires.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,71 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+const PENDING: u8 = 0;
+const FIRED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+/// A handle to a message scheduled with [`AgentHandle::schedule`](crate::common::AgentHandle::schedule).
+///
+/// Dropping the handle does not cancel the delivery.
+#[derive(Debug, Clone)]
+pub struct ScheduledHandle {
+    state: Arc<AtomicU8>,
+    cancellation_token: CancellationToken,
+}
+
+impl ScheduledHandle {
+    pub(crate) fn new(cancellation_token: CancellationToken) -> Self {
+        ScheduledHandle {
+            state: Arc::new(AtomicU8::new(PENDING)),
+            cancellation_token,
+        }
+    }
+
+    /// Cancels the delivery if it has not fired yet.
+    ///
+    /// Returns `true` if the message will not be delivered, or `false` if it was already
+    /// on its way. Only one of `cancel` and the delivery can win, so a `true` result is final.
+    pub fn cancel(&self) -> bool {
+        let cancelled = self
+            .state
+            .compare_exchange(PENDING, CANCELLED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        self.cancellation_token.cancel();
+        cancelled || self.state.load(Ordering::Acquire) == CANCELLED
+    }
+
+    /// Returns `true` once the delay has elapsed and the message is being, or has been, delivered.
+    pub fn is_fired(&self) -> bool {
+        self.state.load(Ordering::Acquire) == FIRED
+    }
+
+    /// Claims the right to deliver the message. Returns `false` if it was cancelled first.
+    pub(crate) fn fire(&self) -> bool {
+        self.state
+            .compare_exchange(PENDING, FIRED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    pub(crate) fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+}
@@ -0,0 +1,69 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio_util::task::TaskTracker;
+
+/// A task the runtime hands to a [`Spawner`] to run.
+pub type SpawnedTask = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Runs the tasks the runtime starts for its agents: each agent's message loop, scheduled
+/// and repeating messages, reliable deliveries, piped futures and parallel handlers.
+///
+/// Set one with [`ActonApp::launch_with_spawner`](crate::common::ActonApp::launch_with_spawner)
+/// to wrap those tasks with tracing or metrics, or to run them somewhere other than the
+/// current tokio runtime. Tasks still rely on tokio's timers and channels, so they must run
+/// inside a tokio runtime.
+pub trait Spawner: Debug + Send + Sync + 'static {
+    /// Starts `task` in the background. The runtime tracks its completion itself, so the
+    /// task only needs to be driven to the end.
+    fn spawn(&self, task: SpawnedTask);
+}
+
+/// The default [`Spawner`], which spawns each task on the current tokio runtime.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioSpawner;
+
+impl Spawner for TokioSpawner {
+    fn spawn(&self, task: SpawnedTask) {
+        tokio::spawn(task);
+    }
+}
+
+/// The spawner shared by every agent of a runtime.
+#[derive(Debug, Clone)]
+pub(crate) struct SpawnerRef(Arc<dyn Spawner>);
+
+impl SpawnerRef {
+    pub(crate) fn new(spawner: impl Spawner) -> Self {
+        SpawnerRef(Arc::new(spawner))
+    }
+
+    /// Spawns `task` on `tracker`, so waiting on the tracker waits for it too.
+    pub(crate) fn spawn(&self, tracker: &TaskTracker, task: impl Future<Output = ()> + Send + 'static) {
+        self.0.spawn(Box::pin(tracker.track_future(task)));
+    }
+}
+
+impl Default for SpawnerRef {
+    fn default() -> Self {
+        SpawnerRef::new(TokioSpawner)
+    }
+}
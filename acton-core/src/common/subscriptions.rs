@@ -0,0 +1,92 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::any::TypeId;
+
+use tracing::trace;
+
+use crate::common::AgentHandle;
+use crate::message::{MessageError, SubscribeBroker, UnsubscribeBroker, DEFAULT_TOPIC};
+use crate::traits::{ActonMessage, Actor, Subscriber};
+
+/// Several message types to subscribe an agent to, or unsubscribe it from, with a single
+/// request to the broker.
+///
+/// Start one with [`AgentHandle::subscriptions`], [`add`](Subscriptions::add) each type, then
+/// call [`subscribe`](Subscriptions::subscribe) or [`unsubscribe`](Subscriptions::unsubscribe).
+#[derive(Debug, Clone)]
+pub struct Subscriptions {
+    subscriber: AgentHandle,
+    message_types: Vec<(TypeId, &'static str)>,
+}
+
+impl Subscriptions {
+    pub(crate) fn new(subscriber: AgentHandle) -> Self {
+        Subscriptions { subscriber, message_types: Vec::new() }
+    }
+
+    /// Adds messages of type `M` to the set.
+    pub fn add<M: ActonMessage + Send + Sync + 'static>(mut self) -> Self {
+        self.message_types.push((TypeId::of::<M>(), std::any::type_name::<M>()));
+        self
+    }
+
+    /// Subscribes the agent to every type in the set. The broker adds them all at once, so
+    /// no broadcast finds the agent subscribed to only some of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::NoBroker` if the agent has no broker.
+    pub async fn subscribe(self) -> Result<(), MessageError> {
+        let broker = self.subscriber.get_broker().ok_or(MessageError::NoBroker)?;
+        trace!(subscriber_ern = self.subscriber.id().to_string(), "Subscribing to {:?}", self.type_names());
+        let subscription = SubscribeBroker {
+            subscriber_id: self.subscriber.id(),
+            message_type_ids: self.type_ids(),
+            topic: DEFAULT_TOPIC.to_string(),
+            subscriber_context: self.subscriber.clone(),
+            filter: None,
+        };
+        broker.send(subscription).await;
+        Ok(())
+    }
+
+    /// Unsubscribes the agent from every type in the set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::NoBroker` if the agent has no broker.
+    pub async fn unsubscribe(self) -> Result<(), MessageError> {
+        let broker = self.subscriber.get_broker().ok_or(MessageError::NoBroker)?;
+        trace!(subscriber_ern = self.subscriber.id().to_string(), "Unsubscribing from {:?}", self.type_names());
+        let subscription = UnsubscribeBroker {
+            subscriber_id: self.subscriber.id(),
+            message_type_ids: self.type_ids(),
+            topic: DEFAULT_TOPIC.to_string(),
+            subscriber_context: self.subscriber.clone(),
+        };
+        broker.send(subscription).await;
+        Ok(())
+    }
+
+    fn type_ids(&self) -> Vec<TypeId> {
+        self.message_types.iter().map(|(type_id, _)| *type_id).collect()
+    }
+
+    fn type_names(&self) -> Vec<&'static str> {
+        self.message_types.iter().map(|(_, type_name)| *type_name).collect()
+    }
+}
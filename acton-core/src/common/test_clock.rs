@@ -0,0 +1,45 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::time::Duration;
+
+/// Drives the virtual clock that `AgentHandle::schedule` and `schedule_interval` run on,
+/// so tests of long timers finish instantly.
+///
+/// This is Tokio's paused time, so the test must run on a current-thread runtime that starts
+/// with the clock paused: `#[tokio::test(start_paused = true)]`. Pausing the clock partway
+/// through a test leaves timers off by up to a millisecond. While paused, the runtime also
+/// jumps ahead on its own whenever every task is waiting on a timer.
+///
+/// Only available with the `testing` feature.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TestClock;
+
+impl TestClock {
+    /// Moves the clock forward by `duration`, firing every timer due in that time.
+    ///
+    /// Intervals fire at most once for a jump longer than their period: missed ticks are
+    /// skipped, as they are in real time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clock isn't paused.
+    pub async fn advance(duration: Duration) {
+        tokio::time::advance(duration).await;
+        // Let the deliveries woken by the jump send their messages, so a `TestProbe` sees them
+        tokio::task::yield_now().await;
+    }
+}
@@ -0,0 +1,60 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::time::Duration;
+
+use crate::common::AgentHandle;
+use crate::traits::{ActonMessage, Actor};
+
+/// How often `TestProbe::await_idle` checks whether the agent is idle.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Wraps an [`AgentHandle`] so tests can send to it and then wait for it to go quiet.
+///
+/// Only available with the `testing` feature.
+#[derive(Debug, Clone)]
+pub struct TestProbe {
+    handle: AgentHandle,
+}
+
+impl TestProbe {
+    /// Creates a probe for the agent behind `handle`.
+    pub fn new(handle: AgentHandle) -> Self {
+        TestProbe { handle }
+    }
+
+    /// Sends a message to the agent.
+    pub async fn tell(&self, message: impl ActonMessage + 'static) {
+        self.handle.send(message).await;
+    }
+
+    /// Waits until every message sent to the agent so far has been handled and no handler
+    /// is running, so assertions see everything those messages did.
+    ///
+    /// Messages held while the agent is suspended or stashed count as not handled yet. An
+    /// agent that has stopped is idle.
+    pub async fn await_idle(&self) {
+        let outbox = &self.handle.outbox;
+        while outbox.unhandled() > 0 && !outbox.is_closed() {
+            tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Returns the handle the probe wraps.
+    pub fn handle(&self) -> &AgentHandle {
+        &self.handle
+    }
+}
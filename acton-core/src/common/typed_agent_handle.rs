@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use crate::common::AgentHandle;
+use crate::traits::{ActonMessage, Actor};
+
+/// An [`AgentHandle`] that only accepts messages of type `M`.
+///
+/// Get one with [`AgentHandle::typed`]. Sending any other message type is a compile error.
+/// It derefs to the untyped handle for everything else, such as `stop` or `suspend`.
+pub struct TypedAgentHandle<M> {
+    handle: AgentHandle,
+    _message: PhantomData<fn(M)>,
+}
+
+impl<M: ActonMessage + 'static> TypedAgentHandle<M> {
+    pub(crate) fn new(handle: AgentHandle) -> Self {
+        TypedAgentHandle {
+            handle,
+            _message: PhantomData,
+        }
+    }
+
+    /// Sends a message to the agent.
+    pub async fn send(&self, message: M) {
+        self.handle.send(message).await;
+    }
+
+    /// Returns the untyped handle.
+    pub fn into_inner(self) -> AgentHandle {
+        self.handle
+    }
+}
+
+impl<M> Clone for TypedAgentHandle<M> {
+    fn clone(&self) -> Self {
+        TypedAgentHandle {
+            handle: self.handle.clone(),
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<M> Debug for TypedAgentHandle<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedAgentHandle")
+            .field("handle", &self.handle)
+            .field("message", &std::any::type_name::<M>())
+            .finish()
+    }
+}
+
+impl<M> Deref for TypedAgentHandle<M> {
+    type Target = AgentHandle;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
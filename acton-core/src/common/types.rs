@@ -14,18 +14,21 @@
  * limitations under that License.
  */
 
-use std::any::TypeId;
+use std::any::{Any, TypeId};
 use std::fmt::Debug;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, Weak};
 
 use dashmap::DashMap;
-use tokio::sync::mpsc::Sender;
+use tokio::sync::{broadcast, mpsc, oneshot, Notify};
+use uuid::Uuid;
 
-use crate::actor::{ManagedAgent, Started};
-use crate::common::AgentHandle;
-use crate::message::Envelope;
+use crate::actor::{EscalationAction, ManagedAgent, Started};
+use crate::common::{AgentHandle, TypeMap};
+use crate::message::{ChildEscalation, Envelope, InterceptDecision};
+use crate::traits::ActonMessage;
 
 /// A type alias for a map of reactors, indexed by `TypeId`.
 pub(crate) type ReactorMap<ActorEntity> = DashMap<TypeId, ReactorItem<ActorEntity>>;
@@ -36,6 +39,8 @@ pub enum ReactorItem<ActorEntity: Default + Send + Debug + 'static> {
     // SignalReactor(Box<SignalHandler<ActorEntity>>),
     /// A future reactor, which reacts to futures.
     FutureReactor(Box<FutureHandler<ActorEntity>>),
+    /// A future reactor whose future can fail; errors go to the agent's `on_error` reactor.
+    FallibleReactor(Box<FallibleHandler<ActorEntity>>),
 }
 
 /// A type alias for a future reactor function.
@@ -44,11 +49,56 @@ pub(crate) type FutureHandler<ManagedEntity> = dyn for<'a, 'b> Fn(&mut ManagedAg
 + Sync
 + 'static;
 
+/// A type alias for a fallible future reactor function.
+pub(crate) type FallibleHandler<ManagedEntity> = dyn for<'a, 'b> Fn(&mut ManagedAgent<Started, ManagedEntity>, &'b mut Envelope) -> FallibleFutureBox
++ Send
++ Sync
++ 'static;
+
 /// A type alias for a boxed future.
 pub(crate) type FutureBox = Pin<Box<dyn Future<Output=()> + Sync + Send + 'static>>;
 
-/// A type alias for an outbound channel, which sends envelopes.
-pub(crate) type Outbox = Sender<Envelope>;
+/// A type alias for a boxed future that can fail.
+pub(crate) type FallibleFutureBox = Pin<Box<dyn Future<Output=anyhow::Result<()>> + Sync + Send + 'static>>;
+
+/// A type alias for the one-shot channel used to answer an `ask` request.
+///
+/// The sender is shared so envelopes stay cloneable; whoever replies first takes it.
+pub(crate) type ReplySender = Arc<ReplySlot>;
+
+/// A type alias for the slot holding an `ask` request's reply channel until it is used.
+pub(crate) type ReplySlot = Mutex<Option<oneshot::Sender<Box<dyn ActonMessage>>>>;
+
+/// A type alias for the channel carrying the items of an `ask_stream` request back to the caller.
+pub(crate) type StreamSender = mpsc::Sender<Box<dyn ActonMessage>>;
+
+/// A type alias for the reply channels of an agent's unanswered `ask` requests.
+///
+/// They are held weakly, so a request the agent drops without answering still ends in
+/// `MessageError::NoReply`.
+pub(crate) type OutstandingAsks = Arc<Mutex<Vec<Weak<ReplySlot>>>>;
+
+/// A type alias for the delivery id of a message sent with `send_reliable`, and the signal
+/// the receiver acknowledges it with.
+///
+/// Every redelivery shares the signal, so an ack for any attempt counts.
+pub(crate) type Delivery = (Uuid, Arc<Notify>);
+
+/// A type alias for the shared record of messages an agent sent, kept when it was configured
+/// with `AgentConfig::with_recording`.
+#[cfg(feature = "testing")]
+pub(crate) type Recording = Arc<Mutex<Vec<Box<dyn ActonMessage>>>>;
+
+/// A type alias for the sending side of an agent's output stream, from
+/// `AgentHandle::broadcast_handle`.
+pub(crate) type OutputSender = broadcast::Sender<Arc<dyn ActonMessage + Send + Sync>>;
+
+/// A type alias for a `ManagedAgent::receive` waiting on a message: the type it wants, and
+/// where to send the message once one arrives.
+pub(crate) type ReceiveWaiter = (TypeId, oneshot::Sender<Arc<dyn ActonMessage + Send + Sync>>);
+
+/// A type alias for an agent's `on_mailbox_full` hook, given the depth of the full mailbox.
+pub(crate) type MailboxFullHook = Arc<dyn Fn(usize) + Send + Sync + 'static>;
 
 /// A type alias for a stop signal, represented by an atomic boolean.
 pub(crate) type HaltSignal = AtomicBool;
@@ -61,5 +111,81 @@ pub(crate) type HaltSignal = AtomicBool;
 pub(crate) type AsyncLifecycleHandler<ManagedEntity> =
 Box<dyn Fn(&ManagedAgent<Started, ManagedEntity>) -> FutureBox + Send + Sync + 'static>;
 
+/// A type alias for the reactor called when a message handler fails, with the envelope it
+/// was handling.
+pub(crate) type AsyncErrorHandler<ManagedEntity> =
+Box<dyn Fn(&ManagedAgent<Started, ManagedEntity>, &anyhow::Error, &Envelope) -> FutureBox + Send + Sync + 'static>;
+
+/// A type alias for the check that tells whether an agent is ready, judged from its model.
+pub(crate) type ReadinessCheck<ManagedEntity> = Box<dyn Fn(&ManagedEntity) -> bool + Send + Sync + 'static>;
+
+/// A type alias for the hook deciding what a supervisor does when a child escalates a failure.
+pub(crate) type ChildFailureHook<ManagedEntity> = Arc<
+    dyn Fn(&mut ManagedAgent<Started, ManagedEntity>, &ChildEscalation) -> EscalationAction + Send + Sync + 'static,
+>;
+
+/// A type alias for a hook applying a `Reconfigure` message, given the message type-erased.
+pub(crate) type ReconfigureHook<ManagedEntity> =
+    Arc<dyn Fn(&mut ManagedAgent<Started, ManagedEntity>, &dyn Any) + Send + Sync + 'static>;
+
+/// A type alias for the function that replays a persistent agent's journal into its state.
+pub(crate) type RecoveryHandler<ManagedEntity> =
+fn(&(dyn Any + Send + Sync), &mut ManagedEntity) -> anyhow::Result<()>;
+
+/// A type alias for a predicate over a type-erased message.
+type MessagePredicate = dyn Fn(&dyn ActonMessage) -> bool + Send + Sync + 'static;
+
+/// A predicate a broker subscriber uses to pick which broadcasts it receives.
+#[derive(Clone)]
+pub struct MessageFilter(Arc<MessagePredicate>);
+
+impl MessageFilter {
+    /// Wraps a predicate over `M`; messages of any other type never match.
+    pub(crate) fn new<M: 'static>(predicate: impl Fn(&M) -> bool + Send + Sync + 'static) -> Self {
+        MessageFilter(Arc::new(move |message: &dyn ActonMessage| {
+            message.as_any().downcast_ref::<M>().is_some_and(&predicate)
+        }))
+    }
+
+    /// Returns whether `message` passes the filter.
+    pub(crate) fn matches(&self, message: &dyn ActonMessage) -> bool {
+        (self.0)(message)
+    }
+}
+
+impl Debug for MessageFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MessageFilter")
+    }
+}
+
+/// A type alias for an interceptor's decision function, which may leave data for the
+/// handler in the agent's context data.
+type InterceptFn = dyn Fn(&Envelope, &mut TypeMap) -> InterceptDecision + Send + Sync + 'static;
+
+/// A check run on every message an agent receives, before its handler; see
+/// `AgentConfig::with_interceptor`.
+#[derive(Clone)]
+pub(crate) struct Interceptor(Arc<InterceptFn>);
+
+impl Interceptor {
+    pub(crate) fn new(
+        intercept: impl Fn(&Envelope, &mut TypeMap) -> InterceptDecision + Send + Sync + 'static,
+    ) -> Self {
+        Interceptor(Arc::new(intercept))
+    }
+
+    /// Returns the interceptor's decision about `envelope`.
+    pub(crate) fn decide(&self, envelope: &Envelope, context_data: &mut TypeMap) -> InterceptDecision {
+        (self.0)(envelope, context_data)
+    }
+}
+
+impl Debug for Interceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Interceptor")
+    }
+}
+
 pub type BrokerRef = AgentHandle;
 pub type ParentRef = AgentHandle;
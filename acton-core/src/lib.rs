@@ -30,6 +30,9 @@ pub(crate) mod actor;
 pub(crate) mod message;
 /// Trait definitions used in the Acton framework.
 pub(crate) mod traits;
+/// Sending messages to agents in other processes over TCP.
+#[cfg(feature = "remote")]
+pub(crate) mod remote;
 
 /// Prelude module for convenient imports.
 ///
@@ -39,10 +42,31 @@ pub mod prelude {
     pub use acton_ern::*;
     pub use async_trait;
 
-    pub use crate::actor::{AgentConfig, Idle, ManagedAgent, Started};
-    pub use crate::common::{ActonApp, AgentBroker, AgentHandle, AgentReply, AgentRuntime};
+    pub use crate::actor::{
+        AgentConfig, Backoff, Behavior, EscalationAction, EventJournal, Idle, ManagedAgent, MemoryJournal,
+        MemorySnapshotStore, OnTimeout, Persistent, RateLimitPolicy, RetryPolicy, Snapshot, SnapshotStore, Started,
+        SupervisionStrategy, TerminatePolicy,
+    };
+    #[cfg(feature = "serde")]
+    pub use crate::actor::FileJournal;
+    pub use crate::common::{
+        ActonApp, AgentBroker, AgentHandle, AgentMetrics, AgentReply, AgentRuntime, BroadcastPolicy, DeliveryHandle, HealthState,
+        HealthStatus, IntervalHandle, LatencyHistogram, LazyAgentHandle, MessageTypeMetrics, ScheduledHandle, SpawnedTask, Spawner, Subscriptions,
+        TokioSpawner, TypeMap, TypedAgentHandle,
+    };
     pub use crate::message::{
-        BrokerRequest, BrokerRequestEnvelope, MessageAddress, OutboundEnvelope,
+        BrokerRequest, BrokerRequestEnvelope, ChildEscalation, DeadLetter, DeadLetterReason, Envelope, FailureKind,
+        FailureReport, InterceptDecision,
+        MessageAddress, MessageError, OutboundEnvelope, Reconfigure, RecvError, StreamEnd, SystemEvent, Terminated,
+        TrySendError,
     };
     pub use crate::traits::{ActonMessage, Actor, Broker, Subscribable, Subscriber};
+    #[cfg(feature = "serde")]
+    pub use crate::message::MessageRegistry;
+    #[cfg(feature = "serde")]
+    pub use crate::traits::SerializableMessage;
+    #[cfg(feature = "remote")]
+    pub use crate::remote::{RemoteAgentHandle, RemoteSystem};
+    #[cfg(feature = "testing")]
+    pub use crate::common::{TestClock, TestProbe};
 }
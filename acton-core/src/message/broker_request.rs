@@ -21,6 +21,9 @@ use tracing::*;
 
 use crate::traits::ActonMessage;
 
+/// The topic of broadcasts and subscriptions that don't name one.
+pub(crate) const DEFAULT_TOPIC: &str = "";
+
 /// Represents a request to the broker for message distribution.
 ///
 /// This struct encapsulates a message along with its type information,
@@ -33,6 +36,8 @@ pub struct BrokerRequest {
     pub message_type_name: String,
     /// The TypeId of the message, used for efficient type checking and routing.
     pub message_type_id: TypeId,
+    /// The topic the message is published to; only subscribers of this topic receive it.
+    pub topic: String,
 }
 
 impl BrokerRequest {
@@ -53,11 +58,19 @@ impl BrokerRequest {
     ///
     /// A new `BrokerRequest` instance containing the provided message and its type information.
     pub fn new<M: ActonMessage + Send + Sync + 'static>(message: M) -> Self {
+        Self::new_with_topic(DEFAULT_TOPIC, message)
+    }
+
+    /// Creates a new `BrokerRequest` that publishes `message` to the named `topic`.
+    ///
+    /// Only agents that subscribed to `M` on the same topic receive it.
+    pub fn new_with_topic<M: ActonMessage + Send + Sync + 'static>(topic: &str, message: M) -> Self {
         let message_type_name = std::any::type_name_of_val(&message).to_string();
         let message_type_id = message.type_id();
         let message = Arc::new(message);
         trace!(
             message_type_name = message_type_name,
+            topic,
             "BroadcastEnvelope::new() message_type_id: {:?}",
             message_type_id
         );
@@ -65,6 +78,7 @@ impl BrokerRequest {
             message,
             message_type_id,
             message_type_name,
+            topic: topic.to_string(),
         }
     }
 }
@@ -18,7 +18,7 @@ use std::sync::Arc;
 
 use tracing::*;
 
-use crate::message::BrokerRequest;
+use crate::message::{BrokerRequest, MessageAddress};
 use crate::traits::ActonMessage;
 
 /// Represents an envelope that carries a message within the actor system.
@@ -29,6 +29,9 @@ use crate::traits::ActonMessage;
 pub struct BrokerRequestEnvelope {
     /// The actual message being carried, wrapped in an Arc for thread-safe sharing.
     pub message: Arc<dyn ActonMessage + Send + Sync + 'static>,
+    /// The agent that published the message, which subscribers' replies go to; `None` when
+    /// it was broadcast from the broker itself.
+    pub(crate) publisher: Option<MessageAddress>,
 }
 
 impl From<BrokerRequest> for BrokerRequestEnvelope {
@@ -48,6 +51,7 @@ impl From<BrokerRequest> for BrokerRequestEnvelope {
         trace!("{:?}", value);
         Self {
             message: value.message,
+            publisher: None,
         }
     }
 }
@@ -71,6 +75,6 @@ impl BrokerRequestEnvelope {
     /// A new `BrokerRequestEnvelope` instance containing the provided message.
     pub fn new<M: ActonMessage + Send + Sync + 'static>(request: M) -> Self {
         let message = Arc::new(request);
-        Self { message }
+        Self { message, publisher: None }
     }
 }
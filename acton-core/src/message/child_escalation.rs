@@ -0,0 +1,32 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::sync::Arc;
+
+use acton_ern::Ern;
+
+/// Tells a supervising agent that one of its children failed beyond what its supervision
+/// could handle and is stopping: a handler panicked under `SupervisionStrategy::Stop`, or
+/// more often than its `SupervisionStrategy::Restart` budget allows.
+///
+/// The supervisor decides what happens next with its `on_child_failure` hook.
+#[derive(Debug, Clone)]
+pub struct ChildEscalation {
+    /// The id of the child that failed.
+    pub ern: Ern,
+    /// The failure that stopped the child.
+    pub error: Arc<anyhow::Error>,
+}
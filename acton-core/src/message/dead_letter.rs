@@ -0,0 +1,49 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use crate::message::Envelope;
+
+/// A message that could not be delivered to its handler, as seen by the runtime's
+/// dead-letter agent.
+///
+/// The dead-letter agent broadcasts every `DeadLetter` it receives, so subscribe to this
+/// type through the broker to observe them.
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    /// The original envelope, including the sender and the message.
+    pub envelope: Envelope,
+    /// Why the message could not be handled.
+    pub reason: DeadLetterReason,
+}
+
+/// Why a message became a [`DeadLetter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeadLetterReason {
+    /// The recipient had no handler registered for the message type.
+    NoHandler,
+    /// The handler kept failing until its `RetryPolicy` ran out of attempts.
+    RetriesExhausted,
+    /// An interceptor added with `AgentConfig::with_interceptor` rejected the message, for
+    /// the given reason.
+    Rejected(String),
+    /// The message arrived faster than the recipient's rate limit allows, and its
+    /// `RateLimitPolicy` is `DeadLetter`.
+    RateLimited,
+    /// A subscriber's mailbox stayed full through every retry of a broadcast, and the
+    /// runtime's `BroadcastPolicy` is `RetryThenDrop`.
+    SubscriberFull,
+}
@@ -15,10 +15,14 @@
  */
 
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
+use acton_ern::Ern;
 use static_assertions::assert_impl_all;
+use tokio::time::Instant;
+use uuid::Uuid;
 
+use crate::common::{Delivery, ReplySender, StreamSender};
 use crate::message::message_address::MessageAddress;
 use crate::traits::ActonMessage;
 
@@ -32,6 +36,24 @@ pub struct Envelope {
     /// The return address for the message response.
     pub reply_to: MessageAddress,
     pub recipient: MessageAddress,
+    /// The channel used to answer the sender when the message was sent with `ask`.
+    pub(crate) reply_channel: Option<ReplySender>,
+    /// The channel carrying streamed replies when the message was sent with `ask_stream`.
+    pub(crate) stream_channel: Option<StreamSender>,
+    /// Identifies this message.
+    pub(crate) message_id: Uuid,
+    /// Shared by every message sent, directly or not, because of the same original message.
+    pub(crate) correlation_id: Uuid,
+    /// The id of the message being handled when this one was sent, if any.
+    pub(crate) causation_id: Option<Uuid>,
+    /// The delivery id and ack signal when the message was sent with `send_reliable`.
+    pub(crate) delivery: Option<Delivery>,
+    /// How many envelopes were sent to the recipient before this one, stamped by its mailbox.
+    pub(crate) sequence: u64,
+    /// When the envelope entered the recipient's mailbox, stamped by the mailbox.
+    pub(crate) enqueued_at: Instant,
+    /// How long the envelope waited in the mailbox, set just before it is handled.
+    pub(crate) queue_latency: Duration,
 }
 
 impl Envelope {
@@ -50,13 +72,81 @@ impl Envelope {
         recipient: MessageAddress,
     ) -> Self {
         let timestamp = SystemTime::now();
+        let message_id = Uuid::new_v4();
         Envelope {
             message,
             recipient,
             reply_to,
             timestamp,
+            reply_channel: None,
+            stream_channel: None,
+            message_id,
+            correlation_id: message_id,
+            causation_id: None,
+            delivery: None,
+            sequence: 0,
+            enqueued_at: Instant::now(),
+            queue_latency: Duration::ZERO,
         }
     }
+
+    /// Returns the id of this message.
+    pub fn message_id(&self) -> Uuid {
+        self.message_id
+    }
+
+    /// Returns the id shared by every message in the cascade this one belongs to.
+    ///
+    /// A message sent outside any handler starts a cascade, and its correlation id is its
+    /// own message id. Messages sent with a `MessageContext`'s envelopes while handling it,
+    /// and messages it is forwarded as, carry the same correlation id.
+    pub fn correlation_id(&self) -> Uuid {
+        self.correlation_id
+    }
+
+    /// Returns the id of the message whose handler sent this one, or `None` if it starts a
+    /// cascade.
+    pub fn causation_id(&self) -> Option<Uuid> {
+        self.causation_id
+    }
+
+    /// Returns the delivery id of a message sent with `send_reliable`, or `None` otherwise.
+    ///
+    /// Redeliveries keep the same delivery id, so receivers can use it to spot duplicates.
+    pub fn delivery_id(&self) -> Option<Uuid> {
+        self.delivery.as_ref().map(|(id, _)| *id)
+    }
+
+    /// Acknowledges a message sent with `send_reliable`, so the sender stops redelivering it.
+    ///
+    /// Does nothing for messages sent any other way.
+    pub fn ack(&self) {
+        if let Some((_, ack)) = &self.delivery {
+            ack.notify_one();
+        }
+    }
+
+    /// Returns the ERN of the agent that sent the message, for handlers that decide what to
+    /// do based on who is asking.
+    ///
+    /// For a broadcast, the sender is the agent that published it. Returns `None` when the
+    /// envelope names the recipient itself as the sender, as it does for broadcasts from
+    /// the broker itself and for messages sent from outside any agent.
+    pub fn sender_ern(&self) -> Option<&Ern> {
+        (self.reply_to.sender != self.recipient.sender).then_some(&self.reply_to.sender)
+    }
+
+    /// Returns how long the message waited in the agent's mailbox before its handler ran.
+    ///
+    /// This is the queueing delay alone; time spent in the handler isn't included.
+    pub fn queue_latency(&self) -> Duration {
+        self.queue_latency
+    }
+
+    /// Returns the message as an `M`, if that is its type.
+    pub fn message_as<M: 'static>(&self) -> Option<&M> {
+        (*self.message).as_any().downcast_ref::<M>()
+    }
 }
 
 // Ensures that Envelope implements the Send trait.
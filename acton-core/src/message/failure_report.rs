@@ -0,0 +1,45 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use acton_ern::Ern;
+
+/// Tells an agent that one of its children's handlers failed, so it can decide what to do
+/// by the kind of failure.
+///
+/// A parent receives one for every failure of a child's handler, whether or not the child
+/// recovers from it, as long as the parent has a handler for `FailureReport` when the
+/// failure happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureReport {
+    /// The id of the child whose handler failed.
+    pub child: Ern,
+    /// The failure, as the child logged it.
+    pub error: String,
+    /// How the handler failed.
+    pub kind: FailureKind,
+}
+
+/// How a handler failed, in a [`FailureReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FailureKind {
+    /// The handler panicked.
+    Panic,
+    /// The handler returned an error.
+    HandlerError,
+    /// A handler added with `act_on_with_timeout` ran past its timeout.
+    Timeout,
+}
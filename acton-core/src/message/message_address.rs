@@ -17,7 +17,7 @@
 use acton_ern::prelude::*;
 use derive_new::new;
 
-use crate::common::Outbox;
+use crate::common::{bounded_mailbox, Outbox};
 
 /// Message address with a sender id
 #[derive(new, Clone, Debug)]
@@ -31,11 +31,16 @@ impl MessageAddress {
     pub fn name(&self) -> &str {
         self.sender.root.as_str()
     }
+
+    /// get the id of the address owner
+    pub fn sender(&self) -> &Ern {
+        &self.sender
+    }
 }
 
 impl Default for MessageAddress {
     fn default() -> Self {
-        let (outbox, _) = tokio::sync::mpsc::channel(1);
+        let (outbox, _) = bounded_mailbox(1);
         Self::new(outbox, Ern::default())
     }
 }
@@ -14,11 +14,21 @@
  * limitations under that License.
  */
 
-use std::time::SystemTime;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+use acton_ern::Ern;
+use futures::{Stream, StreamExt};
 use static_assertions::assert_impl_all;
+use tokio::time::Instant;
+use tracing::{trace, warn};
+use uuid::Uuid;
 
-use crate::message::{MessageAddress, OutboundEnvelope};
+use crate::common::{AgentHandle, Delivery, ReplySender, StreamSender};
+use crate::message::{Envelope, MessageAddress, MessageError, OutboundEnvelope, StreamEnd};
+use crate::traits::{ActonMessage, Actor};
 
 /// Represents a record of an event within the actor system.
 /// This structure maintains the context of a message, including its content,
@@ -36,6 +46,20 @@ pub struct MessageContext<S> {
     pub(crate) origin_envelope: OutboundEnvelope,
     /// Contains routing information about where replies should be sent
     pub(crate) reply_envelope: OutboundEnvelope,
+    /// Completes the caller's future when the message was sent with `ask`
+    pub(crate) reply_channel: Option<ReplySender>,
+    /// Carries streamed replies back to the caller when the message was sent with `ask_stream`
+    pub(crate) stream_channel: Option<StreamSender>,
+    /// Identifies the message
+    pub(crate) message_id: Uuid,
+    /// Shared by every message in the message's cascade
+    pub(crate) correlation_id: Uuid,
+    /// The id of the message whose handler sent this one
+    pub(crate) causation_id: Option<Uuid>,
+    /// The delivery id and ack signal when the message was sent with `send_reliable`
+    pub(crate) delivery: Option<Delivery>,
+    /// How long the message waited in the mailbox before its handler ran
+    pub(crate) queue_latency: Duration,
 }
 
 impl<S> MessageContext<S> {
@@ -54,10 +78,12 @@ impl<S> MessageContext<S> {
     /// Creates a new envelope for sending messages to a specific recipient
     /// while maintaining the current message context's return address
     pub fn new_envelope(&self, recipient: &MessageAddress) -> OutboundEnvelope {
-        OutboundEnvelope::new_with_recipient(
+        let mut envelope = OutboundEnvelope::new_with_recipient(
             self.reply_envelope.return_address.clone(),
             recipient.clone(),
-        )
+        );
+        envelope.cause = self.cause();
+        envelope
     }
 
     /// Returns a reference to the message payload
@@ -69,6 +95,190 @@ impl<S> MessageContext<S> {
     pub fn timestamp(&self) -> &SystemTime {
         &self.timestamp
     }
+
+    /// Returns how long the message waited in the mailbox before its handler ran
+    ///
+    /// Compare it with the handler's own time to tell queueing delay from slow handling.
+    pub fn queue_latency(&self) -> Duration {
+        self.queue_latency
+    }
+
+    /// Returns the ERN of the agent that sent the message, or `None` if there is no single
+    /// sender
+    ///
+    /// For a broadcast, the sender is the agent that published it. Broadcasts from the broker
+    /// itself and messages sent from outside any agent have no sender.
+    pub fn sender_ern(&self) -> Option<&Ern> {
+        let sender = &self.origin_envelope.return_address.sender;
+        (sender != &self.reply_envelope.return_address.sender).then_some(sender)
+    }
+
+    /// Returns the id of the message
+    pub fn message_id(&self) -> Uuid {
+        self.message_id
+    }
+
+    /// Returns the id shared by every message in the message's cascade
+    ///
+    /// Messages sent with this context's envelopes, or forwarded with `forward_to`, carry it too.
+    pub fn correlation_id(&self) -> Uuid {
+        self.correlation_id
+    }
+
+    /// Returns the id of the message whose handler sent this one, or `None` if it started
+    /// its cascade
+    pub fn causation_id(&self) -> Option<Uuid> {
+        self.causation_id
+    }
+
+    /// Returns the delivery id of a message sent with `send_reliable`, or `None` otherwise
+    ///
+    /// Redeliveries keep the same delivery id, so handlers can use it to spot duplicates.
+    pub fn delivery_id(&self) -> Option<Uuid> {
+        self.delivery.as_ref().map(|(id, _)| *id)
+    }
+
+    /// Acknowledges a message sent with `send_reliable`, so the sender stops redelivering it
+    ///
+    /// Does nothing for messages sent any other way.
+    pub fn ack(&self) {
+        if let Some((_, ack)) = &self.delivery {
+            ack.notify_one();
+        }
+    }
+
+    /// What messages sent while handling this one record as their cause
+    fn cause(&self) -> Option<(Uuid, Uuid)> {
+        Some((self.correlation_id, self.message_id))
+    }
+
+    /// Sends `message` back to the agent that sent this one
+    ///
+    /// For a broadcast, the sender is the agent that published it. Does nothing, apart from
+    /// logging a warning, when there is no sender to reply to: for broadcasts from the broker
+    /// itself and messages sent from outside any agent, which name the recipient itself as
+    /// the sender, and when the sender has stopped. The returned future owns everything
+    /// it needs, so it can be returned straight from a handler.
+    pub fn reply_to_sender(&self, message: impl ActonMessage + 'static) -> impl Future<Output = ()> + Send + Sync + 'static {
+        let envelope = self.reply_envelope.clone();
+        async move {
+            let sender = envelope.recipient_address.as_ref().filter(|sender| {
+                sender.sender != envelope.return_address.sender && !sender.address.is_closed()
+            });
+            if sender.is_none() {
+                warn!(actor = %envelope.return_address.sender, "No sender to reply to, dropping {:?}", message);
+                return;
+            }
+            envelope.send(message).await;
+        }
+    }
+
+    /// Sends each item of `stream` back to the sender in order, followed by `StreamEnd`
+    ///
+    /// When the message was sent with `ask_stream`, the items go to the caller's stream;
+    /// otherwise they go to the sending agent as messages, as with `reply_to_sender`. Stops
+    /// early, without sending `StreamEnd`, once the caller drops its stream or the sender
+    /// stops. The returned future owns everything it needs, so it can be returned straight
+    /// from a handler.
+    pub fn reply_stream<T, St>(&self, stream: St) -> impl Future<Output = ()> + Send + Sync + 'static
+    where
+        T: ActonMessage + 'static,
+        St: Stream<Item = T> + Send + Sync + 'static,
+    {
+        let stream_channel = self.stream_channel.clone();
+        let envelope = self.reply_envelope.clone();
+        async move {
+            let mut stream = pin!(stream);
+            if let Some(channel) = stream_channel {
+                while let Some(item) = stream.next().await {
+                    if channel.send(Box::new(item)).await.is_err() {
+                        trace!(actor = %envelope.return_address.sender, "Caller dropped the stream, stopping");
+                        return;
+                    }
+                }
+                let _ = channel.send(Box::new(StreamEnd)).await;
+                return;
+            }
+            let sender_gone = || {
+                envelope
+                    .recipient_address
+                    .as_ref()
+                    .filter(|sender| {
+                        sender.sender != envelope.return_address.sender && !sender.address.is_closed()
+                    })
+                    .is_none()
+            };
+            if sender_gone() {
+                warn!(actor = %envelope.return_address.sender, "No sender to stream replies to");
+                return;
+            }
+            while let Some(item) = stream.next().await {
+                if sender_gone() {
+                    trace!(actor = %envelope.return_address.sender, "Sender stopped, ending the stream");
+                    return;
+                }
+                envelope.send(item).await;
+            }
+            envelope.send(StreamEnd).await;
+        }
+    }
+
+    /// Answers the caller that sent this message with `ask`
+    ///
+    /// Only the first reply is delivered. Returns `MessageError::NoReply` if the message
+    /// wasn't sent with `ask`, it was already answered, or the caller stopped waiting.
+    pub fn reply_with(&self, message: impl ActonMessage + 'static) -> Result<(), MessageError> {
+        let sender = self
+            .reply_channel
+            .as_ref()
+            .and_then(|channel| channel.lock().ok()?.take())
+            .ok_or(MessageError::NoReply)?;
+        sender
+            .send(Box::new(message))
+            .map_err(|_| MessageError::NoReply)
+    }
+}
+
+impl<S: ActonMessage + Clone + 'static> MessageContext<S> {
+    /// Rebuilds the envelope this message arrived in, for example to stash it
+    pub fn envelope(&self) -> Envelope {
+        Envelope {
+            message: Arc::new(self.message.clone()),
+            timestamp: self.timestamp,
+            reply_to: self.origin_envelope.return_address.clone(),
+            recipient: self.reply_envelope.return_address.clone(),
+            reply_channel: self.reply_channel.clone(),
+            stream_channel: self.stream_channel.clone(),
+            message_id: self.message_id,
+            correlation_id: self.correlation_id,
+            causation_id: self.causation_id,
+            delivery: self.delivery.clone(),
+            // Stamped again if the envelope is sent
+            sequence: 0,
+            enqueued_at: Instant::now(),
+            queue_latency: self.queue_latency,
+        }
+    }
+
+    /// Forwards this message to `target`, keeping the original sender as the return address
+    ///
+    /// Replies from `target`, including answers to an `ask`, go to whoever sent the message
+    /// here rather than to this agent. The returned future owns everything it needs, so it
+    /// can be returned straight from a handler.
+    pub fn forward_to(&self, target: &AgentHandle) -> impl Future<Output = ()> + Send + Sync + 'static {
+        let mut envelope = OutboundEnvelope::new_with_recipient(
+            self.origin_envelope.return_address.clone(),
+            target.reply_address(),
+        );
+        envelope.reply_channel = self.reply_channel.clone();
+        envelope.stream_channel = self.stream_channel.clone();
+        envelope.cause = self.cause();
+        envelope.delivery = self.delivery.clone();
+        let message = self.message.clone();
+        async move {
+            envelope.send(message).await;
+        }
+    }
 }
 
 // This static assertion ensures that MessageContext can be safely sent between threads
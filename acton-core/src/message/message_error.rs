@@ -13,11 +13,52 @@
  * See the applicable License for the specific language governing permissions and
  * limitations under that License.
  */
+
+use acton_ern::Ern;
+
 /// Represents errors that can occur when sending messages in the actor system.
 #[derive(Debug)]
 pub enum MessageError {
     /// Indicates that sending a message failed.
     SendFailed(String),
+    /// Indicates that no reply arrived before the requested deadline.
+    Timeout,
+    /// Indicates that a request was handled without a reply being sent, or that
+    /// a reply was attempted for a message that was not sent with `ask`.
+    NoReply,
+    /// Indicates that a handler added with `act_on_with_timeout` ran past its timeout.
+    HandlerTimeout,
+    /// Indicates that a message couldn't be serialized or deserialized.
+    SerializationFailed(String),
+    /// Indicates that no message type is registered under the given tag.
+    UnknownMessageType(String),
+    /// Indicates that the connection to a remote agent was lost.
+    TransportClosed,
+    /// Indicates that the recipient has stopped, so its handle is stale. Looking the agent
+    /// up again, for example with `AgentRuntime::find`, finds its replacement if there is one.
+    AgentStopped {
+        /// The id of the stopped agent.
+        id: Box<Ern>,
+        /// The type of the message that couldn't be delivered.
+        message_type: &'static str,
+    },
+    /// Indicates that a message was sent to the parent of an agent that has none.
+    NoParent,
+    /// Indicates that an agent with no broker tried to subscribe to or unsubscribe from
+    /// broadcasts.
+    NoBroker,
+    /// Indicates that the recipient is draining with `AgentHandle::drain` and takes no new
+    /// messages.
+    Draining,
+    /// Indicates that a send found the mailbox full and couldn't wait for room, as with
+    /// `AgentHandle::send_blocking` on a current-thread runtime or `AgentHandle::terminate`.
+    MailboxFull,
+    /// Indicates that a message sent with `send_reliable` wasn't acknowledged after the given
+    /// number of attempts.
+    NotAcknowledged(usize),
+    /// Indicates that an agent already has as many children as its `with_max_children`
+    /// limit allows.
+    ChildLimitReached(usize),
     /// Represents other types of errors.
     OtherError(String),
 }
@@ -33,6 +74,25 @@ impl std::fmt::Display for MessageError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             MessageError::SendFailed(msg) => write!(f, "Failed to send message: {}", msg),
+            MessageError::Timeout => write!(f, "Timed out waiting for a reply"),
+            MessageError::NoReply => write!(f, "No reply was sent for the request"),
+            MessageError::HandlerTimeout => write!(f, "The handler timed out"),
+            MessageError::SerializationFailed(msg) => write!(f, "Serialization failed: {}", msg),
+            MessageError::UnknownMessageType(tag) => write!(f, "No message type registered as {}", tag),
+            MessageError::TransportClosed => write!(f, "The connection to the remote agent is closed"),
+            MessageError::AgentStopped { id, message_type } => {
+                write!(f, "The agent {} has stopped, so {} couldn't be delivered", id, message_type)
+            }
+            MessageError::NoParent => write!(f, "The agent has no parent"),
+            MessageError::NoBroker => write!(f, "The agent has no broker"),
+            MessageError::Draining => write!(f, "The agent is draining and takes no new messages"),
+            MessageError::MailboxFull => write!(f, "The mailbox is full and this thread can't wait for room"),
+            MessageError::NotAcknowledged(attempts) => {
+                write!(f, "The message was not acknowledged after {} attempts", attempts)
+            }
+            MessageError::ChildLimitReached(limit) => {
+                write!(f, "The agent already has its limit of {} children", limit)
+            }
             MessageError::OtherError(msg) => write!(f, "Error: {}", msg),
         }
     }
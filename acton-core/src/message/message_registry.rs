@@ -0,0 +1,73 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::message::MessageError;
+use crate::traits::{ActonMessage, SerializableMessage};
+
+type Deserializer = fn(&[u8]) -> Result<Box<dyn ActonMessage>, MessageError>;
+
+/// Maps the tags of [`SerializableMessage`] types to their deserializers, so a message can
+/// be rebuilt from its tag and bytes without knowing its type in advance.
+#[derive(Default, Clone)]
+pub struct MessageRegistry {
+    deserializers: HashMap<&'static str, Deserializer>,
+}
+
+impl MessageRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `M` under its tag, replacing any type already registered with the same tag.
+    pub fn register<M: SerializableMessage + 'static>(&mut self) -> &mut Self {
+        self.deserializers.insert(M::TAG, |bytes| {
+            M::from_bytes(bytes).map(|message| Box::new(message) as Box<dyn ActonMessage>)
+        });
+        self
+    }
+
+    /// Returns whether a type is registered under `tag`.
+    pub fn contains(&self, tag: &str) -> bool {
+        self.deserializers.contains_key(tag)
+    }
+
+    /// Rebuilds a message from its tag and the bytes from [`SerializableMessage::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::UnknownMessageType` if nothing is registered under `tag`, or
+    /// `MessageError::SerializationFailed` if the bytes aren't a valid message of that type.
+    pub fn deserialize(&self, tag: &str, bytes: &[u8]) -> Result<Box<dyn ActonMessage>, MessageError> {
+        let deserialize = self
+            .deserializers
+            .get(tag)
+            .ok_or_else(|| MessageError::UnknownMessageType(tag.to_string()))?;
+        deserialize(bytes)
+    }
+}
+
+impl fmt::Debug for MessageRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageRegistry")
+            .field("tags", &self.deserializers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
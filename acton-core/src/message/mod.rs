@@ -15,23 +15,50 @@
  */
 
 pub use broker_request::BrokerRequest;
+pub(crate) use broker_request::DEFAULT_TOPIC;
 pub use broker_request_envelope::BrokerRequestEnvelope;
-pub(crate) use envelope::Envelope;
+pub use child_escalation::ChildEscalation;
+pub use dead_letter::{DeadLetter, DeadLetterReason};
+pub use envelope::Envelope;
+pub use failure_report::{FailureKind, FailureReport};
+pub use intercept_decision::InterceptDecision;
 pub use message_address::MessageAddress;
 pub(crate) use message_context::MessageContext;
-pub(crate) use message_error::MessageError;
+pub use message_error::MessageError;
+#[cfg(feature = "serde")]
+pub use message_registry::MessageRegistry;
 pub use outbound_envelope::OutboundEnvelope;
+pub(crate) use retry_attempt::RetryAttempt;
+pub use reconfigure::Reconfigure;
+pub use recv_error::RecvError;
 pub use signal::SystemSignal;
+pub use stream_end::StreamEnd;
+pub use system_event::SystemEvent;
+pub use terminated::Terminated;
+pub use try_send_error::TrySendError;
 pub(crate) use subscribe_broker::SubscribeBroker;
 pub(crate) use unsubscribe_broker::UnsubscribeBroker;
 
 mod broker_request;
 mod broker_request_envelope;
+mod child_escalation;
+mod dead_letter;
 mod envelope;
+mod failure_report;
+mod intercept_decision;
 mod message_context;
 mod message_error;
+#[cfg(feature = "serde")]
+mod message_registry;
 mod outbound_envelope;
 mod message_address;
+mod reconfigure;
+mod recv_error;
+mod retry_attempt;
 mod signal;
+mod stream_end;
 mod subscribe_broker;
+mod system_event;
+mod terminated;
+mod try_send_error;
 mod unsubscribe_broker;
@@ -18,9 +18,12 @@ use std::cmp::PartialEq;
 use std::sync::Arc;
 
 use tokio::runtime::Runtime;
+use tokio::sync::mpsc::error::TrySendError as ChannelTrySendError;
 use tracing::{error, instrument, trace};
+use uuid::Uuid;
 
-use crate::common::{Envelope, MessageError};
+use crate::common::{Delivery, Envelope, MessageError, Outbox, ReplySender, StreamSender};
+use crate::message::{SystemSignal, TrySendError};
 use crate::message::message_address::MessageAddress;
 use crate::traits::ActonMessage;
 
@@ -29,6 +32,13 @@ use crate::traits::ActonMessage;
 pub struct OutboundEnvelope {
     pub(crate) return_address: MessageAddress,
     pub(crate) recipient_address: Option<MessageAddress>,
+    pub(crate) reply_channel: Option<ReplySender>,
+    /// The channel for streamed replies attached by `ask_stream`.
+    pub(crate) stream_channel: Option<StreamSender>,
+    /// The correlation id and message id of the message that caused the ones sent from here.
+    pub(crate) cause: Option<(Uuid, Uuid)>,
+    /// The delivery id and ack signal attached by `send_reliable`.
+    pub(crate) delivery: Option<Delivery>,
 }
 
 impl PartialEq for MessageAddress {
@@ -63,9 +73,9 @@ impl OutboundEnvelope {
     ///
     /// # Returns
     /// A new `OutboundEnvelope` instance.
-    #[instrument(skip(return_address))]
+    #[instrument(skip(return_address), level = "trace")]
     pub fn new(return_address: MessageAddress) -> Self {
-        OutboundEnvelope { return_address, recipient_address: None }
+        OutboundEnvelope { return_address, recipient_address: None, reply_channel: None, stream_channel: None, cause: None, delivery: None }
     }
 
     /// Gets the return address for the outbound envelope.
@@ -78,9 +88,36 @@ impl OutboundEnvelope {
         &self.recipient_address
     }
 
-    #[instrument(skip(return_address))]
+    #[instrument(skip(return_address), level = "trace")]
     pub(crate) fn new_with_recipient(return_address: MessageAddress, recipient_address: MessageAddress) -> Self {
-        OutboundEnvelope { return_address, recipient_address: Some(recipient_address) }
+        OutboundEnvelope { return_address, recipient_address: Some(recipient_address), reply_channel: None, stream_channel: None, cause: None, delivery: None }
+    }
+
+    /// Attaches a one-shot reply channel which the recipient's handler can complete with
+    /// `MessageContext::reply_with`.
+    pub(crate) fn with_reply_channel(mut self, reply_channel: ReplySender) -> Self {
+        self.reply_channel = Some(reply_channel);
+        self
+    }
+
+    /// Attaches a channel which the recipient's handler can stream replies into with
+    /// `MessageContext::reply_stream`.
+    pub(crate) fn with_stream_channel(mut self, stream_channel: StreamSender) -> Self {
+        self.stream_channel = Some(stream_channel);
+        self
+    }
+
+    /// Attaches a delivery id and the signal the recipient acknowledges it with.
+    pub(crate) fn with_delivery(mut self, delivery: Delivery) -> Self {
+        self.delivery = Some(delivery);
+        self
+    }
+
+    /// Marks messages sent from this envelope as caused by the message in `envelope`, so
+    /// they join its cascade.
+    pub(crate) fn caused_by(mut self, envelope: &Envelope) -> Self {
+        self.cause = Some((envelope.correlation_id, envelope.message_id));
+        self
     }
 
 
@@ -97,6 +134,28 @@ impl OutboundEnvelope {
         &self,
         message: impl ActonMessage + 'static,
     ) -> Result<(), MessageError> {
+        let recipient = self.recipient_channel();
+        let address = &recipient.address;
+        let stopped = || MessageError::AgentStopped {
+            id: Box::new(recipient.sender.clone()),
+            message_type: message.type_name(),
+        };
+        if address.is_closed() {
+            return Err(stopped());
+        }
+        if refuses(address, &message) {
+            return Err(MessageError::Draining);
+        }
+        // An unbounded mailbox never makes the sender wait, so send right away
+        if address.is_unbounded() {
+            trace!(msg = ?message, "Replying to message.");
+            // Can only fail if the recipient stopped since the check above
+            let error = stopped();
+            let message = Arc::new(message);
+            address.try_send(self.envelope_for(message.clone())).map_err(|_| error)?;
+            self.sent(&*message);
+            return Ok(());
+        }
         let envelope = self.clone();
         trace!("*");
         // Event: Replying to Message
@@ -112,6 +171,82 @@ impl OutboundEnvelope {
         Ok(())
     }
 
+    /// Sends a message without waiting for mailbox room.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TrySendError::Full`] with the message if the recipient's mailbox is full, and
+    /// [`TrySendError::Closed`] if the recipient has stopped or is draining.
+    pub fn try_send<M: ActonMessage + 'static>(&self, message: M) -> Result<(), TrySendError<M>> {
+        let address = &self.recipient_channel().address;
+        let message = Arc::new(message);
+        if address.is_closed() || refuses(address, &*message) {
+            return Err(TrySendError::Closed);
+        }
+        match address.try_send(self.envelope_for(message.clone())) {
+            Ok(()) => {
+                self.sent(&*message);
+                Ok(())
+            }
+            Err(ChannelTrySendError::Full(())) => {
+                // The rejected envelope has been dropped, leaving this the only reference
+                let message = Arc::try_unwrap(message).expect("rejected envelope still holds the message");
+                Err(TrySendError::Full(message))
+            }
+            Err(ChannelTrySendError::Closed(())) => Err(TrySendError::Closed),
+        }
+    }
+
+    /// Sends a message, blocking the thread until the recipient's mailbox has room. Must not
+    /// be called from within the tokio runtime.
+    pub(crate) fn send_blocking(&self, message: impl ActonMessage + 'static) -> Result<(), MessageError> {
+        let recipient = self.recipient_channel();
+        let message_type = message.type_name();
+        if refuses(&recipient.address, &message) {
+            return Err(MessageError::Draining);
+        }
+        let message = Arc::new(message);
+        recipient.address.blocking_send(self.envelope_for(message.clone())).map_err(|_| {
+            MessageError::AgentStopped { id: Box::new(recipient.sender.clone()), message_type }
+        })?;
+        self.sent(&*message);
+        Ok(())
+    }
+
+    /// Sends several messages in order with a single wait for mailbox room.
+    #[instrument(skip(self, messages), level = "trace")]
+    pub(crate) async fn send_batch(&self, messages: Vec<Box<dyn ActonMessage>>) {
+        let recipient_channel = self.recipient_channel();
+        if recipient_channel.address.is_draining() {
+            error!("{}::{}", &self.return_address.name(), MessageError::Draining);
+            return;
+        }
+        let messages: Vec<Arc<dyn ActonMessage>> = messages.into_iter().map(Arc::from).collect();
+        let envelopes = messages.iter().map(|message| self.envelope_for(message.clone())).collect();
+        match recipient_channel.address.send_all(envelopes).await {
+            Ok(()) => messages.iter().for_each(|message| self.sent(&**message)),
+            Err(e) => error!("{}::{}", &self.return_address.name(), e.to_string()),
+        }
+    }
+
+    /// The address messages from this envelope go to.
+    fn recipient_channel(&self) -> MessageAddress {
+        self.recipient_address.clone().unwrap_or_else(|| self.return_address.clone())
+    }
+
+    /// Wraps a message for delivery to the recipient.
+    fn envelope_for(&self, message: Arc<dyn ActonMessage + Send + Sync>) -> Envelope {
+        let mut envelope = Envelope::new(message, self.return_address.clone(), self.recipient_channel());
+        envelope.reply_channel = self.reply_channel.clone();
+        envelope.stream_channel = self.stream_channel.clone();
+        envelope.delivery = self.delivery.clone();
+        if let Some((correlation_id, causation_id)) = self.cause {
+            envelope.correlation_id = correlation_id;
+            envelope.causation_id = Some(causation_id);
+        }
+        envelope
+    }
+
     /// Sends a reply message asynchronously.
     ///
     /// # Parameters
@@ -120,42 +255,38 @@ impl OutboundEnvelope {
     ///
     /// # Returns
     /// A result indicating success or failure.
-    #[instrument(skip(self), level = "debug")]
-    async fn send_message_inner(&self, message: Arc<dyn ActonMessage + Send + Sync>) {
-        let recipient_channel = {
-            if let Some(recipient_address) = &self.recipient_address {
-                recipient_address.clone()
-            } else {
-                self.return_address.clone()
-            }
-        };
-        let recipient_id = &recipient_channel.sender.root.to_string();
+    #[instrument(skip(self), level = "trace")]
+    pub(crate) async fn send_message_inner(&self, message: Arc<dyn ActonMessage + Send + Sync>) {
+        if let Err(e) = self.deliver(message).await {
+            error!("{}::{}", &self.return_address.name(), e);
+        }
+    }
+
+    /// Sends a message, waiting for room if the recipient's mailbox is bounded and full, and
+    /// returns why it couldn't be sent, if it couldn't.
+    pub(crate) async fn deliver(&self, message: Arc<dyn ActonMessage + Send + Sync>) -> Result<(), MessageError> {
+        let recipient_channel = self.recipient_channel();
         let address = &recipient_channel.address;
+        let message_type = (*message).type_name();
+        let stopped = || MessageError::AgentStopped { id: Box::new(recipient_channel.sender.clone()), message_type };
 
-        if !&address.is_closed() {
-            // Reserve capacity
-            match recipient_channel.clone().address.reserve().await {
-                Ok(permit) => {
-                    trace!(
-                        "...to {} with message: ",
-                        recipient_id
-                    );
-                    let envelope = Envelope::new(message, self.return_address.clone(), recipient_channel);
-                    permit.send(envelope);
-                }
-                Err(e) => {
-                    error!(
-                        "{}::{}",
-                        &self.return_address.name(), e.to_string()
-                    )
-                }
-            }
-        } else {
-            error!(
-    "recipient channel closed: {}",
-    self.return_address.name()
-);
+        if address.is_closed() {
+            return Err(stopped());
         }
+        if refuses(address, &*message) {
+            return Err(MessageError::Draining);
+        }
+        trace!("...to {} with message: ", recipient_channel.sender.root);
+        // Waits for room when the recipient's mailbox is bounded and full
+        address.send(self.envelope_for(message.clone())).await.map_err(|_| stopped())?;
+        self.sent(&*message);
+        Ok(())
+    }
+
+    /// Adds a message that reached its recipient to the sender's recording, if it keeps one.
+    fn sent(&self, message: &dyn ActonMessage) {
+        #[cfg(feature = "testing")]
+        self.return_address.address.record(message);
     }
 
     /// Sends a reply message asynchronously.
@@ -171,3 +302,32 @@ impl OutboundEnvelope {
         self.send_message_inner(Arc::new(message)).await;
     }
 }
+
+/// Whether a draining agent turns `message` away. System signals still get through, so a
+/// draining agent can be stopped or watched.
+fn refuses(address: &Outbox, message: &dyn ActonMessage) -> bool {
+    address.is_draining() && !message.as_any().is::<SystemSignal>()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::bounded_mailbox;
+
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct Ping;
+
+    #[tokio::test]
+    async fn test_drained_then_stopped() {
+        let (outbox, inbox) = bounded_mailbox(1);
+        let envelope = OutboundEnvelope::new(MessageAddress::new(outbox.clone(), Default::default()));
+        outbox.start_draining();
+        assert!(matches!(envelope.deliver(Arc::new(Ping)).await, Err(MessageError::Draining)));
+
+        // Once the drained agent has stopped, every way of sending says so
+        drop(inbox);
+        assert!(matches!(envelope.deliver(Arc::new(Ping)).await, Err(MessageError::AgentStopped { .. })));
+        assert!(matches!(envelope.reply(Ping), Err(MessageError::AgentStopped { .. })));
+    }
+}
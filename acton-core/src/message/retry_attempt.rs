@@ -0,0 +1,44 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use crate::message::Envelope;
+
+/// Redelivers a message whose handler failed, for another attempt under its `RetryPolicy`.
+///
+/// Generic over the message type so each message type's retries have their own reactor.
+#[derive(Debug)]
+pub(crate) struct RetryAttempt<M> {
+    /// The envelope the message first arrived in, so the retry keeps its sender and reply channel.
+    pub(crate) envelope: Envelope,
+    /// Which attempt this is, counting from 1.
+    pub(crate) attempt: usize,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M> RetryAttempt<M> {
+    pub(crate) fn new(envelope: Envelope, attempt: usize) -> Self {
+        RetryAttempt { envelope, attempt, _message: PhantomData }
+    }
+}
+
+impl<M> Clone for RetryAttempt<M> {
+    fn clone(&self) -> Self {
+        RetryAttempt::new(self.envelope.clone(), self.attempt)
+    }
+}
@@ -14,6 +14,13 @@
  * limitations under that License.
  */
 use std::fmt::Debug;
+use std::sync::Arc;
+
+use acton_ern::Ern;
+
+use crate::actor::SupervisionStrategy;
+use crate::common::AgentHandle;
+use crate::message::ChildEscalation;
 
 /// System-wide signals used to control actor lifecycle events.
 ///
@@ -23,18 +30,37 @@ use std::fmt::Debug;
 #[non_exhaustive]
 pub enum SystemSignal {
     // Wake,
-    // Recreate,
-    // Suspend,
-    // Resume,
+    /// Signal from the supervisor telling a child that reported `Failed` to start over.
+    Recreate,
+    /// Signal to stop handling messages until `Resume` arrives.
+    ///
+    /// Messages received in the meantime are kept and handled in order on resume.
+    Suspend,
+    /// Signal to resume handling messages after `Suspend`.
+    Resume,
     /// Signal to terminate the actor.
     ///
     /// When an actor receives this signal, it should begin its shutdown process,
     /// cleaning up resources and preparing to stop execution.
     Terminate,
     // Supervise,
-    // Watch,
-    // Unwatch,
-    // Failed,
+    /// Signal asking the actor to send a `Terminated` message to the given watcher when it stops.
+    Watch(Box<AgentHandle>),
+    /// Signal removing a watcher previously registered with `Watch`.
+    Unwatch(Ern),
+    /// Signal from a child that failed and is stopping, for the supervisor's
+    /// `on_child_failure` hook.
+    ChildFailed(ChildEscalation),
+    /// Signal from a child that failed under `SupervisionStrategy::Restart`, asking the
+    /// supervisor whether to restart it. The child takes no messages until it hears back.
+    Failed {
+        /// The child that failed.
+        child: Box<AgentHandle>,
+        /// What went wrong.
+        error: Arc<anyhow::Error>,
+        /// The child's strategy, whose budget the supervisor counts its restarts against.
+        strategy: SupervisionStrategy,
+    },
 }
 
 // impl SystemSignal {
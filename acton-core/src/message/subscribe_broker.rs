@@ -19,13 +19,17 @@ use std::fmt::Debug;
 
 use acton_ern::{Ern};
 
-use crate::common::AgentHandle;
+use crate::common::{AgentHandle, MessageFilter};
 
 #[derive(Debug, Clone)]
 pub(crate) struct SubscribeBroker {
     pub(crate) subscriber_id: Ern,
-    pub(crate) message_type_id: TypeId,
+    /// Every type in the request is (un)subscribed together.
+    pub(crate) message_type_ids: Vec<TypeId>,
+    pub(crate) topic: String,
     pub(crate) subscriber_context: AgentHandle,
+    /// Only broadcasts that pass this filter are forwarded to the subscriber.
+    pub(crate) filter: Option<MessageFilter>,
 }
 // impl ActonMessage for SubscribeBroker {
 //     /// Returns a reference to the signal as `Any`.
@@ -0,0 +1,69 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::time::SystemTime;
+
+use acton_ern::Ern;
+
+/// A lifecycle change of any agent in the runtime.
+///
+/// Every agent broadcasts these through the broker as it starts, restarts and stops, so
+/// subscribe to this type to monitor the whole runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SystemEvent {
+    /// The agent started and is about to handle messages.
+    AgentStarted {
+        /// The id of the agent.
+        id: Ern,
+        /// When it happened.
+        at: SystemTime,
+    },
+    /// The agent was restarted after a handler panic.
+    AgentRestarted {
+        /// The id of the agent.
+        id: Ern,
+        /// When it happened.
+        at: SystemTime,
+    },
+    /// The agent stopped handling messages.
+    AgentStopped {
+        /// The id of the agent.
+        id: Ern,
+        /// When it happened.
+        at: SystemTime,
+    },
+}
+
+impl SystemEvent {
+    /// Returns the id of the agent the event is about.
+    pub fn id(&self) -> &Ern {
+        match self {
+            SystemEvent::AgentStarted { id, .. }
+            | SystemEvent::AgentRestarted { id, .. }
+            | SystemEvent::AgentStopped { id, .. } => id,
+        }
+    }
+
+    /// Returns when the event happened.
+    pub fn at(&self) -> SystemTime {
+        match self {
+            SystemEvent::AgentStarted { at, .. }
+            | SystemEvent::AgentRestarted { at, .. }
+            | SystemEvent::AgentStopped { at, .. } => *at,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::fmt::Debug;
+
+/// The error returned by `AgentHandle::try_send` when a message can't be sent right away.
+#[derive(Debug, PartialEq)]
+pub enum TrySendError<M> {
+    /// The agent's mailbox is full; the message is handed back so the caller can retry,
+    /// buffer, or drop it.
+    Full(M),
+    /// The agent has stopped or is draining and takes no new messages.
+    Closed,
+}
+
+impl<M> TrySendError<M> {
+    /// Returns the message that couldn't be sent, if the mailbox was full.
+    pub fn into_inner(self) -> Option<M> {
+        match self {
+            TrySendError::Full(message) => Some(message),
+            TrySendError::Closed => None,
+        }
+    }
+}
+
+impl<M> std::fmt::Display for TrySendError<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "The agent's mailbox is full"),
+            TrySendError::Closed => write!(f, "The agent takes no new messages"),
+        }
+    }
+}
+
+impl<M: Debug> std::error::Error for TrySendError<M> {}
@@ -14,16 +14,18 @@
  * limitations under that License.
  */
 
-// use std::any::TypeId;
+use std::any::TypeId;
 use std::fmt::Debug;
 
-// use acton_ern::{Ern};
-//
-// use crate::common::ActorRef;
+use acton_ern::Ern;
+
+use crate::common::AgentHandle;
 
 #[derive(Debug, Clone)]
 pub(crate) struct UnsubscribeBroker {
-    // ern: Ern<UnixTime>,
-    // message_type_id: TypeId,
-    // subscriber_ref: ActorRef,
+    pub(crate) subscriber_id: Ern,
+    /// Every type in the request is (un)subscribed together.
+    pub(crate) message_type_ids: Vec<TypeId>,
+    pub(crate) topic: String,
+    pub(crate) subscriber_context: AgentHandle,
 }
@@ -0,0 +1,102 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The largest frame accepted, so a corrupt length can't exhaust memory.
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// A message on the wire: the recipient's ERN, the message's registry tag, and its bytes.
+///
+/// Framed as a big-endian `u32` length followed by the recipient and the tag, each
+/// prefixed with their own `u32` length, then the body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub(crate) recipient: String,
+    pub(crate) tag: String,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Frame {
+    /// Writes the frame in a single write, so frames from concurrent senders never interleave.
+    pub(crate) async fn write_to<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> io::Result<()> {
+        let len = 8 + self.recipient.len() + self.tag.len() + self.body.len();
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+        }
+        let mut buffer = Vec::with_capacity(4 + len);
+        buffer.extend_from_slice(&(len as u32).to_be_bytes());
+        for field in [self.recipient.as_bytes(), self.tag.as_bytes()] {
+            buffer.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            buffer.extend_from_slice(field);
+        }
+        buffer.extend_from_slice(&self.body);
+        writer.write_all(&buffer).await?;
+        writer.flush().await
+    }
+
+    /// Reads the next frame, or returns `None` if the stream ended cleanly between frames.
+    pub(crate) async fn read_from<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<Frame>> {
+        let len = match reader.read_u32().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+        }
+        let mut buffer = vec![0; len];
+        reader.read_exact(&mut buffer).await?;
+
+        let mut rest = buffer.as_slice();
+        let recipient = take_string(&mut rest)?;
+        let tag = take_string(&mut rest)?;
+        Ok(Some(Frame { recipient, tag, body: rest.to_vec() }))
+    }
+}
+
+/// Splits a length-prefixed string off the front of `bytes`.
+fn take_string(bytes: &mut &[u8]) -> io::Result<String> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed frame");
+    let (len, rest) = bytes.split_first_chunk::<4>().ok_or_else(invalid)?;
+    let len = u32::from_be_bytes(*len) as usize;
+    if rest.len() < len {
+        return Err(invalid());
+    }
+    let (field, rest) = rest.split_at(len);
+    *bytes = rest;
+    String::from_utf8(field.to_vec()).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_frame_round_trip() {
+        let frame = Frame { recipient: "ern:acton:agent".into(), tag: "ping".into(), body: b"null".to_vec() };
+        let mut wire = Vec::new();
+        frame.write_to(&mut wire).await.unwrap();
+        frame.write_to(&mut wire).await.unwrap();
+
+        let mut reader = wire.as_slice();
+        assert_eq!(Frame::read_from(&mut reader).await.unwrap(), Some(frame.clone()));
+        assert_eq!(Frame::read_from(&mut reader).await.unwrap(), Some(frame));
+        assert_eq!(Frame::read_from(&mut reader).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,94 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use acton_ern::Ern;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::Mutex;
+use tracing::{instrument, trace, warn};
+
+use crate::message::MessageError;
+use crate::remote::frame::Frame;
+use crate::traits::SerializableMessage;
+
+/// A handle to an agent in another process, reached through that process's `RemoteSystem`.
+///
+/// Clones share one connection.
+///
+/// Only one-way sends are supported, so this isn't an `Actor` like `AgentHandle`: there's
+/// no `ask`, supervision, subscription or child management, and the agent's replies to
+/// remote messages are dropped. Sends report failure rather than logging it, and take only
+/// `SerializableMessage`s.
+#[derive(Debug, Clone)]
+pub struct RemoteAgentHandle {
+    id: Ern,
+    peer: SocketAddr,
+    stream: Arc<Mutex<TcpStream>>,
+}
+
+impl RemoteAgentHandle {
+    /// Connects to the `RemoteSystem` listening on `addr`, for sending to its agent `id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection can't be made. The agent isn't looked up until a
+    /// message arrives for it.
+    pub async fn connect(addr: impl ToSocketAddrs, id: Ern) -> anyhow::Result<RemoteAgentHandle> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        let peer = stream.peer_addr()?;
+        trace!(agent = id.to_string(), %peer, "Connected to remote agent");
+        Ok(RemoteAgentHandle { id, peer, stream: Arc::new(Mutex::new(stream)) })
+    }
+
+    /// Returns the ERN of the remote agent.
+    pub fn id(&self) -> &Ern {
+        &self.id
+    }
+
+    /// Returns the address of the remote system.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Serializes the message and sends it to the remote agent.
+    ///
+    /// Delivery isn't confirmed: the message can still be lost if the connection drops
+    /// after it was sent, or if the remote system doesn't know its type or the agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MessageError::SerializationFailed` if the message can't be serialized, and
+    /// `MessageError::TransportClosed` once the connection is lost.
+    #[instrument(skip(self, message), fields(agent = %self.id))]
+    pub async fn send<M: SerializableMessage>(&self, message: M) -> Result<(), MessageError> {
+        let frame = Frame { recipient: self.id.to_string(), tag: M::TAG.to_string(), body: message.to_bytes()? };
+        let mut stream = self.stream.lock().await;
+        // The remote side never writes, so a readable stream means it closed the connection
+        let mut probe = [0; 1];
+        match stream.try_read(&mut probe) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            _ => return Err(MessageError::TransportClosed),
+        }
+        frame.write_to(&mut *stream).await.map_err(|e| {
+            warn!(peer = %self.peer, "Remote send failed: {}", e);
+            MessageError::TransportClosed
+        })
+    }
+}
@@ -0,0 +1,153 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use acton_ern::Ern;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{error, instrument, trace, warn};
+
+use crate::common::{bounded_mailbox, AgentHandle, AgentRuntime};
+use crate::message::{MessageAddress, MessageError, MessageRegistry, OutboundEnvelope};
+use crate::remote::frame::Frame;
+use crate::traits::{ActonMessage, Actor};
+
+/// Accepts connections from `RemoteAgentHandle`s in other processes and delivers the messages
+/// they send to this runtime's agents.
+#[derive(Debug, Clone)]
+pub struct RemoteSystem {
+    local_addr: SocketAddr,
+    stopped: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl RemoteSystem {
+    /// Starts listening on `addr` for messages to the agents of `runtime`.
+    ///
+    /// Incoming messages are rebuilt with `registry`, so every message type remote senders
+    /// use must be registered there.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address can't be bound.
+    pub async fn listen(
+        addr: impl ToSocketAddrs,
+        runtime: AgentRuntime,
+        registry: MessageRegistry,
+    ) -> anyhow::Result<RemoteSystem> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let system = RemoteSystem { local_addr, stopped: CancellationToken::new(), tracker: TaskTracker::new() };
+        trace!(%local_addr, "Remote system listening");
+
+        let registry = Arc::new(registry);
+        let stopped = system.stopped.clone();
+        let tracker = system.tracker.clone();
+        system.tracker.spawn(async move {
+            loop {
+                let (stream, peer) = tokio::select! {
+                    _ = stopped.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!("Failed to accept a remote connection: {}", e);
+                            continue;
+                        }
+                    },
+                };
+                trace!(%peer, "Accepted remote connection");
+                let connection = receive(stream, runtime.clone(), registry.clone());
+                let stopped = stopped.clone();
+                tracker.spawn(async move {
+                    tokio::select! {
+                        _ = stopped.cancelled() => trace!(%peer, "Closing remote connection"),
+                        _ = connection => trace!(%peer, "Remote connection closed"),
+                    }
+                });
+            }
+        });
+        Ok(system)
+    }
+
+    /// Returns the address the system is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting connections and closes the open ones.
+    pub async fn shutdown(&self) {
+        self.stopped.cancel();
+        self.tracker.close();
+        self.tracker.wait().await;
+    }
+}
+
+/// Delivers the messages arriving on one connection until it closes.
+#[instrument(skip_all)]
+async fn receive(mut stream: TcpStream, runtime: AgentRuntime, registry: Arc<MessageRegistry>) {
+    // Looking an agent up by ERN string scans the registry, so remember what was found
+    let mut recipients: HashMap<String, AgentHandle> = HashMap::new();
+    let return_address = remote_return_address();
+    loop {
+        let frame = match Frame::read_from(&mut stream).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Dropping remote connection: {}", e);
+                return;
+            }
+        };
+        let message = match registry.deserialize(&frame.tag, &frame.body) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!(recipient = frame.recipient, "Dropping remote message: {}", e);
+                continue;
+            }
+        };
+        let cached = recipients.get(&frame.recipient).filter(|handle| !handle.outbox.is_closed()).cloned();
+        let Some(recipient) = cached.or_else(|| find_by_name(&runtime, &frame.recipient)) else {
+            warn!(recipient = frame.recipient, "Dropping remote message for unknown agent");
+            continue;
+        };
+        recipients.insert(frame.recipient, recipient.clone());
+        OutboundEnvelope::new_with_recipient(return_address.clone(), recipient.reply_address())
+            .send_message_inner(Arc::<dyn ActonMessage>::from(message))
+            .await;
+    }
+}
+
+/// The return address of messages from remote senders.
+///
+/// Nothing travels back over a connection, so replies are refused here rather than landing
+/// in the recipient's own mailbox.
+fn remote_return_address() -> MessageAddress {
+    let (outbox, _) = bounded_mailbox(1);
+    MessageAddress::new(outbox, Ern::with_root("remote").unwrap())
+}
+
+/// Finds a started agent by the string form of its ERN.
+fn find_by_name(runtime: &AgentRuntime, name: &str) -> Option<AgentHandle> {
+    runtime
+        .0
+        .registry
+        .iter()
+        .find(|entry| entry.key().to_string() == name)
+        .map(|entry| entry.value().clone())
+}
@@ -25,6 +25,9 @@ pub trait ActonMessage: DynClone + Any + Send + Sync + Debug {
 
     /// Returns a mutable reference to the message as `Any`.
     fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Returns the name of the message's type, for logs and metrics.
+    fn type_name(&self) -> &'static str;
 }
 
 impl<T> ActonMessage for T
@@ -38,4 +41,8 @@ where
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
 }
@@ -18,7 +18,6 @@ use std::future::Future;
 
 use acton_ern::{Ern};
 use async_trait::async_trait;
-use dashmap::DashMap;
 use tokio_util::task::TaskTracker;
 use tracing::*;
 
@@ -33,8 +32,10 @@ pub trait Actor {
     fn reply_address(&self) -> MessageAddress;
     /// Returns an envelope for the specified recipient and message, ready to send.
     fn create_envelope(&self, recipient_address: Option<MessageAddress>) -> OutboundEnvelope;
-    /// Returns a map of the actor's children.
-    fn children(&self) -> DashMap<String, AgentHandle>;
+    /// Returns the actor's direct children, keyed by their ERNs.
+    ///
+    /// This is a snapshot: children supervised or stopped afterwards are not reflected in it.
+    fn children(&self) -> Vec<(Ern, AgentHandle)>;
 
     /// Finds a child actor by its ERN.
     ///
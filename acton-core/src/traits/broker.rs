@@ -27,8 +27,22 @@ use crate::traits::Actor;
 #[async_trait]
 pub trait Broker: Clone + Debug + Default {
     /// Broadcast a message from the broker.
+    ///
+    /// Each subscriber gets the broadcasts of one publisher in the order they were awaited.
+    /// There is no order between different publishers' broadcasts, which the broker takes
+    /// as they reach it. The broker queues every subscriber's copies separately, so the
+    /// future finishes once the broker has the message, not once subscribers have it.
     fn broadcast(&self, message: impl ActonMessage) -> impl Future<Output=()> + Send + Sync + '_;
+    /// Broadcast a message from the broker to the subscribers of `topic`.
+    ///
+    /// Only agents that subscribed to the message's type with
+    /// [`subscribe_topic`](crate::traits::Subscribable::subscribe_topic) on the same topic receive it.
+    fn broadcast_topic(&self, topic: &str, message: impl ActonMessage) -> impl Future<Output=()> + Send + Sync + '_;
     /// Broadcast a message from the broker synchronously.
+    ///
+    /// The send isn't awaited, so with the broker's usual bounded mailbox, several calls in a
+    /// row may reach the broker, and the subscribers, out of order. Use `broadcast` where
+    /// order matters.
     fn broadcast_sync(&self, message: impl ActonMessage) -> anyhow::Result<()>
     where
         Self: Actor,
@@ -17,11 +17,15 @@
 pub use acton_message::ActonMessage;
 pub use actor::Actor;
 pub use broker::Broker;
+#[cfg(feature = "serde")]
+pub use serializable_message::SerializableMessage;
 pub use subscribable::Subscribable;
 pub use subscriber::Subscriber;
 
 mod acton_message;
 mod actor;
+#[cfg(feature = "serde")]
+mod serializable_message;
 mod subscribable;
 mod subscriber;
 mod broker;
@@ -0,0 +1,41 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::message::MessageError;
+use crate::traits::ActonMessage;
+
+/// A message that can be turned into bytes and back, to be stored or sent to another process.
+///
+/// Register the type with a `MessageRegistry` so the receiving side can rebuild it from its
+/// [`TAG`](SerializableMessage::TAG) and bytes.
+pub trait SerializableMessage: ActonMessage + Serialize + DeserializeOwned {
+    /// Names the type in serialized form. It must be unique among registered types, and
+    /// should stay the same when the type is renamed or moved.
+    const TAG: &'static str;
+
+    /// Serializes the message.
+    fn to_bytes(&self) -> Result<Vec<u8>, MessageError> {
+        serde_json::to_vec(self).map_err(|e| MessageError::SerializationFailed(e.to_string()))
+    }
+
+    /// Deserializes a message serialized with [`SerializableMessage::to_bytes`].
+    fn from_bytes(bytes: &[u8]) -> Result<Self, MessageError> {
+        serde_json::from_slice(bytes).map_err(|e| MessageError::SerializationFailed(e.to_string()))
+    }
+}
@@ -20,11 +20,17 @@ use std::future::Future;
 use async_trait::async_trait;
 use tracing::*;
 
-use crate::message::{SubscribeBroker, UnsubscribeBroker};
+use crate::common::MessageFilter;
+use crate::message::{MessageError, SubscribeBroker, UnsubscribeBroker, DEFAULT_TOPIC};
 use crate::traits::{ActonMessage, Actor};
 use crate::traits::subscriber::Subscriber;
 
 /// Trait for types that can subscribe to and unsubscribe from messages.
+///
+/// # Errors
+///
+/// Each method's future fails with `MessageError::NoBroker` if the agent has no broker, as
+/// its request would otherwise be dropped without a word.
 #[async_trait]
 pub trait Subscribable {
     /// Subscribes the implementing type to messages of type `T`.
@@ -35,10 +41,52 @@ pub trait Subscribable {
     ///
     /// # Returns
     ///
-    /// A `Future` that resolves to `()` when the subscription is complete.
+    /// A `Future` that resolves once the subscription has been delivered to the broker.
+    ///
+    /// # Errors
+    ///
+    /// See [`Subscribable`](Subscribable#errors).
     fn subscribe<T: ActonMessage + Send + Sync + 'static>(
         &self,
-    ) -> impl Future<Output=()> + Send + Sync + '_
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
+    where
+        Self: Actor + Subscriber;
+
+    /// Subscribes the implementing type to messages of type `T` that match `predicate`.
+    ///
+    /// The broker checks each broadcast `T` against `predicate` and only forwards the ones
+    /// it accepts.
+    ///
+    /// # Returns
+    ///
+    /// A `Future` that resolves once the subscription has been delivered to the broker.
+    ///
+    /// # Errors
+    ///
+    /// See [`Subscribable`](Subscribable#errors).
+    fn subscribe_filtered<T: ActonMessage + Send + Sync + 'static>(
+        &self,
+        predicate: impl Fn(&T) -> bool + Send + Sync + 'static,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
+    where
+        Self: Actor + Subscriber;
+
+    /// Subscribes the implementing type to messages of type `T` published to `topic`.
+    ///
+    /// Broadcasts of `T` on other topics, including plain [`broadcast`](crate::traits::Broker::broadcast)s,
+    /// are not delivered to this subscription.
+    ///
+    /// # Returns
+    ///
+    /// A `Future` that resolves once the subscription has been delivered to the broker.
+    ///
+    /// # Errors
+    ///
+    /// See [`Subscribable`](Subscribable#errors).
+    fn subscribe_topic<T: ActonMessage + Send + Sync + 'static>(
+        &self,
+        topic: &str,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
     where
         Self: Actor + Subscriber;
 
@@ -47,9 +95,35 @@ pub trait Subscribable {
     /// # Type Parameters
     ///
     /// * `T`: The type of message to unsubscribe from. Must implement `ActonMessage`.
-    fn unsubscribe<T: ActonMessage>(&self)
+    ///
+    /// # Returns
+    ///
+    /// A `Future` that resolves once the unsubscribe request has been delivered to the broker.
+    ///
+    /// # Errors
+    ///
+    /// See [`Subscribable`](Subscribable#errors).
+    fn unsubscribe<T: ActonMessage + Send + Sync + 'static>(
+        &self,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
+    where
+        Self: Actor + Subscriber;
+
+    /// Unsubscribes the implementing type from messages of type `T` published to `topic`.
+    ///
+    /// # Returns
+    ///
+    /// A `Future` that resolves once the unsubscribe request has been delivered to the broker.
+    ///
+    /// # Errors
+    ///
+    /// See [`Subscribable`](Subscribable#errors).
+    fn unsubscribe_topic<T: ActonMessage + Send + Sync + 'static>(
+        &self,
+        topic: &str,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
     where
-        Self: Actor + Subscriber + Send + Sync + 'static;
+        Self: Actor + Subscriber;
 }
 
 /// Implementation of `Subscribable` for any type that implements `ActonMessage + Send + Sync + 'static`.
@@ -60,58 +134,116 @@ where
 {
     fn subscribe<M: ActonMessage + Send + Sync + 'static>(
         &self,
-    ) -> impl Future<Output=()> + Send + Sync + '_
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
+    where
+        Self: Actor + Subscriber + 'static,
+    {
+        subscribe_with::<M, _>(self, DEFAULT_TOPIC, None)
+    }
+
+    fn subscribe_filtered<M: ActonMessage + Send + Sync + 'static>(
+        &self,
+        predicate: impl Fn(&M) -> bool + Send + Sync + 'static,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
     where
         Self: Actor + Subscriber + 'static,
     {
-        let subscriber_id = self.id();
-        let message_type_id = TypeId::of::<M>();
-        let message_type_name = std::any::type_name::<M>().to_string();
-        let subscription = SubscribeBroker {
-            subscriber_id,
-            message_type_id,
-            subscriber_context: self.clone_ref(),
-        };
-        let broker = self.get_broker();
-        let ern = self.id().clone();
-
-        async move {
-            trace!( type_id=?message_type_id, subscriber_ern = ern.to_string(), "Subscribing to type_name {}", message_type_name);
-            if let Some(broadcast_broker) = broker {
-                let broker_key = broadcast_broker.name();
-                trace!(
-                    "Subscribing to type_name {} with {}",
-                    message_type_name,
-                    broker_key
-                );
-                broadcast_broker.send(subscription).await;
-            } else {
-                error!( subscriber_ern = ern.to_string(), "No broker found for type_name {}", message_type_name);
-            }
-        }
+        subscribe_with::<M, _>(self, DEFAULT_TOPIC, Some(MessageFilter::new(predicate)))
     }
-    fn unsubscribe<M: ActonMessage>(&self)
+
+    fn subscribe_topic<M: ActonMessage + Send + Sync + 'static>(
+        &self,
+        topic: &str,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
+    where
+        Self: Actor + Subscriber + 'static,
+    {
+        subscribe_with::<M, _>(self, topic, None)
+    }
+
+    fn unsubscribe<M: ActonMessage + Send + Sync + 'static>(
+        &self,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
     where
         Self: Actor + Subscriber,
     {
-        // let subscriber_id = self.ern();
-        let subscription = UnsubscribeBroker {
-            // ern: subscriber_id,
-            // message_type_id: TypeId::of::<M>(),
-            // subscriber_ref: self.clone_ref(),
-        };
-        let broker = self.get_broker();
-        if let Some(broker) = broker {
-            let broker = broker.clone();
-            tokio::spawn(async move {
-                broker.send(subscription).await;
-            });
-        }
+        unsubscribe_with::<M, _>(self, DEFAULT_TOPIC)
+    }
+
+    fn unsubscribe_topic<M: ActonMessage + Send + Sync + 'static>(
+        &self,
+        topic: &str,
+    ) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + '_
+    where
+        Self: Actor + Subscriber,
+    {
+        unsubscribe_with::<M, _>(self, topic)
+    }
+}
+
+/// Sends an unsubscribe for `M` on `topic` to the subscriber's broker.
+fn unsubscribe_with<'a, M, S>(
+    subscriber: &'a S,
+    topic: &str,
+) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + 'a
+where
+    M: ActonMessage + Send + Sync + 'static,
+    S: Actor + Subscriber,
+{
+    let message_type_id = TypeId::of::<M>();
+    let message_type_name = std::any::type_name::<M>().to_string();
+    let subscription = UnsubscribeBroker {
+        subscriber_id: subscriber.id(),
+        message_type_ids: vec![message_type_id],
+        topic: topic.to_string(),
+        subscriber_context: subscriber.clone_ref(),
+    };
+    let broker = subscriber.get_broker();
+    let ern = subscriber.id().clone();
+
+    async move {
+        trace!(type_id = ?message_type_id, subscriber_ern = ern.to_string(), "Unsubscribing from type_name {}", message_type_name);
+        let broadcast_broker = broker.ok_or(MessageError::NoBroker)?;
+        broadcast_broker.send(subscription).await;
+        Ok(())
+    }
+}
+
+/// Sends a subscription for `M` on `topic`, with an optional filter, to the subscriber's broker.
+fn subscribe_with<'a, M, S>(
+    subscriber: &'a S,
+    topic: &str,
+    filter: Option<MessageFilter>,
+) -> impl Future<Output=Result<(), MessageError>> + Send + Sync + 'a
+where
+    M: ActonMessage + Send + Sync + 'static,
+    S: Actor + Subscriber + 'static,
+{
+    let subscriber_id = subscriber.id();
+    let message_type_id = TypeId::of::<M>();
+    let message_type_name = std::any::type_name::<M>().to_string();
+    let subscription = SubscribeBroker {
+        subscriber_id,
+        message_type_ids: vec![message_type_id],
+        topic: topic.to_string(),
+        subscriber_context: subscriber.clone_ref(),
+        filter,
+    };
+    let broker = subscriber.get_broker();
+    let ern = subscriber.id().clone();
+
+    async move {
+        trace!( type_id=?message_type_id, subscriber_ern = ern.to_string(), "Subscribing to type_name {}", message_type_name);
+        // An agent without a broker would never receive a broadcast, so say so rather than
+        // dropping the subscription
+        let broadcast_broker = broker.ok_or(MessageError::NoBroker)?;
+        let broker_key = broadcast_broker.name();
         trace!(
-            type_id = ?TypeId::of::<M>(),
-            repository_actor = self.id().to_string(),
-            "Unsubscribed to {}",
-            std::any::type_name::<M>()
+            "Subscribing to type_name {} with {}",
+            message_type_name,
+            broker_key
         );
+        broadcast_broker.send(subscription).await;
+        Ok(())
     }
 }
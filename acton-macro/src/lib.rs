@@ -61,6 +61,39 @@ pub fn acton_message(_attr: TokenStream, item: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Derives `ActonMessage` for a type.
+///
+/// `ActonMessage` is implemented for every `Any + Send + Sync + Debug + Clone` type, so the
+/// derive adds no impl of its own and can't conflict with one. It checks those bounds where
+/// the type is declared, so a message that can't be sent fails to compile there rather than
+/// at the first `send`. Generic messages are checked where they are used.
+///
+/// See the `acton_reactive` prelude for an example.
+#[proc_macro_derive(ActonMessage)]
+pub fn derive_acton_message(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    // Generic messages can only be checked once their parameters are known.
+    if !input.generics.params.is_empty() {
+        return TokenStream::new();
+    }
+    let name = &input.ident;
+
+    let expanded = quote! {
+        const _: () = {
+            fn assert_acton_message<
+                T: ::std::any::Any + Send + Sync + ::std::fmt::Debug + Clone + 'static,
+            >() {
+            }
+            fn assert_all() {
+                assert_acton_message::<#name>();
+            }
+        };
+    };
+
+    TokenStream::from(expanded)
+}
+
 #[proc_macro_attribute]
 pub fn acton_actor(_attr: TokenStream, item: TokenStream) -> TokenStream {
     // Parse the input tokens into a syntax tree.
@@ -163,7 +163,7 @@ async fn main() {
                         SetForegroundColor(Color::DarkYellow),
                         Print("\u{2713}  "), // Checkmark
                         SetForegroundColor(Color::Yellow),
-                        Print(format!("{}", who)),
+                        Print(who),
                         SetForegroundColor(Color::DarkYellow),
                         Print(format!(" is {}!\n", what)),
                         ResetColor
@@ -176,7 +176,7 @@ async fn main() {
                         SetForegroundColor(Color::DarkCyan),
                         Print("\u{2139}  "), // Info symbol
                         SetForegroundColor(Color::Cyan),
-                        Print(format!("{}", who)),
+                        Print(who),
                         SetForegroundColor(Color::DarkCyan),
                         Print(format!(" is {}!\n", what)),
                         ResetColor
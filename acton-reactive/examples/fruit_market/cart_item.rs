@@ -43,10 +43,10 @@ impl CartItem {
         let name = name.into();
         // Create a unique ID based on the item name
         let mut upc = "upc_".to_string();
-        upc.push_str(&*name.clone());
+        upc.push_str(&name);
 
         CartItem {
-            name: name.into(),
+            name,
             quantity,
             upc: upc.create_type_id::<V7>(),
             ..Default::default()
@@ -116,10 +116,10 @@ impl Display for Cost {
 
 // Multiply cost by a quantity
 impl Mul<i32> for Cost {
-    type Output = ();
+    type Output = i32;
 
     fn mul(self, rhs: i32) -> Self::Output {
-        self.0 * rhs;
+        self.0 * rhs
     }
 }
 
@@ -42,6 +42,7 @@ pub(crate) struct PriceService;
 
 impl PriceService {
     // Create a new price service agent
+    #[allow(clippy::new_ret_no_self)]
     #[instrument(skip(app))]
     pub(crate) async fn new(app: &mut AgentRuntime) -> anyhow::Result<AgentHandle> {
         // Set up the service configuration
@@ -138,7 +138,7 @@ impl From<DisplayItem> for FormattedItem {
                         COLOR_MEDIUM_BLUE.2
                     )
                     .paint(item.quantity().to_string()),
-                    MoneyFmt(**item.cost()).to_string(),
+                    MoneyFmt(**item.cost()),
                     RGB(COLOR_DARK_GREY.0, COLOR_DARK_GREY.1, COLOR_DARK_GREY.2).paint("│"),
                     RGB(COLOR_GREEN.0, COLOR_GREEN.1, COLOR_GREEN.2)
                         .paint(MoneyFmt(item.price().0).to_string())
@@ -304,9 +304,9 @@ impl Printer {
     // Update the entire display
     fn repaint(printer: &Printer) -> anyhow::Result<()> {
         Self::print_header()?;
-        Self::print_items(&printer)?;
-        Self::print_totals(&printer)?;
-        Self::print_help(&printer)?;
+        Self::print_items(printer)?;
+        Self::print_totals(printer)?;
+        Self::print_help(printer)?;
         Ok(())
     }
 
@@ -321,17 +321,17 @@ impl Printer {
             .paint("─".repeat(COLS as usize + 1))
             .to_string();
         queue!(stdout, cursor::MoveTo(PAD_LEFT, top))?;
-        stdout.write(header_border.as_bytes())?;
+        stdout.write_all(header_border.as_bytes())?;
 
         // Draw centered title
         let padding = (COLS as usize).saturating_sub(TRANSACTION_RECEIPT.len()) / 2;
         let centered_text = format!("{}{}", " ".repeat(padding), TRANSACTION_RECEIPT);
         queue!(stdout, cursor::MoveTo(PAD_LEFT, top + 1))?;
-        stdout.write(centered_text.as_bytes())?;
+        stdout.write_all(centered_text.as_bytes())?;
 
         // Draw bottom border
         queue!(stdout, cursor::MoveTo(PAD_LEFT, top + 2))?;
-        stdout.write(
+        stdout.write_all(
             RGB(COLOR_DARK_GREY.0, COLOR_DARK_GREY.1, COLOR_DARK_GREY.2)
                 .paint(format!(
                     "{}{}{}",
@@ -407,7 +407,7 @@ impl Printer {
 
         queue!(stdout, cursor::MoveTo(PAD_LEFT, top))?;
         queue!(stdout, Clear(ClearType::CurrentLine))?;
-        stdout.write(separator.as_bytes())?;
+        stdout.write_all(separator.as_bytes())?;
 
         // Calculate totals
         let subtotal = printer
@@ -454,17 +454,17 @@ impl Printer {
 
         queue!(stdout, cursor::MoveTo(start_col, top + 1))?;
         queue!(stdout, Clear(ClearType::CurrentLine))?;
-        stdout.write(subtotal_str.as_bytes())?;
+        stdout.write_all(subtotal_str.as_bytes())?;
 
         queue!(stdout, cursor::MoveTo(start_col, top + 2))?;
         queue!(stdout, Clear(ClearType::CurrentLine))?;
         stdout.flush()?;
-        stdout.write(tax_str.as_bytes())?;
+        stdout.write_all(tax_str.as_bytes())?;
 
         queue!(stdout, cursor::MoveTo(start_col, top + 3))?;
         queue!(stdout, Clear(ClearType::CurrentLine))?;
         stdout.flush()?;
-        stdout.write(total_due_str.as_bytes())?;
+        stdout.write_all(total_due_str.as_bytes())?;
         stdout.flush()?;
 
         Ok(())
@@ -493,7 +493,7 @@ impl Printer {
         queue!(stdout, cursor::MoveTo(start_col, top + 1))?;
         queue!(stdout, Clear(ClearType::FromCursorDown))?;
         queue!(stdout, cursor::MoveDown(1))?;
-        stdout.write(
+        stdout.write_all(
             RGB(COLOR_HELP_TEXT.0, COLOR_HELP_TEXT.1, COLOR_HELP_TEXT.2)
                 .paint(help_msg)
                 .to_string()
@@ -24,6 +24,45 @@
 /// Prelude module for convenient imports.
 ///
 /// This module re-exports commonly used and required items from the `acton_macro` and `acton_core` crates.
+///
+/// Any `Clone + Debug + Send + Sync + 'static` type is a message. Deriving `ActonMessage`
+/// checks this where the message is declared:
+///
+/// ```
+/// use acton_reactive::prelude::*;
+///
+/// #[derive(Debug, Clone, ActonMessage)]
+/// struct Ping;
+///
+/// #[derive(Debug, Clone, ActonMessage)]
+/// struct Wrapped<T: Clone + Send + Sync + std::fmt::Debug + 'static>(T);
+///
+/// #[derive(Debug, Default)]
+/// struct Counter {
+///     pings: usize,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut runtime: AgentRuntime = ActonApp::launch();
+///     let mut counter = runtime.new_agent::<Counter>().await;
+///     counter
+///         .act_on::<Ping>(|agent, _| {
+///             agent.model.pings += 1;
+///             AgentReply::immediate()
+///         })
+///         .act_on::<Wrapped<u32>>(|_, _| AgentReply::immediate())
+///         .after_stop(|agent| {
+///             assert_eq!(agent.model.pings, 1);
+///             AgentReply::immediate()
+///         });
+///     let counter = counter.start().await;
+///
+///     counter.send(Ping).await;
+///     counter.send(Wrapped(42u32)).await;
+///     runtime.shutdown_all().await
+/// }
+/// ```
 pub mod prelude {
     pub use acton_core::prelude::*;
     pub use acton_macro::*;
@@ -238,6 +238,24 @@ async fn test_find_child_actor() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[acton_test]
+async fn test_child_ern_under_parent() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let parent = runtime.new_agent_with_name::<Counter>("parent".to_string()).await;
+    let first = parent.create_child("first".to_string()).await?;
+    let second = parent.create_child("second".to_string()).await?;
+
+    // Each child's ERN extends its parent's, so siblings never share one
+    let parent_id = parent.id().to_string();
+    for child in [&first, &second] {
+        assert!(child.id().to_string().starts_with(&parent_id), "{} isn't under {}", child.id(), parent_id);
+    }
+    assert_ne!(first.id(), second.id(), "siblings should have their own ERNs");
+    Ok(())
+}
+
 #[acton_test]
 async fn test_actor_mutation() -> anyhow::Result<()> {
     initialize_tracing();
@@ -365,3 +383,1033 @@ async fn test_child_count_in_reactor() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[acton_test]
+async fn test_handled_types() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .act_on::<Ping>(|_, _| AgentReply::immediate())
+        .act_on_fallible::<Pong>(|_, _| Box::pin(async { Ok(()) }))
+        .act_once::<StatusReport>(|_, _| AgentReply::immediate());
+    let counter = counter.start().await;
+
+    let mut handled = counter.handled_types();
+    handled.sort();
+    let mut expected = vec![TypeId::of::<Ping>(), TypeId::of::<Pong>(), TypeId::of::<StatusReport>()];
+    expected.sort();
+    assert_eq!(handled, expected, "every registered message type should be reported");
+    let names = counter.handled_type_names();
+    assert_eq!(names.len(), 3);
+    assert!(names.iter().any(|name| name.ends_with("::Ping")), "got {names:?}");
+    assert!(!counter.handled_types().contains(&TypeId::of::<Tally>()));
+
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_pipe_to_self() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (done, mut finished) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let report_done = done.clone();
+    counter
+        .act_on::<Ping>(|agent, _| {
+            agent.pipe_to_self(async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                StatusReport::Complete(42)
+            });
+            agent.try_pipe_to_self(async { Err::<Pong, _>("lookup failed") }, |_| Tally::AddCount);
+            AgentReply::immediate()
+        })
+        .act_on::<StatusReport>(move |agent, context| {
+            let StatusReport::Complete(n) = context.message();
+            agent.model.count += n;
+            let _ = report_done.send(());
+            AgentReply::immediate()
+        })
+        .act_on::<Tally>(move |agent, _| {
+            agent.model.count += 1;
+            let _ = done.send(());
+            AgentReply::immediate()
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.count, 43, "both piped results should be handled");
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    counter.send(Ping).await;
+    for _ in 0..2 {
+        tokio::time::timeout(Duration::from_secs(1), finished.recv()).await?;
+    }
+    counter.stop().await?;
+    Ok(())
+}
+
+/// Records the level of every span and event inside the run of an agent whose name
+/// contains `agent`.
+struct AgentLevels {
+    agent: &'static str,
+    levels: std::sync::Arc<std::sync::Mutex<Vec<(&'static str, tracing::Level)>>>,
+}
+
+/// The `actor` field of an agent's run span.
+struct ActorField(String);
+
+impl tracing::field::Visit for ActorField {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "actor" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+impl AgentLevels {
+    fn watched<S: for<'a> tracing_subscriber::registry::LookupSpan<'a>>(
+        &self,
+        mut scope: tracing_subscriber::registry::Scope<'_, S>,
+    ) -> bool {
+        scope.any(|span| span.extensions().get::<ActorField>().is_some_and(|actor| actor.0.contains(self.agent)))
+    }
+}
+
+impl<S> tracing_subscriber::Layer<S> for AgentLevels
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let span = ctx.span(id).unwrap();
+        if span.name() == "wake" {
+            let mut actor = ActorField(String::new());
+            attrs.record(&mut actor);
+            span.extensions_mut().insert(actor);
+        }
+        if self.watched(span.scope()) {
+            self.levels.lock().unwrap().push((span.name(), *span.metadata().level()));
+        }
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        if ctx.event_scope(event).is_some_and(|scope| self.watched(scope)) {
+            self.levels.lock().unwrap().push((event.metadata().name(), *event.metadata().level()));
+        }
+    }
+}
+
+// Runs on the test's thread, where the capturing subscriber is the default
+#[tokio::test]
+async fn test_trace_level() -> anyhow::Result<()> {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let levels = std::sync::Arc::default();
+    let subscriber = tracing_subscriber::registry()
+        .with(AgentLevels { agent: "quiet", levels: std::sync::Arc::clone(&levels) });
+    let _default = tracing::subscriber::set_default(subscriber);
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("quiet").unwrap(), None, None)?.with_trace_level(tracing::Level::TRACE);
+    let mut quiet = runtime.create_actor_with_config::<Counter>(config).await;
+    quiet.act_on::<Ping>(|agent, _| {
+        agent.model.count += 1;
+        AgentReply::immediate()
+    });
+    let quiet = quiet.start().await;
+
+    for _ in 0..3 {
+        quiet.send(Ping).await;
+    }
+    quiet.stop().await?;
+
+    let levels = levels.lock().unwrap();
+    assert_eq!(
+        levels.iter().filter(|(name, _)| *name == "handle").count(),
+        3,
+        "each message should open a span inside the agent's run"
+    );
+    let louder: Vec<_> = levels.iter().filter(|(_, level)| *level < tracing::Level::TRACE).collect();
+    assert!(louder.is_empty(), "a quieted agent should emit nothing above TRACE, got {louder:?}");
+    Ok(())
+}
+
+/// Collects the messages of warnings.
+struct Warnings(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+/// The message of an event.
+struct EventMessage(String);
+
+impl tracing::field::Visit for EventMessage {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Warnings {
+    fn on_event(&self, event: &tracing::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+        if *event.metadata().level() == tracing::Level::WARN {
+            let mut message = EventMessage(String::new());
+            event.record(&mut message);
+            self.0.lock().unwrap().push(message.0);
+        }
+    }
+}
+
+// Runs on the test's thread, where the capturing subscriber is the default
+#[tokio::test]
+async fn test_duplicate_handler_warns() -> anyhow::Result<()> {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let warnings = std::sync::Arc::default();
+    let subscriber = tracing_subscriber::registry().with(Warnings(std::sync::Arc::clone(&warnings)));
+    let _default = tracing::subscriber::set_default(subscriber);
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter.act_on::<Ping>(|_, _| AgentReply::immediate());
+    assert!(warnings.lock().unwrap().is_empty(), "a first handler is no cause for a warning");
+    counter.act_on::<Ping>(|_, _| AgentReply::immediate());
+
+    let warnings = warnings.lock().unwrap();
+    assert_eq!(warnings.len(), 1, "replacing a handler should warn once: {warnings:?}");
+    assert!(warnings[0].contains("Ping"), "the warning should name the message type: {warnings:?}");
+    Ok(())
+}
+
+// Panics on purpose, which `acton_test` would count as a failure
+#[tokio::test(flavor = "multi_thread")]
+async fn test_duplicate_handler_strict() -> anyhow::Result<()> {
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("strict")?.with_strict_handlers(true);
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    counter.act_on::<Ping>(|_, _| AgentReply::immediate());
+    let duplicate = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        counter.act_on_fallible::<Ping>(|_, _| Box::pin(async { Ok(()) }));
+    }));
+
+    let panic = duplicate.expect_err("a second handler for Ping should panic in strict mode");
+    let message = panic.downcast_ref::<String>().cloned().unwrap_or_default();
+    assert!(message.contains("already has a handler") && message.contains("Ping"), "got {message:?}");
+    Ok(())
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    #[default]
+    Closed,
+    Opening,
+    Open,
+}
+
+#[derive(Default, Debug)]
+struct Door {
+    phase: Phase,
+    transitions: usize,
+}
+
+#[derive(Default, Debug, Clone)]
+struct Advance;
+
+#[derive(Debug, Clone)]
+struct Transitioned;
+
+#[acton_test]
+async fn test_send_self() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    // A one-message mailbox: each step's second message to self finds it full
+    let config = AgentConfig::new(Ern::with_root("door").unwrap(), None, None)?.with_mailbox_capacity(1);
+    let (done, mut opened) = tokio::sync::mpsc::unbounded_channel();
+    let mut door = runtime.create_actor_with_config::<Door>(config).await;
+    door.act_on::<Advance>(move |agent, _| {
+        agent.model.phase = match agent.model.phase {
+            Phase::Closed => Phase::Opening,
+            Phase::Opening | Phase::Open => Phase::Open,
+        };
+        if agent.model.phase != Phase::Open {
+            agent.send_self(Advance);
+        }
+        agent.send_self(Transitioned);
+        AgentReply::immediate()
+    })
+    .act_on::<Transitioned>(move |agent, _| {
+        agent.model.transitions += 1;
+        if agent.model.phase == Phase::Open && agent.model.transitions == 2 {
+            let _ = done.send(());
+        }
+        AgentReply::immediate()
+    })
+    .after_stop(|agent| {
+        assert_eq!(agent.model.phase, Phase::Open, "the door should advance to its final phase");
+        assert_eq!(agent.model.transitions, 2, "every message to self should be handled");
+        AgentReply::immediate()
+    });
+    let door = door.start().await;
+
+    door.send(Advance).await;
+    tokio::time::timeout(Duration::from_secs(1), opened.recv()).await?;
+    door.stop().await?;
+    Ok(())
+}
+
+#[derive(Default, Debug)]
+struct WarmingUp {
+    ready: bool,
+    handled: Vec<usize>,
+}
+
+#[acton_test]
+async fn test_stash_until_ready() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let mut agent = runtime.new_agent::<WarmingUp>().await;
+    agent
+        .act_on::<StatusReport>(|agent, context| {
+            if agent.model.ready {
+                let StatusReport::Complete(n) = context.message();
+                agent.model.handled.push(*n);
+            } else {
+                agent.stash(context.envelope());
+            }
+            AgentReply::immediate()
+        })
+        .act_on::<Tally>(|agent, _| {
+            agent.model.ready = true;
+            agent.unstash_all();
+            AgentReply::immediate()
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.handled, vec![1, 2, 3, 4], "stashed messages keep their order");
+            AgentReply::immediate()
+        });
+    let agent = agent.start().await;
+
+    for n in 1..=3 {
+        agent.send(StatusReport::Complete(n)).await;
+    }
+    agent.send(Tally::AddCount).await;
+    agent.send(StatusReport::Complete(4)).await;
+    agent.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_become_behavior() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (replies, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let greeted = replies.clone();
+    counter.act_on::<Ping>(move |agent, _| {
+        let _ = greeted.send("awaiting auth");
+        let mut authenticated = Behavior::new();
+        let greeted = greeted.clone();
+        authenticated.act_on::<Ping>(move |agent, _| {
+            let _ = greeted.send("authenticated");
+            agent.unbecome();
+            AgentReply::immediate()
+        });
+        agent.become_behavior(authenticated);
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    for _ in 0..3 {
+        counter.send(Ping).await;
+    }
+    let mut seen = Vec::new();
+    for _ in 0..3 {
+        seen.push(tokio::time::timeout(Duration::from_secs(1), received.recv()).await?.unwrap());
+    }
+    assert_eq!(seen, vec!["awaiting auth", "authenticated", "awaiting auth"]);
+
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_act_once() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (letters, mut dead) = tokio::sync::mpsc::unbounded_channel();
+    let mut observer = runtime.new_agent::<Counter>().await;
+    observer.act_on::<DeadLetter>(move |_, context| {
+        let _ = letters.send((*context.message().envelope.message).as_any().is::<Ping>());
+        AgentReply::immediate()
+    });
+    observer.handle().subscribe::<DeadLetter>().await?;
+    let _observer = observer.start().await;
+
+    // A oneshot sender is used up by sending, so only an FnOnce handler can own it
+    let (ready, handshake) = tokio::sync::oneshot::channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter.act_once::<Ping>(move |_, _| {
+        let _ = ready.send(());
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    for _ in 0..3 {
+        counter.send(Ping).await;
+    }
+    tokio::time::timeout(Duration::from_secs(1), handshake).await??;
+    for _ in 0..2 {
+        let dead_ping = tokio::time::timeout(Duration::from_secs(1), dead.recv()).await?;
+        assert_eq!(dead_ping, Some(true), "later pings should have no handler");
+    }
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_find_by_ern() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (handled, mut pings) = tokio::sync::mpsc::unbounded_channel();
+    let parent = runtime.new_agent_with_name::<PoolItem>("registry_parent".to_string()).await;
+    let mut child = parent.create_child("worker".to_string()).await?;
+    child.act_on::<Ping>(move |_, _| {
+        let _ = handled.send(());
+        AgentReply::immediate()
+    });
+    let child_id = child.id().clone();
+    let parent = parent.start().await;
+    parent.supervise(child).await?;
+
+    let found = runtime.find(&child_id).expect("the child should be registered once started");
+    found.send(Ping).await;
+    tokio::time::timeout(Duration::from_secs(1), pings.recv()).await?;
+
+    let family = runtime.find_prefix(&parent.id().to_string());
+    assert_eq!(family.len(), 2, "the prefix should match the parent and its child");
+
+    parent.stop().await?;
+    assert!(runtime.find(&child_id).is_none(), "a stopped agent should be removed");
+    assert!(runtime.find(&parent.id()).is_none());
+    Ok(())
+}
+
+#[acton_test]
+async fn test_send_matching() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (handled, mut pings) = tokio::sync::mpsc::unbounded_channel();
+    let mut tenants = Vec::new();
+    let mut acme_workers = Vec::new();
+    for tenant_name in ["acme", "globex"] {
+        let mut tenant = runtime.new_agent_with_name::<Counter>(tenant_name.to_string()).await;
+        let tenant_handled = handled.clone();
+        tenant.act_on::<Ping>(move |agent, _| {
+            let _ = tenant_handled.send(agent.id().clone());
+            AgentReply::immediate()
+        });
+        let mut workers = Vec::new();
+        for _ in 0..2 {
+            let mut worker = tenant.create_child("worker".to_string()).await?;
+            let worker_handled = handled.clone();
+            worker.act_on::<Ping>(move |agent, _| {
+                let _ = worker_handled.send(agent.id().clone());
+                AgentReply::immediate()
+            });
+            if tenant_name == "acme" {
+                acme_workers.push(worker.id().clone());
+            }
+            workers.push(worker);
+        }
+        let tenant = tenant.start().await;
+        for worker in workers {
+            tenant.supervise(worker).await?;
+        }
+        tenants.push(tenant);
+    }
+
+    let failures = runtime.send_matching("acme/*", Ping);
+    assert!(failures.is_empty(), "{failures:?}");
+    for tenant in &tenants {
+        tenant.stop().await?;
+    }
+
+    let mut reached = Vec::new();
+    while let Ok(id) = pings.try_recv() {
+        reached.push(id);
+    }
+    reached.sort_by_key(|id| id.to_string());
+    acme_workers.sort_by_key(|id| id.to_string());
+    assert_eq!(reached, acme_workers, "only acme's workers should get the ping");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_fallible_handler_reports_error() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (failed, mut failures) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .act_on_fallible::<StatusReport>(|agent, context| {
+            let StatusReport::Complete(n) = *context.message();
+            agent.model.count += 1;
+            AgentReply::from_fallible(async move {
+                anyhow::ensure!(n < 10, "report {n} is out of range");
+                Ok(())
+            })
+        })
+        .on_error(move |_, error, envelope| {
+            let report = envelope.message_as::<StatusReport>().cloned();
+            let _ = failed.send((error.to_string(), report));
+            AgentReply::immediate()
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.count, 3, "the agent should keep going after a failure");
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    counter.send(StatusReport::Complete(1)).await;
+    counter.send(StatusReport::Complete(42)).await;
+    counter.send(StatusReport::Complete(2)).await;
+
+    let (error, report) = tokio::time::timeout(Duration::from_secs(1), failures.recv()).await?.unwrap();
+    assert_eq!(error, "report 42 is out of range");
+    assert!(matches!(report, Some(StatusReport::Complete(42))));
+
+    counter.stop().await?;
+    assert!(failures.try_recv().is_err(), "only one message should fail");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_retry_until_success() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (handled, mut done) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .act_on_with_retry::<Ping>(RetryPolicy::fixed(5, Duration::from_millis(10)), move |agent, _| {
+            agent.model.count += 1;
+            let attempt = agent.model.count;
+            let handled = handled.clone();
+            AgentReply::from_fallible(async move {
+                anyhow::ensure!(attempt > 2, "transient failure on attempt {attempt}");
+                let _ = handled.send(attempt);
+                Ok(())
+            })
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.count, 3, "the handler should run exactly three times");
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    counter.send(Ping).await;
+    let attempt = tokio::time::timeout(Duration::from_secs(1), done.recv()).await?;
+    assert_eq!(attempt, Some(3));
+
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_handler_timeout() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (events, mut seen) = tokio::sync::mpsc::unbounded_channel();
+    let timeouts = events.clone();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .act_on_with_timeout::<Ping>(Duration::from_millis(50), OnTimeout::Cancel, |_, _| {
+            AgentReply::from_async(async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            })
+        })
+        .act_on::<Pong>(move |_, _| {
+            let _ = events.send("pong");
+            AgentReply::immediate()
+        })
+        .on_error(move |_, error, _| {
+            assert!(matches!(error.downcast_ref::<MessageError>(), Some(MessageError::HandlerTimeout)));
+            let _ = timeouts.send("timeout");
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    counter.send(Ping).await;
+    counter.send(Pong).await;
+    for expected in ["timeout", "pong"] {
+        let event = tokio::time::timeout(Duration::from_secs(1), seen.recv()).await?;
+        assert_eq!(event, Some(expected));
+    }
+
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_list_children() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let parent = runtime.new_agent::<PoolItem>().await;
+    let first = parent.create_child("first".to_string()).await?;
+    let second = parent.create_child("second".to_string()).await?;
+    let mut expected = vec![first.id().clone(), second.id().clone()];
+    let parent = parent.start().await;
+    parent.supervise(first).await?;
+    parent.supervise(second).await?;
+
+    let children = parent.children();
+    let mut ids: Vec<Ern> = children.iter().map(|(id, _)| id.clone()).collect();
+    ids.sort_by_key(|id| id.to_string());
+    expected.sort_by_key(|id| id.to_string());
+    assert_eq!(ids, expected);
+    assert!(children.iter().all(|(id, child)| &child.id() == id), "each handle should match its ERN");
+
+    parent.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broadcast_to_children() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (handled, mut pings) = tokio::sync::mpsc::unbounded_channel();
+    // A pong keeps a child busy until the gate opens
+    let gate = std::sync::Arc::new(tokio::sync::Semaphore::new(0));
+    let parent = runtime.new_agent::<PoolItem>().await;
+    let parent = parent.start().await;
+    let mut children = Vec::new();
+    for i in 0..3 {
+        let mut child = runtime.new_agent::<PoolItem>().await;
+        let handled = handled.clone();
+        let gate = gate.clone();
+        child
+            .act_on::<Ping>(move |agent, _| {
+                let _ = handled.send(agent.id().clone());
+                AgentReply::immediate()
+            })
+            .act_on::<Pong>(move |_, _| {
+                let gate = gate.clone();
+                AgentReply::from_async(async move {
+                    let _ = gate.acquire().await.map(|permit| permit.forget());
+                })
+            });
+        trace!("Supervising child {i}");
+        children.push(parent.supervise(child).await?);
+    }
+
+    let errors = parent.broadcast_to_children(Ping).await;
+    assert!(errors.is_empty(), "no sends should fail: {errors:?}");
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        received.push(tokio::time::timeout(Duration::from_secs(1), pings.recv()).await?.unwrap());
+    }
+    received.sort_by_key(|id| id.to_string());
+    received.dedup();
+    assert_eq!(received.len(), 3, "each child should receive the broadcast once");
+
+    // A child that can't take the message is reported, and the others still get it
+    let draining = children[0].clone();
+    draining.send(Pong).await;
+    let drained = tokio::spawn({
+        let draining = draining.clone();
+        async move { draining.drain().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let errors = parent.broadcast_to_children(Ping).await;
+    assert_eq!(errors.len(), 1, "only the draining child should fail: {errors:?}");
+    assert_eq!(errors[0].0, draining.id());
+    assert!(matches!(errors[0].1, MessageError::Draining));
+    for _ in 0..2 {
+        tokio::time::timeout(Duration::from_secs(1), pings.recv()).await?.unwrap();
+    }
+    gate.add_permits(1);
+    drained.await??;
+
+    parent.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_terminate_child() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (handled, mut pings) = tokio::sync::mpsc::unbounded_channel();
+    let parent = runtime.new_agent::<PoolItem>().await.start().await;
+    let mut children = Vec::new();
+    for _ in 0..2 {
+        let mut child = runtime.new_agent::<PoolItem>().await;
+        let handled = handled.clone();
+        child.act_on::<Ping>(move |agent, _| {
+            let _ = handled.send(agent.id().clone());
+            AgentReply::immediate()
+        });
+        children.push(parent.supervise(child).await?);
+    }
+    let (doomed, survivor) = (&children[0], &children[1]);
+
+    parent.terminate_child(&doomed.id()).await?;
+    assert!(parent.find_child(&doomed.id()).is_none(), "the terminated child should be removed");
+    assert!(parent.terminate_child(&doomed.id()).await.is_err(), "it's no longer a child");
+
+    survivor.send(Ping).await;
+    let from = tokio::time::timeout(Duration::from_secs(1), pings.recv()).await?.unwrap();
+    assert_eq!(from, survivor.id(), "the sibling should keep handling messages");
+
+    parent.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_max_children() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("limited")?.with_max_children(2);
+    let parent = runtime.create_actor_with_config::<PoolItem>(config).await.start().await;
+    let mut children = Vec::new();
+    for _ in 0..2 {
+        let child = runtime.new_agent::<PoolItem>().await;
+        children.push(parent.supervise(child).await?);
+    }
+
+    let error = parent
+        .supervise(runtime.new_agent::<PoolItem>().await)
+        .await
+        .expect_err("a third child is over the limit");
+    assert!(matches!(error.downcast_ref::<MessageError>(), Some(MessageError::ChildLimitReached(2))));
+    assert_eq!(parent.children().len(), 2);
+
+    // A child stopping on its own frees its place
+    children[0].stop().await?;
+    parent.supervise(runtime.new_agent::<PoolItem>().await).await?;
+    assert_eq!(parent.children().len(), 2);
+
+    parent.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_send_to_parent() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (handled, mut pongs) = tokio::sync::mpsc::unbounded_channel();
+    let mut parent = runtime.new_agent::<PoolItem>().await;
+    parent.act_on::<Pong>(move |_, context| {
+        let _ = handled.send(context.origin_envelope().reply_to().sender().clone());
+        AgentReply::immediate()
+    });
+    let parent = parent.start().await;
+
+    let mut child = runtime.new_agent::<PoolItem>().await;
+    child.act_on::<Ping>(|agent, _| {
+        let sent = agent.send_to_parent(Pong);
+        Box::pin(async move {
+            sent.await.expect("the child has a parent");
+        })
+    });
+    let child = parent.supervise(child).await?;
+    assert_eq!(child.parent().map(|parent| parent.id()), Some(parent.id()));
+    assert!(parent.parent().is_none(), "a root agent has no parent");
+
+    child.send(Ping).await;
+    let from = tokio::time::timeout(Duration::from_secs(1), pongs.recv()).await?.unwrap();
+    assert_eq!(from, child.id(), "the parent should see the child as the sender");
+
+    let mut orphan = runtime.new_agent::<PoolItem>().await;
+    let (failed, mut failures) = tokio::sync::mpsc::unbounded_channel();
+    orphan.act_on::<Ping>(move |agent, _| {
+        let sent = agent.send_to_parent(Pong);
+        let failed = failed.clone();
+        Box::pin(async move {
+            let _ = failed.send(matches!(sent.await, Err(MessageError::NoParent)));
+        })
+    });
+    let orphan = orphan.start().await;
+    orphan.send(Ping).await;
+    let no_parent = tokio::time::timeout(Duration::from_secs(1), failures.recv()).await?.unwrap();
+    assert!(no_parent, "a root agent should get NoParent");
+
+    parent.stop().await?;
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_agent_metrics() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("measured")?, None, None)?.with_metrics();
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    counter.act_on::<Ping>(|agent, _| {
+        agent.model.count += 1;
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+    let unmeasured = runtime.new_agent::<Counter>().await.start().await;
+    assert!(unmeasured.metrics().is_none(), "metrics are off unless configured");
+
+    const PINGS: u64 = 25;
+    for _ in 0..PINGS {
+        counter.send(Ping).await;
+    }
+    counter.stop().await?;
+
+    let metrics = counter.metrics().expect("metrics were enabled");
+    assert_eq!(metrics.messages_handled, PINGS);
+    assert_eq!(metrics.handler_latency.count, PINGS);
+    assert_eq!(metrics.mailbox_depth, 0);
+    let (_, slowest) = metrics.handler_latency.buckets.last().copied().unwrap();
+    assert_eq!(slowest, PINGS, "every ping should be well under the largest bucket");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_queue_latency() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("delayed")?.with_metrics();
+    let (waited, mut latencies) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    counter.act_on::<Ping>(move |_, context| {
+        let _ = waited.send(context.queue_latency());
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    // Held in the mailbox while the agent is suspended
+    const DELAY: Duration = Duration::from_millis(100);
+    counter.suspend().await;
+    counter.send(Ping).await;
+    tokio::time::sleep(DELAY).await;
+    counter.resume().await;
+    counter.stop().await?;
+
+    let latency = latencies.recv().await.unwrap();
+    assert!(latency >= DELAY, "the ping waited {DELAY:?} but recorded {latency:?}");
+    let metrics = counter.metrics().expect("metrics were enabled");
+    assert_eq!(metrics.queue_latency.count, 1);
+    assert!(metrics.queue_latency.sum >= DELAY);
+    assert!(metrics.handler_latency.sum < DELAY, "waiting shouldn't count as handling");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[cfg(feature = "prometheus")]
+#[acton_test]
+async fn test_prometheus_metrics_text() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("scraped")?, None, None)?.with_metrics();
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    counter.act_on::<Ping>(|_, _| AgentReply::immediate());
+    let counter = counter.start().await;
+    for _ in 0..3 {
+        counter.send(Ping).await;
+    }
+    // Wait for the pings to be handled, without stopping the agent
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while counter.metrics().unwrap().messages_handled < 3 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await?;
+
+    let text = runtime.metrics_text();
+    assert!(text.contains("# TYPE acton_messages_total counter"));
+    assert!(text.contains("# TYPE acton_handler_seconds histogram"));
+    let labels = format!("agent=\"{}\",message_type=\"{}\"", counter.id(), std::any::type_name::<Ping>());
+    assert!(text.contains(&format!("acton_messages_total{{{labels}}} 3")), "{text}");
+    assert!(text.contains(&format!("acton_handler_seconds_bucket{{{labels},le=\"+Inf\"}} 3")), "{text}");
+    assert!(text.contains(&format!("acton_handler_seconds_count{{{labels}}} 3")), "{text}");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_correlation_ids_follow_replies() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (seen, mut ids) = tokio::sync::mpsc::unbounded_channel();
+    let mut responder = runtime.new_agent::<Counter>().await;
+    let ping_seen = seen.clone();
+    responder.act_on::<Ping>(move |_, context| {
+        let _ = ping_seen.send((context.message_id(), context.correlation_id(), context.causation_id()));
+        let reply = context.reply_envelope();
+        AgentReply::from_async(async move { reply.send(Pong).await })
+    });
+    let mut requester = runtime.new_agent::<Counter>().await;
+    requester.act_on::<Pong>(move |_, context| {
+        let _ = seen.send((context.message_id(), context.correlation_id(), context.causation_id()));
+        AgentReply::immediate()
+    });
+    let responder = responder.start().await;
+    let requester = requester.start().await;
+
+    requester.create_envelope(Some(responder.reply_address())).send(Ping).await;
+    let (ping_id, ping_correlation, ping_cause) = tokio::time::timeout(Duration::from_secs(1), ids.recv()).await?.unwrap();
+    let (pong_id, pong_correlation, pong_cause) = tokio::time::timeout(Duration::from_secs(1), ids.recv()).await?.unwrap();
+
+    assert_eq!(ping_correlation, ping_id, "a message sent outside a handler starts a cascade");
+    assert_eq!(ping_cause, None);
+    assert_eq!(pong_correlation, ping_correlation, "the reply joins the ping's cascade");
+    assert_eq!(pong_cause, Some(ping_id), "the reply was caused by the ping");
+    assert_ne!(pong_id, ping_id);
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_parallel_handlers() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("stateless")?.with_parallelism(4);
+    let (handled, mut done) = tokio::sync::mpsc::unbounded_channel();
+    let mut stateless = runtime.create_actor_with_config::<Counter>(config).await;
+    stateless.act_on::<Ping>(move |_, _| {
+        let handled = handled.clone();
+        AgentReply::from_async(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let _ = handled.send(());
+        })
+    });
+    let stateless = stateless.start().await;
+
+    let started = tokio::time::Instant::now();
+    for _ in 0..4 {
+        stateless.send(Ping).await;
+    }
+    // Stopping waits for the handlers still running
+    stateless.stop().await?;
+    let elapsed = started.elapsed();
+
+    for _ in 0..4 {
+        assert_eq!(done.try_recv(), Ok(()), "every handler should have finished before the agent stopped");
+    }
+    assert!(elapsed < Duration::from_millis(400), "the handlers ran one after another, taking {elapsed:?}");
+    Ok(())
+}
+
+/// Counts the tasks it's given before spawning them on tokio.
+#[derive(Debug, Default, Clone)]
+struct CountingSpawner(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+impl CountingSpawner {
+    fn spawned(&self) -> usize {
+        self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+impl Spawner for CountingSpawner {
+    fn spawn(&self, task: SpawnedTask) {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        tokio::spawn(task);
+    }
+}
+
+#[acton_test]
+async fn test_custom_spawner() -> anyhow::Result<()> {
+    initialize_tracing();
+    let spawner = CountingSpawner::default();
+    let mut runtime: AgentRuntime = ActonApp::launch_with_spawner(spawner.clone());
+    // The message loops of the broker and the dead letter office
+    assert_eq!(spawner.spawned(), 2);
+
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter.act_on::<Ping>(|agent, _| {
+        agent.model.count += 1;
+        AgentReply::immediate()
+    });
+    counter.after_stop(|agent| {
+        assert_eq!(agent.model.count, 2, "both pings should have been handled");
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+    assert_eq!(spawner.spawned(), 3, "starting the agent should spawn its message loop");
+
+    counter.send(Ping).await;
+    assert_eq!(spawner.spawned(), 3, "handling a message shouldn't spawn anything");
+    counter.schedule(Duration::from_millis(10), Ping);
+    assert_eq!(spawner.spawned(), 4, "scheduling a message should spawn its timer");
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_message_weights() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("telemetry")?, None, None)?.with_message_weight::<Ping>(10);
+    let mut agent = runtime.create_actor_with_config::<Counter>(config).await;
+    let handled = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let telemetry = handled.clone();
+    let control = handled.clone();
+    agent
+        .act_on::<StatusReport>(move |_, _| {
+            let telemetry = telemetry.clone();
+            AgentReply::from_async(async move {
+                tokio::time::sleep(Duration::from_millis(2)).await;
+                telemetry.lock().unwrap().push("telemetry");
+            })
+        })
+        .act_on::<Ping>(move |_, _| {
+            control.lock().unwrap().push("control");
+            AgentReply::immediate()
+        });
+    let agent = agent.start().await;
+
+    for n in 0..40 {
+        agent.send(StatusReport::Complete(n)).await;
+    }
+    for _ in 0..5 {
+        agent.send(Ping).await;
+    }
+    agent.stop().await?;
+
+    let handled = handled.lock().unwrap();
+    assert_eq!(handled.len(), 45, "every message should be handled");
+    let last_control = handled.iter().rposition(|kind| *kind == "control").unwrap();
+    assert!(
+        last_control < 15,
+        "the control messages waited behind the telemetry, the last handled at {last_control}: {handled:?}"
+    );
+    Ok(())
+}
@@ -65,9 +65,9 @@ async fn test_broker() -> anyhow::Result<()> {
             AgentReply::immediate()
         });
 
-    counter_actor.handle().subscribe::<Pong>().await;
-    comedy_show.handle().subscribe::<Ping>().await;
-    comedy_show.handle().subscribe::<Pong>().await;
+    counter_actor.handle().subscribe::<Pong>().await?;
+    comedy_show.handle().subscribe::<Ping>().await?;
+    comedy_show.handle().subscribe::<Pong>().await?;
 
     let agent = comedy_show.start().await;
     let _ = counter_actor.start().await;
@@ -97,9 +97,11 @@ async fn test_broker_from_handler() -> anyhow::Result<()> {
         Some(broker.clone()),
     )?;
     let mut counter_actor = app.create_actor_with_config::<Counter>(actor_config).await;
-    counter_actor.act_on::<Pong>(|agent, context| {
+    let (ponged, mut pong_received) = tokio::sync::mpsc::unbounded_channel();
+    counter_actor.act_on::<Pong>(move |agent, context| {
         info!("Also SUCCESS! PONG!");
         agent.model.count += 1;
+        let _ = ponged.send(());
 
         AgentReply::immediate()
     }).after_stop(|agent| {
@@ -115,15 +117,471 @@ async fn test_broker_from_handler() -> anyhow::Result<()> {
             })
         });
 
-    counter_actor.handle().subscribe::<Pong>().await;
-    comedy_show.handle().subscribe::<Ping>().await;
+    counter_actor.handle().subscribe::<Pong>().await?;
+    comedy_show.handle().subscribe::<Ping>().await?;
 
     let _ = comedy_show.start().await;
     let _ = counter_actor.start().await;
 
     broker.broadcast(Ping).await;
+    // The broker queues its deliveries rather than making them, so the pong can still be on
+    // its way through the broker when shutdown would stop the counter
+    tokio::time::timeout(std::time::Duration::from_secs(1), pong_received.recv()).await?;
+
+    app.shutdown_all().await?;
+
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_reply_to_publisher() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+    let broker = app.broker();
+
+    let (replied, mut pongs) = tokio::sync::mpsc::unbounded_channel();
+    let mut publisher = app.new_agent::<Counter>().await;
+    publisher.act_on::<Pong>(move |agent, _| {
+        let _ = replied.send(agent.id().clone());
+        AgentReply::immediate()
+    });
+    let mut comedian = app.new_agent::<Comedian>().await;
+    comedian.act_on::<Ping>(|_, context| Box::pin(context.reply_to_sender(Pong)));
+
+    comedian.handle().subscribe::<Ping>().await?;
+    let _comedian = comedian.start().await;
+    let publisher = publisher.start().await;
+
+    publisher.broadcast(Ping).await;
+    let from = tokio::time::timeout(std::time::Duration::from_secs(1), pongs.recv()).await?;
+    assert_eq!(from, Some(publisher.id()), "the reply should reach the publisher");
+
+    // Broadcast from the broker itself, there's no one to reply to
+    broker.broadcast(Ping).await;
+    let stray = tokio::time::timeout(std::time::Duration::from_millis(100), pongs.recv()).await;
+    assert!(stray.is_err(), "nothing should reply to the broker's own broadcast");
+
+    app.shutdown_all().await?;
+
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_unsubscribe() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+    let broker = app.broker();
+
+    let actor_config = AgentConfig::new(
+        Ern::with_root("counter").unwrap(),
+        None,
+        Some(broker.clone()),
+    )?;
+    let mut counter_actor = app.create_actor_with_config::<Counter>(actor_config).await;
+    counter_actor.act_on::<Ping>(|agent, context| {
+        agent.model.count += 1;
+        AgentReply::immediate()
+    }).after_stop(|agent| {
+        assert_eq!(agent.model.count, 1, "count should be 1 after unsubscribing");
+        AgentReply::immediate()
+    });
+
+    counter_actor.handle().subscribe::<Ping>().await?;
+    let counter = counter_actor.start().await;
+
+    broker.broadcast(Ping).await;
+    counter.unsubscribe::<Ping>().await?;
+    broker.broadcast(Ping).await;
+
+    app.shutdown_all().await?;
+
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_subscribe_filtered() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+    let broker = app.broker();
+
+    let (received, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter_actor = app.new_agent::<Counter>().await;
+    counter_actor.act_on::<StatusReport>(move |agent, context| {
+        let StatusReport::Complete(n) = context.message();
+        assert_eq!(n % 2, 0, "only even reports should arrive");
+        agent.model.count += 1;
+        let _ = received.send(*n);
+        AgentReply::immediate()
+    }).after_stop(|agent| {
+        assert_eq!(agent.model.count, 2, "two of the four reports match the filter");
+        AgentReply::immediate()
+    });
+
+    counter_actor
+        .handle()
+        .subscribe_filtered::<StatusReport>(|report| matches!(report, StatusReport::Complete(n) if n % 2 == 0))
+        .await;
+    let counter = counter_actor.start().await;
+
+    for n in 1..=4 {
+        broker.broadcast(StatusReport::Complete(n)).await;
+    }
+    for expected in [2, 4] {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(1), reports.recv()).await?;
+        assert_eq!(n, Some(expected));
+    }
+
+    app.shutdown_all().await?;
+
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_subscribe_many() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+    let broker = app.broker();
+
+    let (received, mut deliveries) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = app.new_agent::<Counter>().await;
+    let (ping, pong, tally) = (received.clone(), received.clone(), received);
+    counter
+        .act_on::<Ping>(move |_, _| {
+            let _ = ping.send("ping");
+            AgentReply::immediate()
+        })
+        .act_on::<Pong>(move |_, _| {
+            let _ = pong.send("pong");
+            AgentReply::immediate()
+        })
+        .act_on::<Tally>(move |_, _| {
+            let _ = tally.send("tally");
+            AgentReply::immediate()
+        });
+    counter.handle().subscriptions().add::<Ping>().add::<Pong>().add::<Tally>().subscribe().await?;
+    let counter = counter.start().await;
+
+    broker.broadcast(Ping).await;
+    broker.broadcast(Pong).await;
+    broker.broadcast(Tally::AddCount).await;
+    for expected in ["ping", "pong", "tally"] {
+        let delivery = tokio::time::timeout(std::time::Duration::from_secs(1), deliveries.recv()).await?;
+        assert_eq!(delivery, Some(expected));
+    }
+
+    counter.subscriptions().add::<Ping>().add::<Pong>().unsubscribe().await?;
+    broker.broadcast(Ping).await;
+    broker.broadcast(Pong).await;
+    broker.broadcast(Tally::AddCount).await;
+    let delivery = tokio::time::timeout(std::time::Duration::from_secs(1), deliveries.recv()).await?;
+    assert_eq!(delivery, Some("tally"), "only Tally should still be subscribed");
 
     app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_slow_subscriber() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+    app.set_broadcast_concurrency(2);
+    let broker = app.broker();
+
+    // The slow subscriber's handler waits on the gate, and its one-slot mailbox fills quickly
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    let handler_gate = gate.clone();
+    let (slow_received, mut slow_pings) = tokio::sync::mpsc::unbounded_channel();
+    let config = AgentConfig::new(Ern::with_root("slow").unwrap(), None, Some(broker.clone()))?
+        .with_mailbox_capacity(1);
+    let mut slow = app.create_actor_with_config::<Counter>(config).await;
+    slow.act_on::<Ping>(move |_, _| {
+        let gate = handler_gate.clone();
+        let received = slow_received.clone();
+        AgentReply::from_async(async move {
+            let _ = gate.acquire().await.map(|permit| permit.forget());
+            let _ = received.send(());
+        })
+    });
+    slow.handle().subscribe::<Ping>().await?;
+    let slow = slow.start().await;
+
+    let (fast_received, mut fast_pings) = tokio::sync::mpsc::unbounded_channel();
+    let mut fast = app.new_agent::<Counter>().await;
+    fast.act_on::<Ping>(move |_, _| {
+        let _ = fast_received.send(());
+        AgentReply::immediate()
+    });
+    fast.handle().subscribe::<Ping>().await?;
+    fast.start().await;
+
+    // One ping keeps the slow handler busy and the next fills its mailbox
+    slow.send(Ping).await;
+    slow.send(Ping).await;
 
+    broker.broadcast(Ping).await;
+    tokio::time::timeout(std::time::Duration::from_secs(1), fast_pings.recv()).await?;
+    assert!(slow_pings.try_recv().is_err(), "the slow subscriber should still be blocked");
+
+    // The slow subscriber's first copy is still waiting for room, which holds up no one else
+    broker.broadcast(Ping).await;
+    tokio::time::timeout(std::time::Duration::from_secs(1), fast_pings.recv()).await?;
+    assert!(slow_pings.try_recv().is_err(), "the slow subscriber should still be blocked");
+
+    gate.add_permits(4);
+    for _ in 0..4 {
+        tokio::time::timeout(std::time::Duration::from_secs(1), slow_pings.recv()).await?;
+    }
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_drops_for_full_subscriber() -> anyhow::Result<()> {
+    initialize_tracing();
+    let policy = BroadcastPolicy::RetryThenDrop(RetryPolicy::fixed(3, std::time::Duration::from_millis(10)));
+    let mut app: AgentRuntime = ActonApp::launch_with_broadcast_policy(policy);
+    let broker = app.broker();
+
+    // The slow subscriber's handler waits on the gate, and its one-slot mailbox fills quickly
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    let handler_gate = gate.clone();
+    let (slow_received, mut slow_pings) = tokio::sync::mpsc::unbounded_channel();
+    let config = AgentConfig::new(Ern::with_root("slow").unwrap(), None, Some(broker.clone()))?
+        .with_mailbox_capacity(1);
+    let mut slow = app.create_actor_with_config::<Counter>(config).await;
+    slow.act_on::<Ping>(move |_, _| {
+        let gate = handler_gate.clone();
+        let received = slow_received.clone();
+        AgentReply::from_async(async move {
+            let _ = gate.acquire().await.map(|permit| permit.forget());
+            let _ = received.send(());
+        })
+    });
+    slow.handle().subscribe::<Ping>().await?;
+    let slow = slow.start().await;
+
+    let (fast_received, mut fast_pings) = tokio::sync::mpsc::unbounded_channel();
+    let mut fast = app.new_agent::<Counter>().await;
+    fast.act_on::<Ping>(move |_, _| {
+        let _ = fast_received.send(());
+        AgentReply::immediate()
+    });
+    fast.handle().subscribe::<Ping>().await?;
+    fast.start().await;
+
+    let (dropped, mut dead_letters) = tokio::sync::mpsc::unbounded_channel();
+    let mut observer = app.new_agent::<Counter>().await;
+    observer.act_on::<DeadLetter>(move |_, context| {
+        let _ = dropped.send(context.message().clone());
+        AgentReply::immediate()
+    });
+    observer.handle().subscribe::<DeadLetter>().await?;
+    observer.start().await;
+
+    // One ping keeps the slow handler busy and the next fills its mailbox
+    slow.send(Ping).await;
+    slow.send(Ping).await;
+
+    broker.broadcast(Ping).await;
+    broker.broadcast(Ping).await;
+    for _ in 0..2 {
+        tokio::time::timeout(std::time::Duration::from_secs(1), fast_pings.recv()).await?;
+    }
+    for _ in 0..2 {
+        let dead_letter = tokio::time::timeout(std::time::Duration::from_secs(1), dead_letters.recv())
+            .await?
+            .expect("the observer stopped early");
+        assert_eq!(dead_letter.reason, DeadLetterReason::SubscriberFull);
+        assert_eq!(dead_letter.envelope.recipient.sender(), &slow.id());
+        assert!((*dead_letter.envelope.message).as_any().is::<Ping>());
+    }
+
+    // The slow subscriber gets only the two pings that fit
+    gate.add_permits(4);
+    for _ in 0..2 {
+        tokio::time::timeout(std::time::Duration::from_secs(1), slow_pings.recv()).await?;
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(slow_pings.try_recv().is_err(), "the dropped broadcasts shouldn't arrive");
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_preserves_order() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+    let broker = app.broker();
+
+    // A small mailbox and a gated, then slow, handler back this subscriber's queue up
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    let handler_gate = gate.clone();
+    let (received, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    let config = AgentConfig::new(Ern::with_root("ordered").unwrap(), None, Some(broker.clone()))?
+        .with_mailbox_capacity(2);
+    let mut ordered = app.create_actor_with_config::<Counter>(config).await;
+    ordered.act_on::<StatusReport>(move |_, context| {
+        let StatusReport::Complete(n) = *context.message();
+        let gate = handler_gate.clone();
+        let received = received.clone();
+        AgentReply::from_async(async move {
+            let _ = gate.acquire().await.map(|permit| permit.forget());
+            tokio::time::sleep(std::time::Duration::from_micros(n as u64 % 3 * 200)).await;
+            let _ = received.send(n);
+        })
+    });
+    ordered.handle().subscribe::<StatusReport>().await?;
+    let _ordered = ordered.start().await;
+
+    let (also_received, mut also_reports) = tokio::sync::mpsc::unbounded_channel();
+    let mut also_subscribed = app.new_agent::<Counter>().await;
+    also_subscribed.act_on::<StatusReport>(move |_, context| {
+        let StatusReport::Complete(n) = *context.message();
+        let _ = also_received.send(n);
+        AgentReply::immediate()
+    });
+    also_subscribed.handle().subscribe::<StatusReport>().await?;
+    let _also_subscribed = also_subscribed.start().await;
+
+    let publisher = app.new_agent::<Counter>().await.start().await;
+    for n in 0..100 {
+        publisher.broadcast(StatusReport::Complete(n)).await;
+    }
+
+    // The other subscriber's queue drains while this one's is stuck
+    let mut also_seen = Vec::new();
+    while also_seen.len() < 100 {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(1), also_reports.recv()).await?;
+        also_seen.extend(n);
+    }
+    assert_eq!(also_seen, (0..100).collect::<Vec<_>>(), "broadcasts should arrive in publish order");
+    assert!(reports.try_recv().is_err(), "the gated subscriber shouldn't have handled anything yet");
+
+    gate.add_permits(100);
+    let mut seen = Vec::new();
+    while seen.len() < 100 {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(1), reports.recv()).await?;
+        seen.extend(n);
+    }
+    assert_eq!(seen, (0..100).collect::<Vec<_>>(), "broadcasts should arrive in publish order");
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broker_topics() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+    let broker = app.broker();
+
+    // Two topics and the default one carry the same report type
+    let mut reports = Vec::new();
+    for topic in ["alerts", "audit", ""] {
+        let (received, observed) = tokio::sync::mpsc::unbounded_channel();
+        let mut agent = app.new_agent::<Counter>().await;
+        agent.act_on::<StatusReport>(move |_, context| {
+            let StatusReport::Complete(n) = context.message();
+            let _ = received.send(*n);
+            AgentReply::immediate()
+        });
+        if topic.is_empty() {
+            agent.handle().subscribe::<StatusReport>().await?;
+        } else {
+            agent.handle().subscribe_topic::<StatusReport>(topic).await?;
+        }
+        agent.start().await;
+        reports.push(observed);
+    }
+
+    broker.broadcast_topic("alerts", StatusReport::Complete(1)).await;
+    broker.broadcast_topic("audit", StatusReport::Complete(2)).await;
+    broker.broadcast(StatusReport::Complete(3)).await;
+
+    for (observed, expected) in reports.iter_mut().zip([1, 2, 3]) {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(1), observed.recv()).await?;
+        assert_eq!(n, Some(expected));
+    }
+
+    app.shutdown_all().await?;
+    for observed in reports.iter_mut() {
+        assert!(observed.try_recv().is_err(), "each subscriber should only see its own topic");
+    }
+
+    Ok(())
+}
+
+#[acton_test]
+async fn test_system_events() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+
+    let (observed, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let mut monitor = app.new_agent::<Counter>().await;
+    monitor.act_on::<SystemEvent>(move |_, context| {
+        let _ = observed.send(context.message().clone());
+        AgentReply::immediate()
+    });
+    monitor.handle().subscribe::<SystemEvent>().await?;
+    let monitor = monitor.start().await;
+
+    // Events for the runtime's own agents may arrive too, so look for the ones we expect
+    let mut next_event_for = async |id: Ern| -> anyhow::Result<SystemEvent> {
+        loop {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(1), events.recv()).await?.unwrap();
+            if *event.id() == id {
+                return Ok(event);
+            }
+        }
+    };
+
+    // The monitor hears about its own start without anything looping
+    let event = next_event_for(monitor.id()).await?;
+    assert!(matches!(event, SystemEvent::AgentStarted { .. }), "{event:?}");
+
+    let worker = app.new_agent::<Counter>().await.start().await;
+    let event = next_event_for(worker.id()).await?;
+    assert!(matches!(event, SystemEvent::AgentStarted { .. }), "{event:?}");
+
+    worker.stop().await?;
+    let event = next_event_for(worker.id()).await?;
+    assert!(matches!(event, SystemEvent::AgentStopped { .. }), "{event:?}");
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_subscribe_without_broker() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app: AgentRuntime = ActonApp::launch();
+
+    // A handle made outside any runtime has no broker to subscribe with
+    let detached = AgentHandle::default();
+    let subscribed = detached.subscribe::<Ping>().await;
+    assert!(
+        matches!(subscribed, Err(MessageError::NoBroker)),
+        "subscribing without a broker should fail, got {subscribed:?}"
+    );
+    let unsubscribed = detached.subscriptions().add::<Ping>().unsubscribe().await;
+    assert!(matches!(unsubscribed, Err(MessageError::NoBroker)), "got {unsubscribed:?}");
+
+    // A config without a broker gets the runtime's, so its subscriptions are delivered
+    let config = AgentConfig::new(Ern::with_root("no_broker_configured")?, None, None)?;
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+    let (pinged, mut received) = tokio::sync::mpsc::unbounded_channel();
+    counter.act_on::<Ping>(move |_, _| {
+        let _ = pinged.send(());
+        AgentReply::immediate()
+    });
+    counter.handle().subscribe::<Ping>().await?;
+    counter.start().await;
+
+    app.broker().broadcast(Ping).await;
+    tokio::time::timeout(std::time::Duration::from_secs(1), received.recv()).await?;
+
+    app.shutdown_all().await?;
     Ok(())
 }
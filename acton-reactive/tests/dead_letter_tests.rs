@@ -0,0 +1,64 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use acton_reactive::prelude::*;
+use acton_test::prelude::*;
+
+use crate::setup::*;
+
+mod setup;
+
+#[acton_test]
+async fn test_unhandled_message_becomes_dead_letter() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (letters, mut received) = mpsc::unbounded_channel();
+    let mut observer = runtime.new_agent::<Counter>().await;
+    observer.act_on::<DeadLetter>(move |_, context| {
+        let dead_letter = context.message();
+        let _ = letters.send((
+            dead_letter.envelope.reply_to.sender().clone(),
+            dead_letter.reason.clone(),
+            (*dead_letter.envelope.message).as_any().is::<Joke>(),
+        ));
+        AgentReply::immediate()
+    });
+    observer.handle().subscribe::<DeadLetter>().await?;
+    let _observer = observer.start().await;
+
+    let sender = runtime.new_agent::<Counter>().await.start().await;
+    let recipient = runtime.new_agent::<Counter>().await.start().await;
+
+    sender
+        .create_envelope(Some(recipient.reply_address()))
+        .send(Joke)
+        .await;
+
+    let (sender_id, reason, is_joke) = timeout(Duration::from_secs(1), received.recv())
+        .await?
+        .expect("dead letter channel closed");
+    assert_eq!(sender_id, sender.id());
+    assert_eq!(reason, DeadLetterReason::NoHandler);
+    assert!(is_joke, "the original message should be kept");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
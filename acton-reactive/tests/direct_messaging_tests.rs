@@ -14,6 +14,8 @@
  * limitations under that License.
  */
 
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use tracing::{debug, info, instrument, trace};
@@ -52,6 +54,266 @@ async fn test_reply() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[acton_test]
+async fn test_ask() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let mut counter = app.new_agent::<Counter>().await;
+    counter.act_on::<Ping>(|agent, context| {
+        agent.model.count += 1;
+        let _ = context.reply_with(PongResponse(agent.model.count as i8));
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    let first: PongResponse = counter.ask(Ping).await?;
+    let second: PongResponse = counter.ask(Ping).await?;
+    assert_eq!(first.0, 1);
+    assert_eq!(second.0, 2);
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_ask_timeout() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let mut slow_service = app.new_agent::<Messenger>().await;
+    slow_service.act_on::<Ping>(|_agent, context| {
+        let context = context.clone();
+        AgentReply::from_async(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let _ = context.reply_with(PongResponse(1));
+        })
+    });
+    let slow_service = slow_service.start().await;
+
+    let reply = slow_service
+        .ask_timeout::<Ping, PongResponse>(Ping, Duration::from_millis(20))
+        .await;
+    assert!(matches!(reply, Err(MessageError::Timeout)));
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_ask_fails_when_agent_stops() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let (started, mut handling) = tokio::sync::mpsc::unbounded_channel();
+    let mut forgetful = app.new_agent::<Messenger>().await;
+    forgetful.act_on::<Ping>(move |_agent, context| {
+        // The reply channel outlives the agent in a task that never answers
+        let context = context.clone();
+        tokio::spawn(async move {
+            let _context = context;
+            std::future::pending::<()>().await;
+        });
+        let _ = started.send(());
+        AgentReply::immediate()
+    });
+    let forgetful = forgetful.start().await;
+
+    let asker = forgetful.clone();
+    let reply = tokio::spawn(async move { asker.ask::<Ping, PongResponse>(Ping).await });
+    handling.recv().await;
+    forgetful.stop().await?;
+
+    let reply = tokio::time::timeout(Duration::from_secs(1), reply).await??;
+    assert!(matches!(reply, Err(MessageError::AgentStopped { .. })), "got {reply:?}");
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_ask_fails_when_agent_suspends() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let mut counter = app.new_agent::<Counter>().await;
+    counter.act_on::<Ping>(|agent, context| {
+        agent.model.count += 1;
+        let _ = context.reply_with(PongResponse(agent.model.count as i8));
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    counter.suspend().await;
+    let reply = tokio::time::timeout(Duration::from_secs(1), counter.ask::<Ping, PongResponse>(Ping)).await?;
+    assert!(matches!(reply, Err(MessageError::AgentStopped { .. })), "got {reply:?}");
+
+    // The ping is still handled, and asks are answered again, once the agent resumes
+    counter.resume().await;
+    let reply: PongResponse = counter.ask(Ping).await?;
+    assert_eq!(reply.0, 2);
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_forward_keeps_original_sender() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let senders = Arc::new(Mutex::new(Vec::new()));
+    let mut backend = app.new_agent::<Counter>().await;
+    let seen = senders.clone();
+    backend.act_on::<Ping>(move |_, context| {
+        seen.lock().unwrap().push(context.origin_envelope().reply_to().sender().clone());
+        let _ = context.reply_with(PongResponse(7));
+        AgentReply::immediate()
+    });
+    let backend = backend.start().await;
+
+    let mut router = app.new_agent::<Router>().await;
+    router.model.next = backend.clone();
+    router.act_on::<Ping>(|agent, context| Box::pin(context.forward_to(&agent.model.next)));
+    let router = router.start().await;
+
+    // the ask's reply channel travels with the forwarded message
+    let reply: PongResponse = router.ask(Ping).await?;
+    assert_eq!(reply.0, 7);
+
+    let caller = app.new_agent::<Counter>().await.start().await;
+    caller.create_envelope(Some(router.reply_address())).send(Ping).await;
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while senders.lock().unwrap().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await?;
+    assert_eq!(senders.lock().unwrap()[1], caller.id(), "the backend should see the caller, not the router");
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_send_reliable_redelivers_until_acked() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let attempts = Arc::new(Mutex::new(0));
+    let handled = Arc::new(Mutex::new(0));
+    let seen_ids = Arc::new(Mutex::new(HashSet::new()));
+    let mut receiver = app.new_agent::<Counter>().await;
+    let (seen_attempts, seen_handled) = (attempts.clone(), handled.clone());
+    receiver.act_on::<Ping>(move |_, context| {
+        *seen_attempts.lock().unwrap() += 1;
+        let delivery_id = context.delivery_id().expect("sent reliably");
+        if seen_ids.lock().unwrap().insert(delivery_id) {
+            // handle the first attempt but "lose" its ack
+            *seen_handled.lock().unwrap() += 1;
+        } else {
+            context.ack();
+        }
+        AgentReply::immediate()
+    });
+    let receiver = receiver.start().await;
+
+    let delivery = receiver.send_reliable(Ping, Duration::from_millis(50), 3);
+    delivery.outcome().await?;
+    assert_eq!(*attempts.lock().unwrap(), 2, "the ack on the second attempt should end the redeliveries");
+    assert_eq!(*handled.lock().unwrap(), 1, "the redelivery should be recognized as a duplicate");
+
+    // nobody acks this one
+    let mut silent = app.new_agent::<Counter>().await;
+    silent.act_on::<Ping>(|_, _| AgentReply::immediate());
+    let silent = silent.start().await;
+    let outcome = silent.send_reliable(Ping, Duration::from_millis(20), 3).outcome().await;
+    assert!(matches!(outcome, Err(MessageError::NotAcknowledged(3))));
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_dedup_skips_redelivered_messages() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let handled = Arc::new(Mutex::new(0));
+    let config = AgentConfig::new_with_name("deduplicating")?.with_dedup(16);
+    let mut receiver = app.create_actor_with_config::<Counter>(config).await;
+    let seen = handled.clone();
+    // never acks, so the sender delivers the same id again
+    receiver.act_on::<Ping>(move |_, _| {
+        *seen.lock().unwrap() += 1;
+        AgentReply::immediate()
+    });
+    let receiver = receiver.start().await;
+
+    let outcome = receiver.send_reliable(Ping, Duration::from_millis(20), 2).outcome().await;
+    assert!(outcome.is_ok(), "the skipped duplicate should be acknowledged");
+    assert_eq!(*handled.lock().unwrap(), 1);
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_send_to_stopped_agent() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let counter = app.new_agent::<Counter>().await.start().await;
+    let stale = counter.clone();
+    counter.stop().await?;
+
+    let sent = stale.create_envelope(None).reply(Ping);
+    // The error says what couldn't be delivered as well as where
+    assert!(
+        matches!(
+            &sent,
+            Err(MessageError::AgentStopped { id, message_type })
+                if **id == counter.id() && *message_type == std::any::type_name::<Ping>()
+        ),
+        "expected AgentStopped for a Ping, got {:?}",
+        sent
+    );
+    assert!(sent.unwrap_err().to_string().contains(std::any::type_name::<Ping>()));
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_reply_to_sender() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let mut responder = app.new_agent::<Counter>().await;
+    responder.act_on::<Ping>(|_, context| Box::pin(context.reply_to_sender(PongResponse(42))));
+    let responder = responder.start().await;
+
+    let (replies, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let mut caller = app.new_agent::<Counter>().await;
+    caller.act_on::<PongResponse>(move |_, context| {
+        let _ = replies.send(context.message().0);
+        AgentReply::immediate()
+    });
+    let caller = caller.start().await;
+
+    caller.create_envelope(Some(responder.reply_address())).send(Ping).await;
+    let reply = tokio::time::timeout(Duration::from_secs(1), received.recv()).await?;
+    assert_eq!(reply, Some(42));
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Router {
+    next: AgentHandle,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct PongResponse(i8);
 
@@ -132,3 +394,58 @@ impl ShoppingCart {
         self.agent_handle.send(Ping).await;
     }
 }
+
+#[acton_test]
+async fn test_sender_ern() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let (senders, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let mut guard = app.new_agent_with_name::<Counter>("guard".to_string()).await;
+    guard.act_on::<Ping>(move |_, context| {
+        let _ = senders.send(context.sender_ern().cloned());
+        AgentReply::immediate()
+    });
+    guard.handle().subscribe::<Ping>().await?;
+    let guard = guard.start().await;
+    let alice = app.new_agent_with_name::<Counter>("alice".to_string()).await.start().await;
+
+    alice.create_envelope(Some(guard.reply_address())).send(Ping).await;
+    let sender = tokio::time::timeout(Duration::from_secs(1), received.recv()).await?.flatten();
+    assert_eq!(sender, Some(alice.id()), "the handler should see who sent the ping");
+
+    guard.send(Ping).await;
+    let sender = tokio::time::timeout(Duration::from_secs(1), received.recv()).await?;
+    assert_eq!(sender, Some(None), "a message sent from outside any agent has no sender");
+
+    app.broker().broadcast(Ping).await;
+    let sender = tokio::time::timeout(Duration::from_secs(1), received.recv()).await?;
+    assert_eq!(sender, Some(None), "a broadcast from the broker itself has no single sender");
+
+    app.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_ask_stream() -> anyhow::Result<()> {
+    use futures::StreamExt;
+
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let mut counter = app.new_agent::<Counter>().await;
+    counter.act_on::<Ping>(|_, context| {
+        Box::pin(context.reply_stream(futures::stream::iter((1..=5).map(PongResponse))))
+    });
+    let counter = counter.start().await;
+
+    let mut replies = Box::pin(counter.ask_stream::<Ping, PongResponse>(Ping).await);
+    let mut received = Vec::new();
+    while let Some(reply) = tokio::time::timeout(Duration::from_secs(1), replies.next()).await? {
+        received.push(reply.0);
+    }
+    assert_eq!(received, vec![1, 2, 3, 4, 5], "every item should arrive, in order, before the end");
+
+    app.shutdown_all().await?;
+    Ok(())
+}
@@ -13,6 +13,10 @@
  * See the applicable License for the specific language governing permissions and
  * limitations under that License.
  */
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use acton_reactive::prelude::*;
 use acton_test::prelude::*;
 
@@ -40,3 +44,633 @@ async fn test_actor_lifecycle_events() -> anyhow::Result<()> {
     actor_context.stop().await?;
     Ok(())
 }
+
+#[acton_test]
+async fn test_watch_and_unwatch() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let mut watcher = runtime.new_agent::<Counter>().await;
+    watcher
+        .act_on::<Terminated>(|agent, context| {
+            tracing::info!("{} terminated", context.message().who);
+            agent.model.count += 1;
+            AgentReply::immediate()
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.count, 1, "only the watched agent should be reported");
+            AgentReply::immediate()
+        });
+    let watcher = watcher.start().await;
+
+    let watched = runtime.new_agent::<PoolItem>().await.start().await;
+    let unwatched = runtime.new_agent::<PoolItem>().await.start().await;
+
+    watcher.watch(&watched).await;
+    watcher.watch(&unwatched).await;
+    watcher.unwatch(&unwatched).await;
+
+    watched.stop().await?;
+    unwatched.stop().await?;
+    watcher.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_suspend_and_resume() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let handled = Arc::new(Mutex::new(Vec::new()));
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let reports = handled.clone();
+    counter.act_on::<StatusReport>(move |_, context| {
+        let StatusReport::Complete(n) = context.message();
+        reports.lock().unwrap().push(*n);
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    counter.suspend().await;
+    for n in 1..=3 {
+        counter.send(StatusReport::Complete(n)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(handled.lock().unwrap().is_empty(), "a suspended agent should not handle messages");
+
+    counter.resume().await;
+    counter.stop().await?;
+    assert_eq!(*handled.lock().unwrap(), vec![1, 2, 3], "held messages should be handled in order");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_suspend_keeps_backpressure() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let handled = Arc::new(Mutex::new(Vec::new()));
+    let config = AgentConfig::new(Ern::with_root("tiny_mailbox")?, None, None)?.with_mailbox_capacity(2);
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    let reports = handled.clone();
+    counter.act_on::<StatusReport>(move |_, context| {
+        let StatusReport::Complete(n) = context.message();
+        reports.lock().unwrap().push(*n);
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    counter.suspend().await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    // The messages stay in the mailbox, so it fills as it would for a busy agent
+    for n in 1..=2 {
+        counter.try_send(StatusReport::Complete(n)).expect("the mailbox has room");
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(
+        matches!(counter.try_send(StatusReport::Complete(3)), Err(TrySendError::Full(_))),
+        "a suspended agent should leave its mailbox full"
+    );
+
+    counter.resume().await;
+    counter.stop().await?;
+    assert_eq!(*handled.lock().unwrap(), vec![1, 2]);
+    Ok(())
+}
+
+#[acton_test]
+async fn test_stop_under_load() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("flooded")?, None, None)?
+        .with_unbounded_mailbox()
+        .with_signal_check_interval(8);
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    let (observed, mut counts) = tokio::sync::mpsc::unbounded_channel();
+    let (before, after) = (observed.clone(), observed);
+    counter
+        .act_on::<Ping>(|agent, _| {
+            agent.model.count += 1;
+            AgentReply::from_async(tokio::time::sleep(Duration::from_millis(1)))
+        })
+        .before_stop(move |agent| {
+            let _ = before.send(agent.model.count);
+            AgentReply::immediate()
+        })
+        .after_stop(move |agent| {
+            let _ = after.send(agent.model.count);
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    for _ in 0..500 {
+        counter.send(Ping).await;
+    }
+    counter.stop().await?;
+
+    // Terminate was taken long before the flood was worked through, which still finished
+    let at_terminate = counts.recv().await.unwrap();
+    assert!(at_terminate < 100, "Terminate waited behind {at_terminate} messages");
+    assert_eq!(counts.recv().await, Some(500));
+    Ok(())
+}
+
+#[acton_test]
+async fn test_terminate_from_handler() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (stopped, mut stops) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .act_on::<Ping>(|agent, _| {
+            agent.model.count += 1;
+            // A handler can't wait on its own agent stopping, but it can ask it to
+            let _ = agent.handle().terminate();
+            AgentReply::immediate()
+        })
+        .after_stop(move |agent| {
+            let _ = stopped.send(agent.model.count);
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    counter.send(Ping).await;
+    let count = tokio::time::timeout(Duration::from_secs(1), stops.recv()).await?;
+    assert_eq!(count, Some(1));
+
+    counter.tracker().wait().await;
+    assert!(
+        matches!(counter.terminate(), Err(MessageError::AgentStopped { .. })),
+        "terminating a stopped agent should say so"
+    );
+    Ok(())
+}
+
+#[acton_test]
+async fn test_terminate_from_plain_thread() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (stopped, mut stops) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter.after_stop(move |_| {
+        let _ = stopped.send(());
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    // No runtime on this thread, and nothing to await
+    let handle = counter.clone();
+    std::thread::spawn(move || handle.terminate()).join().expect("the terminating thread panicked")?;
+    tokio::time::timeout(Duration::from_secs(1), stops.recv()).await?;
+
+    counter.tracker().wait().await;
+    let handle = counter.clone();
+    let error = std::thread::spawn(move || handle.terminate()).join().expect("the terminating thread panicked");
+    assert!(matches!(error, Err(MessageError::AgentStopped { .. })), "got {error:?}");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_drain() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let handled = Arc::new(Mutex::new(Vec::new()));
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let reports = handled.clone();
+    counter.act_on::<StatusReport>(move |_, context| {
+        let StatusReport::Complete(n) = *context.message();
+        let reports = reports.clone();
+        AgentReply::from_async(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            reports.lock().unwrap().push(n);
+        })
+    });
+    let counter = counter.start().await;
+
+    for n in 1..=5 {
+        counter.send(StatusReport::Complete(n)).await;
+    }
+    let (drained, rejected) = tokio::join!(counter.drain(), async {
+        counter.create_envelope(None).reply(StatusReport::Complete(6))
+    });
+    drained?;
+    assert!(matches!(rejected, Err(MessageError::Draining)), "a draining agent should refuse new messages");
+    assert_eq!(*handled.lock().unwrap(), vec![1, 2, 3, 4, 5], "queued messages should all be handled");
+    Ok(())
+}
+
+/// Starts an agent that takes 20ms over each `StatusReport`, sends it five, stops it, and
+/// returns how many it handled.
+async fn handled_before_stop(policy: TerminatePolicy) -> anyhow::Result<usize> {
+    let mut runtime: AgentRuntime = ActonApp::launch();
+    let config = AgentConfig::new(Ern::with_root("worker")?, None, None)?.with_terminate_policy(policy);
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    let handled = Arc::new(AtomicIsize::new(0));
+    let count = handled.clone();
+    counter.act_on::<StatusReport>(move |_, _| {
+        let count = count.clone();
+        AgentReply::from_async(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            count.fetch_add(1, Ordering::SeqCst);
+        })
+    });
+    let counter = counter.start().await;
+
+    for n in 1..=5 {
+        counter.send(StatusReport::Complete(n)).await;
+    }
+    // Let the agent get started on the first one
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    counter.stop().await?;
+    Ok(usize::try_from(handled.load(Ordering::SeqCst))?)
+}
+
+#[acton_test]
+async fn test_terminate_drains_pending() -> anyhow::Result<()> {
+    initialize_tracing();
+    assert_eq!(handled_before_stop(TerminatePolicy::DrainPending).await?, 5, "every queued message should be handled");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_terminate_drops_pending() -> anyhow::Result<()> {
+    initialize_tracing();
+    assert_eq!(
+        handled_before_stop(TerminatePolicy::DropPending).await?,
+        1,
+        "only the message being handled should finish; the queued ones are dropped"
+    );
+    Ok(())
+}
+
+#[acton_test]
+async fn test_uptime_and_last_message() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let counter = runtime.new_agent::<Counter>().await.start().await;
+    assert!(counter.last_message_at().is_none(), "no message has been sent yet");
+
+    counter.send(Ping).await;
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while counter.last_message_at().is_none() {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await?;
+
+    // The agent started, and took its only message, before this wait began
+    let interval = Duration::from_millis(50);
+    tokio::time::sleep(interval).await;
+    assert!(counter.uptime() >= interval, "uptime {:?} should cover the wait", counter.uptime());
+    let idle = counter.last_message_at().unwrap().elapsed();
+    assert!(idle >= interval, "idle time {idle:?} should cover the wait");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_health() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .act_on::<Ping>(|_, _| AgentReply::immediate())
+        .act_on::<StatusReport>(|_, _| {
+            AgentReply::from_async(async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            })
+        });
+    let counter = counter.start().await;
+
+    counter.send(Ping).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let health = counter.health();
+    assert_eq!(health.state, HealthState::Healthy);
+    assert!(health.is_live() && health.is_ready());
+    assert!(health.idle_for.is_some(), "the agent has taken a message");
+
+    counter.send(StatusReport::Complete(1)).await;
+    let (drained, draining) = tokio::join!(counter.drain(), async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        counter.health()
+    });
+    drained?;
+    assert_eq!(draining.state, HealthState::Draining, "the agent was still finishing its queued message");
+    assert!(draining.is_live() && !draining.is_ready());
+    assert_eq!(counter.health().state, HealthState::Stopped);
+    assert!(!counter.health().is_live());
+    Ok(())
+}
+
+#[acton_test]
+async fn test_readiness_check() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter.act_on::<Ping>(|agent, _| {
+        agent.model.count += 1;
+        AgentReply::immediate()
+    });
+    counter.readiness_check(|counter| counter.count > 0);
+    let counter = counter.start().await;
+    assert_eq!(counter.health().state, HealthState::NotReady, "no ping has warmed the agent up yet");
+
+    counter.suspend().await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(counter.health().state, HealthState::Suspended);
+    counter.resume().await;
+
+    counter.send(Ping).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(counter.health().state, HealthState::Healthy);
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_cancellation_token() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (started, mut running) = tokio::sync::mpsc::unbounded_channel();
+    let mut agent = runtime.new_agent::<Counter>().await;
+    agent.act_on::<Ping>(move |agent, _| {
+        let token = agent.cancellation_token();
+        let started = started.clone();
+        Box::pin(async move {
+            let _ = started.send(());
+            // Stands in for a stream that never ends on its own
+            while !token.is_cancelled() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+    });
+    let handle = agent.start().await;
+
+    handle.send(Ping).await;
+    tokio::time::timeout(Duration::from_secs(1), running.recv()).await?;
+    tokio::time::timeout(Duration::from_secs(1), handle.stop())
+        .await
+        .expect("stopping should cut the handler short")?;
+
+    Ok(())
+}
+
+#[acton_test]
+async fn test_idle_timeout() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("session")?, None, None)?
+        .with_idle_timeout(Duration::from_millis(100));
+    let mut session = runtime.create_actor_with_config::<Counter>(config).await;
+    let (stopped, mut stops) = tokio::sync::mpsc::unbounded_channel();
+    session
+        .act_on::<Ping>(|agent, _| {
+            agent.model.count += 1;
+            AgentReply::immediate()
+        })
+        .after_stop(move |agent| {
+            let _ = stopped.send(agent.model.count);
+            AgentReply::immediate()
+        });
+    let session = session.start().await;
+
+    // Messages keep arriving well within the timeout, for longer than the timeout
+    for _ in 0..6 {
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        session.send(Ping).await;
+    }
+    assert!(stops.try_recv().is_err(), "a busy agent should stay alive");
+
+    // Once they stop, the agent stops itself after handling them all
+    let count = tokio::time::timeout(Duration::from_secs(1), stops.recv()).await?;
+    assert_eq!(count, Some(6));
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+/// How many `Tracked` models are alive.
+static LIVE_MODELS: AtomicIsize = AtomicIsize::new(0);
+
+/// A model that counts how many of it are alive.
+#[derive(Debug)]
+struct Tracked;
+
+impl Default for Tracked {
+    fn default() -> Self {
+        LIVE_MODELS.fetch_add(1, Ordering::SeqCst);
+        Tracked
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        LIVE_MODELS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[acton_test]
+async fn test_stopped_agents_are_freed() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime = ActonApp::launch();
+
+    for _ in 0..3 {
+        let mut handles = Vec::new();
+        for _ in 0..1000 {
+            handles.push(runtime.new_agent::<Tracked>().await.start().await);
+        }
+        assert_eq!(LIVE_MODELS.load(Ordering::SeqCst), 1000);
+        for result in futures::future::join_all(handles.iter().map(|handle| handle.stop())).await {
+            result?;
+        }
+        // Each cycle starts from nothing, rather than adding to what earlier ones left behind
+        assert_eq!(LIVE_MODELS.load(Ordering::SeqCst), 0, "every stopped agent should be freed");
+        assert_eq!(runtime.agent_count(), 0, "stopped agents shouldn't be kept as roots");
+    }
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_prepare_starts_on_first_send() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime = ActonApp::launch();
+
+    let starts = Arc::new(Mutex::new(0));
+    let (pinged, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let counted = starts.clone();
+    counter
+        .after_start(move |_| {
+            *counted.lock().unwrap() += 1;
+            AgentReply::immediate()
+        })
+        .act_on::<Ping>(move |agent, _| {
+            agent.model.count += 1;
+            let _ = pinged.send(agent.model.count);
+            AgentReply::immediate()
+        });
+    let counter = counter.prepare();
+    // Never used, so it must not hold up shutdown
+    let _cold = runtime.new_agent::<Counter>().await.prepare();
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!counter.is_started(), "a prepared agent should wait to be used");
+    assert_eq!(*starts.lock().unwrap(), 0, "after_start shouldn't run before the first send");
+    assert!(runtime.find(counter.id()).is_none(), "a prepared agent has no running task to find");
+
+    // Racing first sends start the agent once
+    let sends = (0..5).map(|_| {
+        let counter = counter.clone();
+        tokio::spawn(async move { counter.send(Ping).await })
+    });
+    for send in sends {
+        send.await?;
+    }
+    for _ in 0..5 {
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await?;
+    }
+    assert!(counter.is_started());
+    assert_eq!(*starts.lock().unwrap(), 1, "the agent should start exactly once");
+
+    tokio::time::timeout(Duration::from_secs(1), runtime.shutdown_all()).await??;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_prepare_survives_cancelled_send() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime = ActonApp::launch();
+
+    let (pinged, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .before_start(|_| {
+            AgentReply::from_async(async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            })
+        })
+        .act_on::<Ping>(move |_, _| {
+            let _ = pinged.send(());
+            AgentReply::immediate()
+        });
+    let counter = counter.prepare();
+
+    // The first send gives up while the agent is still starting
+    let cancelled = tokio::time::timeout(Duration::from_millis(10), counter.send(Ping)).await;
+    assert!(cancelled.is_err(), "the first send should time out mid-start");
+    assert!(!counter.is_started());
+
+    tokio::time::timeout(Duration::from_secs(1), counter.send(Ping)).await?;
+    tokio::time::timeout(Duration::from_secs(1), received.recv()).await?;
+    assert!(counter.is_started());
+
+    tokio::time::timeout(Duration::from_secs(1), runtime.shutdown_all()).await??;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_runtime_shutdown() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime = ActonApp::launch();
+
+    let stopped = Arc::new(Mutex::new(0));
+    for _ in 0..5 {
+        let mut counter = runtime.new_agent::<Counter>().await;
+        let stopped = stopped.clone();
+        counter.after_stop(move |_| {
+            *stopped.lock().unwrap() += 1;
+            AgentReply::immediate()
+        });
+        counter.start().await;
+    }
+
+    runtime.shutdown().await?;
+    assert_eq!(*stopped.lock().unwrap(), 5, "every agent should run after_stop");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_shutdown_ordered() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime = ActonApp::launch();
+
+    let stopped = Arc::new(Mutex::new(Vec::new()));
+    let mut dependency = runtime.new_agent_with_name::<Counter>("b".to_string()).await;
+    let dependency_id = dependency.id().clone();
+    let config = AgentConfig::new_with_name("a")?.depends_on(dependency_id);
+    let mut dependent = runtime.create_actor_with_config::<Counter>(config).await;
+    for (agent, name) in [(&mut dependency, "b"), (&mut dependent, "a")] {
+        let stopped = stopped.clone();
+        agent.after_stop(move |_| {
+            stopped.lock().unwrap().push(name);
+            AgentReply::immediate()
+        });
+    }
+    dependency.start().await;
+    dependent.start().await;
+
+    runtime.shutdown_ordered().await?;
+    assert_eq!(*stopped.lock().unwrap(), vec!["a", "b"], "a depends on b, so it should stop first");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_runtime_shutdown_timeout() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime = ActonApp::launch();
+
+    let mut slow = runtime.new_agent_with_name::<Counter>("slow".to_string()).await;
+    slow.before_stop(|_| {
+        AgentReply::from_async(async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        })
+    });
+    slow.start().await;
+    runtime.new_agent::<Counter>().await.start().await;
+
+    let error = runtime
+        .shutdown_with_timeout(Duration::from_millis(50))
+        .await
+        .expect_err("the slow agent should not stop in time");
+    let message = error.to_string();
+    assert!(message.contains("slow"), "{message}");
+    assert!(!message.contains("agent"), "{message}");
+    Ok(())
+}
+
+#[acton_test]
+async fn test_shutdown_timeout() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime = ActonApp::launch();
+
+    let flushed = Arc::new(Mutex::new(false));
+    let config = AgentConfig::new_with_name("flusher")?.with_shutdown_timeout(Duration::from_millis(100));
+    let mut flusher = runtime.create_actor_with_config::<Counter>(config).await;
+    let done = flushed.clone();
+    flusher.before_stop(move |_| {
+        let done = done.clone();
+        AgentReply::from_async(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            *done.lock().unwrap() = true;
+        })
+    });
+    let flusher = flusher.start().await;
+
+    let started = tokio::time::Instant::now();
+    flusher.stop().await?;
+    assert!(started.elapsed() < Duration::from_secs(1), "stop should not wait for the slow hook");
+    assert!(!*flushed.lock().unwrap(), "the hook should have been cut short");
+    Ok(())
+}
@@ -0,0 +1,477 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+
+use acton_reactive::prelude::*;
+use acton_test::prelude::*;
+
+use crate::setup::*;
+
+mod setup;
+
+#[acton_test]
+async fn test_mailbox_capacity_backpressure() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("tiny_mailbox")?, None, None)?
+        .with_mailbox_capacity(1);
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+
+    // every Ping waits for a permit, so the agent stays busy until we release it
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    counter
+        .act_on::<Ping>(move |agent, _context| {
+            agent.model.count += 1;
+            let gate = handler_gate.clone();
+            AgentReply::from_async(async move {
+                let _ = gate.acquire().await.map(|permit| permit.forget());
+            })
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.count, 3, "all three pings should be handled");
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    // the first ping is taken by the busy handler, the second fills the only slot
+    counter.send(Ping).await;
+    counter.send(Ping).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // a third send has nowhere to go until the first ping is consumed
+    assert!(
+        timeout(Duration::from_millis(50), counter.send(Ping)).await.is_err(),
+        "send should block while the mailbox is full"
+    );
+
+    gate.add_permits(1);
+    timeout(Duration::from_millis(500), counter.send(Ping)).await?;
+
+    gate.add_permits(2);
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_mailbox_len() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("small_mailbox")?, None, None)?
+        .with_mailbox_capacity(4);
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    counter.act_on::<Ping>(move |_, _| {
+        let gate = handler_gate.clone();
+        AgentReply::from_async(async move {
+            let _ = gate.acquire().await.map(|permit| permit.forget());
+        })
+    });
+    let counter = counter.start().await;
+    assert_eq!(counter.mailbox_capacity(), 4);
+    assert_eq!(counter.mailbox_len(), 0);
+
+    // the first ping is taken by the busy handler, the rest wait in the mailbox
+    counter.send(Ping).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    for _ in 0..3 {
+        counter.send(Ping).await;
+    }
+    assert_eq!(counter.mailbox_len(), 3);
+
+    gate.add_permits(4);
+    counter.stop().await?;
+    assert_eq!(counter.mailbox_len(), 0);
+    Ok(())
+}
+
+#[acton_test]
+async fn test_try_send() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("tiny_mailbox")?, None, None)?
+        .with_mailbox_capacity(1);
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    counter.act_on::<StatusReport>(move |_, _| {
+        let gate = handler_gate.clone();
+        AgentReply::from_async(async move {
+            let _ = gate.acquire().await.map(|permit| permit.forget());
+        })
+    });
+    let counter = counter.start().await;
+
+    // the first report is taken by the busy handler, the second fills the only slot
+    counter.try_send(StatusReport::Complete(1)).expect("the mailbox has room");
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    counter.try_send(StatusReport::Complete(2)).expect("the mailbox has room");
+
+    // the next one is handed straight back instead of waiting
+    let error = counter.try_send(StatusReport::Complete(3)).unwrap_err();
+    assert!(matches!(error.into_inner(), Some(StatusReport::Complete(3))));
+
+    gate.add_permits(2);
+    counter.stop().await?;
+    assert!(matches!(counter.try_send(StatusReport::Complete(4)), Err(TrySendError::Closed)));
+    Ok(())
+}
+
+#[acton_test]
+async fn test_send_blocking() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("tiny_mailbox")?, None, None)?
+        .with_mailbox_capacity(1);
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    let (handled, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    counter.act_on::<StatusReport>(move |_, context| {
+        let gate = handler_gate.clone();
+        let handled = handled.clone();
+        let StatusReport::Complete(n) = *context.message();
+        AgentReply::from_async(async move {
+            let _ = gate.acquire().await.map(|permit| permit.forget());
+            let _ = handled.send(n);
+        })
+    });
+    let counter = counter.start().await;
+
+    // On a blocking task the first two fit, and the third waits for room, keeping its place
+    let sender = counter.clone();
+    let sending = tokio::task::spawn_blocking(move || {
+        for n in 1..=4 {
+            sender.send_blocking(StatusReport::Complete(n))?;
+        }
+        Ok::<_, MessageError>(())
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!sending.is_finished(), "the blocking task should wait for room");
+
+    gate.add_permits(4);
+    let mut received = Vec::new();
+    for _ in 0..4 {
+        received.push(timeout(Duration::from_secs(1), reports.recv()).await?.unwrap());
+    }
+    assert_eq!(received, vec![1, 2, 3, 4]);
+    sending.await??;
+
+    // Off the runtime, the call waits for room too
+    let sender = counter.clone();
+    let blocked = std::thread::spawn(move || {
+        for n in 5..=7 {
+            sender.send_blocking(StatusReport::Complete(n))?;
+        }
+        Ok::<_, MessageError>(())
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!blocked.is_finished(), "the sending thread should wait for room");
+
+    gate.add_permits(3);
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        received.push(timeout(Duration::from_secs(1), reports.recv()).await?.unwrap());
+    }
+    assert_eq!(received, vec![5, 6, 7]);
+    blocked.join().expect("the sending thread panicked")?;
+
+    counter.stop().await?;
+    let error = tokio::task::spawn_blocking(move || counter.send_blocking(StatusReport::Complete(8))).await?;
+    assert!(matches!(error, Err(MessageError::AgentStopped { .. })));
+
+    // An unbounded mailbox is never full, but a stopped agent still says it stopped
+    let config = AgentConfig::new(Ern::with_root("unbounded")?, None, None)?.with_unbounded_mailbox();
+    let counter = app.create_actor_with_config::<Counter>(config).await.start().await;
+    counter.stop().await?;
+    let error = tokio::task::spawn_blocking(move || counter.send_blocking(StatusReport::Complete(9))).await?;
+    assert!(matches!(error, Err(MessageError::AgentStopped { .. })), "got {error:?}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_send_blocking_current_thread() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("tiny_mailbox")?, None, None)?
+        .with_mailbox_capacity(1);
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    counter.act_on::<StatusReport>(move |_, _| {
+        let gate = handler_gate.clone();
+        AgentReply::from_async(async move {
+            let _ = gate.acquire().await.map(|permit| permit.forget());
+        })
+    });
+    let counter = counter.start().await;
+
+    // The runtime's only thread is the one the agent needs to make room
+    counter.send_blocking(StatusReport::Complete(1))?;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    counter.send_blocking(StatusReport::Complete(2))?;
+    assert!(matches!(counter.send_blocking(StatusReport::Complete(3)), Err(MessageError::MailboxFull)));
+
+    gate.add_permits(2);
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_on_mailbox_full() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("tiny_mailbox")?, None, None)?
+        .with_mailbox_capacity(1);
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+
+    let (overloaded, mut alarms) = tokio::sync::mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    counter
+        .act_on::<StatusReport>(move |_, _| {
+            let gate = handler_gate.clone();
+            AgentReply::from_async(async move {
+                let _ = gate.acquire().await.map(|permit| permit.forget());
+            })
+        })
+        .on_mailbox_full(move |id, depth| {
+            let _ = overloaded.send((id.clone(), depth));
+        });
+    let counter = counter.start().await;
+
+    for round in 1..=2 {
+        // the first report is taken by the busy handler, the second fills the only slot
+        counter.try_send(StatusReport::Complete(1)).expect("the mailbox has room");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        counter.try_send(StatusReport::Complete(2)).expect("the mailbox has room");
+        for n in 3..=6 {
+            assert!(counter.try_send(StatusReport::Complete(n)).is_err());
+        }
+        let (id, depth) = alarms.try_recv().expect("the hook should fire when the mailbox fills");
+        assert_eq!((id, depth), (counter.id(), 1));
+        assert!(alarms.try_recv().is_err(), "round {round}: the hook should fire once per overload");
+
+        // once the agent catches up, the next overload is reported again
+        gate.add_permits(2);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_on_mailbox_full_batch() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("tiny_mailbox")?, None, None)?
+        .with_mailbox_capacity(1);
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+
+    let (overloaded, mut alarms) = tokio::sync::mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    counter
+        .act_on::<StatusReport>(move |_, _| {
+            let gate = handler_gate.clone();
+            AgentReply::from_async(async move {
+                let _ = gate.acquire().await.map(|permit| permit.forget());
+            })
+        })
+        .on_mailbox_full(move |id, depth| {
+            let _ = overloaded.send((id.clone(), depth));
+        });
+    let counter = counter.start().await;
+
+    // the busy handler holds one report and the next fills the only slot, so the batch
+    // has to wait for the last
+    let reports: Vec<Box<dyn ActonMessage>> =
+        (1..=3).map(|n| Box::new(StatusReport::Complete(n)) as Box<dyn ActonMessage>).collect();
+    let sender = counter.clone();
+    let batch = tokio::spawn(async move { sender.send_batch(reports).await });
+    let (id, depth) = tokio::time::timeout(Duration::from_secs(1), alarms.recv()).await?.expect("the hook is kept");
+    assert_eq!((id, depth), (counter.id(), 1));
+
+    gate.add_permits(3);
+    batch.await?;
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_unbounded_mailbox() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("aggregator")?, None, None)?.with_unbounded_mailbox();
+    let mut counter = app.create_actor_with_config::<Counter>(config).await;
+
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    counter
+        .act_on::<Ping>(move |agent, _| {
+            agent.model.count += 1;
+            let gate = handler_gate.clone();
+            AgentReply::from_async(async move {
+                let _ = gate.acquire().await.map(|permit| permit.forget());
+            })
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.count, 10_000);
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+    assert_eq!(counter.mailbox_capacity(), usize::MAX);
+
+    // the handler is stuck on the first ping, so a bounded mailbox would fill up
+    timeout(Duration::from_secs(5), async {
+        for _ in 0..10_000 {
+            counter.send(Ping).await;
+        }
+    })
+    .await
+    .expect("sending to an unbounded mailbox should never wait");
+
+    gate.add_permits(10_000);
+    counter.stop().await?;
+    Ok(())
+}
+
+#[derive(Default, Debug)]
+struct Recorder {
+    handled: Vec<usize>,
+}
+
+#[acton_test]
+async fn test_priority_mailbox() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    let config = AgentConfig::new(Ern::with_root("control_plane")?, None, None)?.with_priority_mailbox();
+    let mut recorder = app.create_actor_with_config::<Recorder>(config).await;
+
+    let gate = Arc::new(Semaphore::new(0));
+    let handler_gate = gate.clone();
+    recorder
+        .act_on::<Ping>(move |_, _| {
+            let gate = handler_gate.clone();
+            AgentReply::from_async(async move {
+                let _ = gate.acquire().await.map(|permit| permit.forget());
+            })
+        })
+        .act_on::<StatusReport>(|agent, context| {
+            let StatusReport::Complete(n) = context.message();
+            agent.model.handled.push(*n);
+            AgentReply::immediate()
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.handled, vec![1, 3, 5, 0, 2, 4], "priority messages go first");
+            AgentReply::immediate()
+        });
+    let recorder = recorder.start().await;
+
+    // hold the agent busy so both mailboxes fill up
+    recorder.send(Ping).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    for n in 0..6 {
+        if n % 2 == 1 {
+            recorder.send_priority(StatusReport::Complete(n)).await;
+        } else {
+            recorder.send(StatusReport::Complete(n)).await;
+        }
+    }
+
+    gate.add_permits(1);
+    recorder.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_send_batch() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut app = ActonApp::launch();
+
+    // a batch larger than the mailbox goes out in mailbox-sized chunks
+    let config = AgentConfig::new(Ern::with_root("batch_sink")?, None, None)?.with_mailbox_capacity(4);
+    let mut recorder = app.create_actor_with_config::<Recorder>(config).await;
+    recorder
+        .act_on::<StatusReport>(|agent, context| {
+            let StatusReport::Complete(n) = context.message();
+            agent.model.handled.push(*n);
+            AgentReply::immediate()
+        })
+        .after_stop(|agent| {
+            assert_eq!(agent.model.handled, (0..10).collect::<Vec<_>>(), "the batch should arrive in order");
+            AgentReply::immediate()
+        });
+    let recorder = recorder.start().await;
+
+    let batch: Vec<Box<dyn ActonMessage>> = (0..10)
+        .map(|n| Box::new(StatusReport::Complete(n)) as Box<dyn ActonMessage>)
+        .collect();
+    recorder.send_batch(batch).await;
+
+    recorder.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_rate_limit() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    // Twenty a second after a burst of two
+    let config = AgentConfig::new_with_name("limited")?.with_rate_limit(20, 2);
+    let (handled, mut times) = tokio::sync::mpsc::unbounded_channel();
+    let mut limited = runtime.create_actor_with_config::<Counter>(config).await;
+    limited.act_on::<Ping>(move |_, _| {
+        let _ = handled.send(tokio::time::Instant::now());
+        AgentReply::immediate()
+    });
+    let limited = limited.start().await;
+
+    for _ in 0..6 {
+        limited.send(Ping).await;
+    }
+    let mut handled_at = Vec::new();
+    for _ in 0..6 {
+        handled_at.push(timeout(Duration::from_secs(5), times.recv()).await?.unwrap());
+    }
+    let gaps: Vec<Duration> = handled_at.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    assert!(gaps[0] < Duration::from_millis(25), "the burst should go through at once: {gaps:?}");
+    for gap in &gaps[2..] {
+        assert!(*gap >= Duration::from_millis(49), "messages past the burst should be 50ms apart: {gaps:?}");
+    }
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
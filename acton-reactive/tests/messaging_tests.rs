@@ -15,6 +15,7 @@
  */
 
 use std::any::TypeId;
+use std::time::Duration;
 
 use tracing::*;
 
@@ -89,3 +90,284 @@ async fn test_async_messaging_behavior() -> anyhow::Result<()> {
     context.stop().await?;
     Ok(())
 }
+
+#[cfg(feature = "serde")]
+#[acton_test]
+async fn test_message_registry_round_trip() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut registry = MessageRegistry::new();
+    registry.register::<Ping>();
+
+    let bytes = Ping.to_bytes()?;
+    let message = registry.deserialize(Ping::TAG, &bytes)?;
+    assert_eq!(message.as_any().downcast_ref::<Ping>(), Some(&Ping));
+
+    assert!(matches!(
+        registry.deserialize("acton.test.unknown", &bytes),
+        Err(MessageError::UnknownMessageType(_))
+    ));
+    assert!(matches!(
+        registry.deserialize(Ping::TAG, b"not a ping"),
+        Err(MessageError::SerializationFailed(_))
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "testing")]
+#[acton_test]
+async fn test_probe_await_idle() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let handled = std::sync::Arc::new(std::sync::Mutex::new(0));
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let seen = handled.clone();
+    counter.act_on::<Ping>(move |_, _| {
+        let seen = seen.clone();
+        AgentReply::from_async(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            *seen.lock().unwrap() += 1;
+        })
+    });
+    let probe = TestProbe::new(counter.start().await);
+
+    for _ in 0..3 {
+        probe.tell(Ping).await;
+    }
+    probe.await_idle().await;
+    assert_eq!(*handled.lock().unwrap(), 3, "every message should be handled once the agent is idle");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[cfg(feature = "testing")]
+#[acton_test]
+async fn test_recorded_messages() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("recorded")?.with_recording();
+    let mut comedian = runtime.create_actor_with_config::<Comedian>(config).await;
+    comedian.act_on::<Ping>(|_, context| {
+        let envelope = context.reply_envelope();
+        AgentReply::from_async(async move {
+            envelope.send(Pong).await;
+        })
+    });
+    let probe = TestProbe::new(comedian.start().await);
+
+    probe.tell(Ping).await;
+    probe.await_idle().await;
+    assert_eq!(probe.handle().recorded_as::<Pong>().len(), 1, "the handler should have sent one Pong");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[cfg(feature = "testing")]
+#[acton_test]
+async fn test_recording_skips_failed_sends() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("recorded")?.with_recording();
+    let comedian = runtime.create_actor_with_config::<Comedian>(config).await.start().await;
+    comedian.stop().await?;
+
+    // A handle sends as its own agent, so these are recorded unless they fail
+    comedian.send_batch(vec![Box::new(Pong), Box::new(Pong)]).await;
+    let _ = comedian.try_send(Pong);
+    comedian.send(Pong).await;
+    assert!(comedian.recorded_as::<Pong>().is_empty(), "Pongs to a stopped agent were recorded as sent");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_broadcast_handle_lagging_listener() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("producer")?.with_output_capacity(4);
+    let producer = runtime.create_actor_with_config::<Counter>(config).await.start().await;
+    let output = producer.broadcast_handle();
+    let mut keeping_up = output.subscribe();
+    let mut lagging = output.subscribe();
+
+    for n in 1..=10 {
+        // Sending never waits, however far behind a listener is
+        output.send(std::sync::Arc::new(StatusReport::Complete(n)))?;
+        let message = keeping_up.recv().await?;
+        let report = (*message).as_any().downcast_ref::<StatusReport>().cloned();
+        assert!(matches!(report, Some(StatusReport::Complete(m)) if m == n));
+    }
+
+    let skipped = lagging.recv().await;
+    assert!(matches!(skipped, Err(tokio::sync::broadcast::error::RecvError::Lagged(6))), "got {skipped:?}");
+    let message = lagging.recv().await?;
+    let report = (*message).as_any().downcast_ref::<StatusReport>().cloned();
+    assert!(matches!(report, Some(StatusReport::Complete(7))), "the oldest kept message comes next");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_interceptor_drops_messages() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("guarded")?.with_interceptor(|envelope| {
+        match (*envelope.message).as_any().downcast_ref::<StatusReport>() {
+            Some(StatusReport::Complete(n)) if n % 2 == 1 => InterceptDecision::Drop,
+            _ => InterceptDecision::Continue,
+        }
+    });
+    let (handled, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    let mut guarded = runtime.create_actor_with_config::<Counter>(config).await;
+    guarded.act_on::<StatusReport>(move |_, context| {
+        let StatusReport::Complete(n) = context.message();
+        let _ = handled.send(*n);
+        AgentReply::immediate()
+    });
+    let guarded = guarded.start().await;
+
+    for n in 1..=4 {
+        guarded.send(StatusReport::Complete(n)).await;
+    }
+    guarded.stop().await?;
+
+    let mut seen = Vec::new();
+    while let Ok(n) = reports.try_recv() {
+        seen.push(n);
+    }
+    assert_eq!(seen, vec![2, 4], "odd reports should never reach the handler");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+/// The report number an interceptor saw last, left in the agent's context data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Inspected(usize);
+
+#[acton_test]
+async fn test_context_data() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let config = AgentConfig::new_with_name("inspected")?.with_context_interceptor(|envelope, context_data| {
+        if let Some(StatusReport::Complete(n)) = envelope.message_as::<StatusReport>() {
+            context_data.set(Inspected(*n));
+        }
+        InterceptDecision::Continue
+    });
+    let (handled, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    let mut inspected = runtime.create_actor_with_config::<Counter>(config).await;
+    inspected.act_on::<StatusReport>(move |agent, context| {
+        let StatusReport::Complete(n) = context.message();
+        let _ = handled.send((*n, agent.context_data().get::<Inspected>().copied()));
+        AgentReply::immediate()
+    });
+    let inspected = inspected.start().await;
+
+    for n in 1..=3 {
+        inspected.send(StatusReport::Complete(n)).await;
+    }
+    inspected.stop().await?;
+
+    let mut seen = Vec::new();
+    while let Ok(report) = reports.try_recv() {
+        seen.push(report);
+    }
+    assert_eq!(
+        seen,
+        vec![(1, Some(Inspected(1))), (2, Some(Inspected(2))), (3, Some(Inspected(3)))],
+        "each handler should read what the interceptor left for its message"
+    );
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_selective_receive() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (handled, mut seen) = tokio::sync::mpsc::unbounded_channel();
+    let (on_ping, on_pong) = (handled.clone(), handled);
+    let mut counter = runtime.new_agent::<Counter>().await;
+    counter
+        .act_on::<StatusReport>(move |agent, _| {
+            let pong = agent.receive::<Pong>(Duration::from_secs(1));
+            let on_pong = on_pong.clone();
+            AgentReply::from_async(async move {
+                if pong.await.is_ok() {
+                    let _ = on_pong.send("pong");
+                }
+            })
+        })
+        .act_on::<Ping>(move |_, _| {
+            let _ = on_ping.send("ping");
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    counter.send(StatusReport::Complete(1)).await;
+    counter.send(Ping).await;
+    counter.send(Pong).await;
+    counter.stop().await?;
+
+    // The Ping was set aside while the handler waited, and handled once it finished
+    assert_eq!(seen.recv().await, Some("pong"));
+    assert_eq!(seen.recv().await, Some("ping"));
+    Ok(())
+}
+
+#[derive(Default, Debug, Clone)]
+struct Thermostat {
+    threshold: usize,
+    readings: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Threshold(usize);
+
+#[acton_test]
+async fn test_reconfigure() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (alerts, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let mut thermostat = runtime.new_agent::<Thermostat>().await;
+    thermostat
+        .act_on::<StatusReport>(move |agent, context| {
+            let StatusReport::Complete(reading) = *context.message();
+            agent.model.readings += 1;
+            let _ = alerts.send((agent.model.readings, reading > agent.model.threshold));
+            AgentReply::immediate()
+        })
+        .on_reconfigure(|agent, Threshold(threshold): &Threshold| {
+            agent.model.threshold = *threshold;
+        });
+    let thermostat = thermostat.start().await;
+
+    thermostat.send(StatusReport::Complete(5)).await;
+    thermostat.reconfigure(Threshold(10)).await;
+    thermostat.send(StatusReport::Complete(5)).await;
+    thermostat.stop().await?;
+
+    let mut seen = Vec::new();
+    while let Ok(alert) = received.try_recv() {
+        seen.push(alert);
+    }
+    assert_eq!(
+        seen,
+        vec![(1, true), (2, false)],
+        "the reading after the reconfigure should use the new threshold, and the count should survive it"
+    );
+    Ok(())
+}
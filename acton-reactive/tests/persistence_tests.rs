@@ -0,0 +1,208 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use acton_reactive::prelude::*;
+use acton_test::prelude::*;
+
+use crate::setup::*;
+
+mod setup;
+
+#[derive(Debug, Default)]
+struct Account {
+    balance: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum AccountEvent {
+    Deposited(i64),
+    Withdrew(i64),
+}
+
+impl Persistent for Account {
+    type Event = AccountEvent;
+
+    fn apply_event(&mut self, event: &AccountEvent) {
+        match event {
+            AccountEvent::Deposited(amount) => self.balance += amount,
+            AccountEvent::Withdrew(amount) => self.balance -= amount,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Deposit(i64);
+
+#[derive(Clone, Debug)]
+struct Withdraw(i64);
+
+/// Starts an account agent on `journal`, reporting its balance once recovered.
+async fn start_account(
+    runtime: &mut AgentRuntime,
+    journal: Arc<dyn EventJournal<AccountEvent>>,
+    balances: tokio::sync::mpsc::UnboundedSender<i64>,
+) -> AgentHandle {
+    let mut account = runtime.new_agent::<Account>().await;
+    account
+        .with_journal("account-1", journal)
+        .act_on::<Deposit>(|agent, context| {
+            let amount = context.message().0;
+            agent.persist(AccountEvent::Deposited(amount)).expect("the journal should record the deposit");
+            AgentReply::immediate()
+        })
+        .act_on::<Withdraw>(|agent, context| {
+            let amount = context.message().0;
+            agent.persist(AccountEvent::Withdrew(amount)).expect("the journal should record the withdrawal");
+            AgentReply::immediate()
+        })
+        .after_start(move |agent| {
+            let _ = balances.send(agent.model.balance);
+            AgentReply::immediate()
+        });
+    account.start().await
+}
+
+#[acton_test]
+async fn test_persistent_state_is_recovered() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+    let journal: Arc<dyn EventJournal<AccountEvent>> = Arc::new(MemoryJournal::new());
+    let (balances, mut recovered) = tokio::sync::mpsc::unbounded_channel();
+
+    let account = start_account(&mut runtime, journal.clone(), balances.clone()).await;
+    assert_eq!(recovered.recv().await, Some(0), "a new account starts empty");
+    account.send(Deposit(100)).await;
+    account.send(Withdraw(30)).await;
+    account.send(Deposit(5)).await;
+    account.stop().await?;
+    assert_eq!(journal.replay("account-1")?.len(), 3);
+
+    let _account = start_account(&mut runtime, journal, balances).await;
+    let balance = tokio::time::timeout(Duration::from_secs(1), recovered.recv()).await?;
+    assert_eq!(balance, Some(75), "the balance should be rebuilt from the journal");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[acton_test]
+async fn test_file_journal() -> anyhow::Result<()> {
+    initialize_tracing();
+    let dir = std::env::temp_dir().join(format!("acton-journal-{}", std::process::id()));
+    let journal = FileJournal::new(&dir)?;
+
+    journal.append("account-1", &AccountEvent::Deposited(10))?;
+    journal.append("account-1", &AccountEvent::Withdrew(4))?;
+    let reopened = FileJournal::new(&dir)?;
+    let events: Vec<AccountEvent> = reopened.replay("account-1")?;
+    assert_eq!(events, vec![AccountEvent::Deposited(10), AccountEvent::Withdrew(4)]);
+    assert!(EventJournal::<AccountEvent>::replay(&reopened, "account-2")?.is_empty());
+    assert!(EventJournal::<AccountEvent>::append(&reopened, "../escape", &AccountEvent::Deposited(1)).is_err());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+impl Snapshot for Account {
+    fn to_snapshot(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.balance.to_be_bytes().to_vec())
+    }
+
+    fn from_snapshot(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(Account { balance: i64::from_be_bytes(bytes.try_into()?) })
+    }
+}
+
+/// Counts the events replayed from the journal it wraps.
+#[derive(Debug, Default)]
+struct CountingJournal {
+    inner: MemoryJournal<AccountEvent>,
+    replayed: std::sync::atomic::AtomicUsize,
+}
+
+impl EventJournal<AccountEvent> for CountingJournal {
+    fn append(&self, persistence_id: &str, event: &AccountEvent) -> anyhow::Result<()> {
+        self.inner.append(persistence_id, event)
+    }
+
+    fn replay(&self, persistence_id: &str) -> anyhow::Result<Vec<AccountEvent>> {
+        self.replay_after(persistence_id, 0)
+    }
+
+    fn replay_after(&self, persistence_id: &str, sequence: u64) -> anyhow::Result<Vec<AccountEvent>> {
+        let events = self.inner.replay_after(persistence_id, sequence)?;
+        self.replayed.fetch_add(events.len(), std::sync::atomic::Ordering::SeqCst);
+        Ok(events)
+    }
+}
+
+#[acton_test]
+async fn test_recovery_starts_from_snapshot() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+    let journal = Arc::new(CountingJournal::default());
+    let snapshots: Arc<dyn SnapshotStore> = Arc::new(MemorySnapshotStore::new());
+    let (balances, mut recovered) = tokio::sync::mpsc::unbounded_channel();
+
+    let start = |runtime: &mut AgentRuntime| {
+        let mut runtime = runtime.clone();
+        let (journal, snapshots, balances) = (journal.clone(), snapshots.clone(), balances.clone());
+        async move {
+            let config = AgentConfig::new(Ern::with_root("snapshotted")?, None, None)?.with_snapshot_interval(10);
+            let mut account = runtime.create_actor_with_config::<Account>(config).await;
+            account
+                .with_journal("account-2", journal)
+                .with_snapshots(snapshots)
+                .act_on::<Deposit>(|agent, context| {
+                    let amount = context.message().0;
+                    agent.persist(AccountEvent::Deposited(amount)).expect("the journal should record the deposit");
+                    AgentReply::immediate()
+                })
+                .after_start(move |agent| {
+                    let _ = balances.send(agent.model.balance);
+                    AgentReply::immediate()
+                });
+            anyhow::Ok(account.start().await)
+        }
+    };
+
+    let account = start(&mut runtime).await?;
+    assert_eq!(recovered.recv().await, Some(0));
+    for _ in 0..105 {
+        account.send(Deposit(1)).await;
+    }
+    account.stop().await?;
+    let (_, sequence) = snapshots.load_latest("account-2")?.expect("snapshots should have been saved");
+    assert_eq!(sequence, 100, "a snapshot is saved every 10 events");
+
+    journal.replayed.store(0, std::sync::atomic::Ordering::SeqCst);
+    let _account = start(&mut runtime).await?;
+    let balance = tokio::time::timeout(Duration::from_secs(1), recovered.recv()).await?;
+    assert_eq!(balance, Some(105), "the snapshot and the tail add up to every deposit");
+    assert_eq!(
+        journal.replayed.load(std::sync::atomic::Ordering::SeqCst),
+        5,
+        "only the events after the snapshot should be replayed"
+    );
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
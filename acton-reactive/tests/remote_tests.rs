@@ -0,0 +1,111 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+
+#![cfg(feature = "remote")]
+
+use std::time::Duration;
+
+use acton_reactive::prelude::*;
+use acton_test::prelude::*;
+
+use crate::setup::*;
+
+mod setup;
+
+#[acton_test]
+async fn test_remote_send() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut registry = MessageRegistry::new();
+    registry.register::<Ping>();
+
+    // Two runtimes standing in for two processes
+    let mut receiving: AgentRuntime = ActonApp::launch();
+    let sending: AgentRuntime = ActonApp::launch();
+    let receiving_system = RemoteSystem::listen("127.0.0.1:0", receiving.clone(), registry.clone()).await?;
+    let sending_system = RemoteSystem::listen("127.0.0.1:0", sending.clone(), registry).await?;
+
+    let (handled, mut pings) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = receiving.new_agent::<Counter>().await;
+    counter.act_on::<Ping>(move |_, _| {
+        let _ = handled.send(());
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    let remote = RemoteAgentHandle::connect(receiving_system.local_addr(), counter.id()).await?;
+    for _ in 0..3 {
+        remote.send(Ping).await?;
+    }
+    for _ in 0..3 {
+        tokio::time::timeout(Duration::from_secs(1), pings.recv()).await?;
+    }
+
+    // Once the receiving side goes away, sends report the lost connection
+    receiving_system.shutdown().await;
+    let closed = tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            if let Err(e) = remote.send(Ping).await {
+                return e;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+    assert!(matches!(closed, MessageError::TransportClosed));
+
+    sending_system.shutdown().await;
+    receiving.shutdown_all().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_remote_reply_refused() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut registry = MessageRegistry::new();
+    registry.register::<Ping>();
+
+    let mut runtime: AgentRuntime = ActonApp::launch();
+    let system = RemoteSystem::listen("127.0.0.1:0", runtime.clone(), registry).await?;
+
+    let (handled, mut handled_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let pinged = handled.clone();
+    counter
+        .act_on::<Ping>(move |_, context| {
+            let _ = pinged.send("ping");
+            let envelope = context.reply_envelope();
+            AgentReply::from_async(async move {
+                envelope.send(Pong).await;
+            })
+        })
+        .act_on::<Pong>(move |_, _| {
+            let _ = handled.send("pong");
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    let remote = RemoteAgentHandle::connect(system.local_addr(), counter.id()).await?;
+    remote.send(Ping).await?;
+    assert_eq!(tokio::time::timeout(Duration::from_secs(1), handled_rx.recv()).await?, Some("ping"));
+
+    // The reply has nowhere to go, and mustn't loop back to the agent that sent it
+    let looped = tokio::time::timeout(Duration::from_millis(100), handled_rx.recv()).await;
+    assert!(looped.is_err(), "the reply to a remote message reached the replying agent");
+
+    system.shutdown().await;
+    runtime.shutdown_all().await?;
+    Ok(())
+}
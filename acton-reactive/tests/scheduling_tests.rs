@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::sleep;
+
+use acton_reactive::prelude::*;
+use acton_test::prelude::*;
+
+use crate::setup::*;
+
+mod setup;
+
+#[acton_test]
+async fn test_schedule() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let pings = Arc::new(AtomicUsize::new(0));
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let ping_count = pings.clone();
+    counter.act_on::<Ping>(move |_, _| {
+        ping_count.fetch_add(1, Ordering::SeqCst);
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    let scheduled = counter.schedule(Duration::from_millis(50), Ping);
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(pings.load(Ordering::SeqCst), 0, "the message should not arrive before the delay");
+    assert!(!scheduled.is_fired());
+
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(pings.load(Ordering::SeqCst), 1, "the message should arrive after the delay");
+    assert!(scheduled.is_fired());
+    assert!(!scheduled.cancel(), "a delivered message can't be cancelled");
+
+    let cancelled = counter.schedule(Duration::from_millis(50), Ping);
+    assert!(cancelled.cancel());
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(pings.load(Ordering::SeqCst), 1, "a cancelled message should not arrive");
+
+    // A pending delivery must not hold up shutdown
+    counter.schedule(Duration::from_secs(3600), Ping);
+    counter.stop().await?;
+    Ok(())
+}
+
+#[acton_test]
+async fn test_schedule_interval() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let pings = Arc::new(AtomicUsize::new(0));
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let ping_count = pings.clone();
+    counter.act_on::<Ping>(move |_, _| {
+        ping_count.fetch_add(1, Ordering::SeqCst);
+        AgentReply::immediate()
+    });
+    let counter = counter.start().await;
+
+    let interval = counter.schedule_interval(Duration::from_millis(20), || Ping);
+    sleep(Duration::from_millis(110)).await;
+    let ticks = pings.load(Ordering::SeqCst);
+    assert!(ticks >= 3, "expected several ticks, got {ticks}");
+
+    drop(interval);
+    sleep(Duration::from_millis(30)).await;
+    let ticks = pings.load(Ordering::SeqCst);
+    sleep(Duration::from_millis(60)).await;
+    assert_eq!(pings.load(Ordering::SeqCst), ticks, "a dropped interval should stop ticking");
+
+    // An interval that is still running must not hold up shutdown
+    let _interval = counter.schedule_interval(Duration::from_millis(20), || Ping);
+    counter.stop().await?;
+    Ok(())
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test(start_paused = true)]
+async fn test_schedule_on_virtual_time() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let pings = Arc::new(AtomicUsize::new(0));
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let ping_count = pings.clone();
+    counter.act_on::<Ping>(move |_, _| {
+        ping_count.fetch_add(1, Ordering::SeqCst);
+        AgentReply::immediate()
+    });
+    let probe = TestProbe::new(counter.start().await);
+    let counter = probe.handle();
+
+    counter.schedule(Duration::from_secs(3600), Ping);
+    TestClock::advance(Duration::from_secs(3599)).await;
+    probe.await_idle().await;
+    assert_eq!(pings.load(Ordering::SeqCst), 0, "the message should not arrive before the hour is up");
+    TestClock::advance(Duration::from_secs(1)).await;
+    probe.await_idle().await;
+    assert_eq!(pings.load(Ordering::SeqCst), 1, "the message should arrive once the hour is up");
+
+    // A day's jump fires an hourly interval once; the missed ticks are skipped
+    let _interval = counter.schedule_interval(Duration::from_secs(3600), || Ping);
+    TestClock::advance(Duration::from_secs(24 * 3600)).await;
+    probe.await_idle().await;
+    assert_eq!(pings.load(Ordering::SeqCst), 2);
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
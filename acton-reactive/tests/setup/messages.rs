@@ -20,10 +20,15 @@ use acton_reactive::prelude::*;
 #[derive(Clone, Debug)]
 pub struct Pong;
 
-// #[acton_message]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, ActonMessage)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize, PartialEq))]
 pub struct Ping;
 
+#[cfg(feature = "serde")]
+impl SerializableMessage for Ping {
+    const TAG: &'static str = "acton.test.ping";
+}
+
 #[acton_message]
 pub enum FunnyJoke {
     ChickenCrossesRoad,
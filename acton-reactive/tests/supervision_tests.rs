@@ -0,0 +1,375 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use acton_reactive::prelude::*;
+
+use crate::setup::*;
+
+mod setup;
+
+// A panicking handler would trip the `acton_test` panic hook, so these use plain tokio tests.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_restart_after_panic() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let starts = Arc::new(AtomicUsize::new(0));
+    let final_count = Arc::new(AtomicUsize::new(usize::MAX));
+
+    // The parent decides on the restarts and counts them
+    let parent = runtime.new_agent_with_name::<Counter>("parent".to_string()).await.start().await;
+    let config = AgentConfig::new(Ern::with_root("restarting_counter")?, Some(parent.clone()), None)?.with_supervision(
+        SupervisionStrategy::Restart {
+            max_retries: 1,
+            within: Duration::from_secs(60),
+        },
+    );
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    let after_start_count = starts.clone();
+    let after_stop_count = final_count.clone();
+    counter
+        .act_on::<Ping>(|_, _| panic!("deliberate panic"))
+        .act_on::<Pong>(|agent, _| {
+            agent.model.count += 1;
+            AgentReply::immediate()
+        })
+        .after_start(move |_| {
+            after_start_count.fetch_add(1, Ordering::SeqCst);
+            AgentReply::immediate()
+        })
+        .after_stop(move |agent| {
+            after_stop_count.store(agent.model.count, Ordering::SeqCst);
+            AgentReply::immediate()
+        });
+    let counter = parent.supervise(counter).await?;
+
+    counter.send(Pong).await;
+    counter.send(Pong).await;
+    // First panic: restarted with a fresh model, keeping its handle and the pong sent after
+    counter.send(Ping).await;
+    counter.send(Pong).await;
+    // Second panic exceeds max_retries, so the parent stops the agent
+    counter.send(Ping).await;
+    tokio::time::timeout(Duration::from_secs(2), counter.tracker().wait()).await?;
+
+    assert_eq!(starts.load(Ordering::SeqCst), 2, "after_start should run again on restart");
+    assert_eq!(final_count.load(Ordering::SeqCst), 1, "the model should be reset on restart");
+    assert!(parent.children().is_empty(), "the parent should have stopped the child");
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_restart_needs_parent() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let starts = Arc::new(AtomicUsize::new(0));
+    let after_start_count = starts.clone();
+    let config = AgentConfig::new_with_name("orphan")?
+        .with_supervision(SupervisionStrategy::Restart { max_retries: 1, within: Duration::from_secs(60) });
+    let mut orphan = runtime.create_actor_with_config::<Counter>(config).await;
+    orphan.act_on::<Ping>(|_, _| panic!("deliberate panic")).after_start(move |_| {
+        after_start_count.fetch_add(1, Ordering::SeqCst);
+        AgentReply::immediate()
+    });
+    let orphan = orphan.start().await;
+
+    // Without a parent to restart it, the agent stops
+    orphan.send(Ping).await;
+    tokio::time::timeout(Duration::from_secs(2), orphan.tracker().wait()).await?;
+    assert_eq!(starts.load(Ordering::SeqCst), 1, "a root agent shouldn't restart");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_restart_clears_state() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (reported, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    let reports_handled = Arc::new(AtomicUsize::new(0));
+    let handled = reports_handled.clone();
+    let parent = runtime.new_agent_with_name::<Counter>("parent".to_string()).await.start().await;
+    let config = AgentConfig::new(Ern::with_root("forgetful_counter")?, Some(parent.clone()), None)?.with_supervision(
+        SupervisionStrategy::Restart {
+            max_retries: 1,
+            within: Duration::from_secs(60),
+        },
+    );
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    counter
+        .act_on::<StatusReport>(move |agent, context| {
+            handled.fetch_add(1, Ordering::SeqCst);
+            agent.stash(context.envelope());
+            agent.context_data_mut().set(String::from("from the failed incarnation"));
+            AgentReply::immediate()
+        })
+        .act_on::<Ping>(|_, _| panic!("deliberate panic"))
+        .act_on::<Pong>(move |agent, _| {
+            // Anything still stashed would be handled again
+            agent.unstash_all();
+            let _ = reported.send((agent.context_data().contains::<String>(), agent.model.count));
+            agent.model.count += 1;
+            AgentReply::immediate()
+        });
+    let counter = parent.supervise(counter).await?;
+
+    counter.send(StatusReport::Complete(1)).await;
+    counter.send(Ping).await;
+    counter.send(Pong).await;
+    let (has_context_data, count) = tokio::time::timeout(Duration::from_secs(2), reports.recv())
+        .await?
+        .expect("the restarted agent should handle the pong");
+    assert!(!has_context_data, "context data should be cleared on restart");
+    assert_eq!(count, 0, "the model should be reset on restart");
+
+    counter.stop().await?;
+    assert_eq!(reports_handled.load(Ordering::SeqCst), 1, "the stash should be cleared on restart");
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_panic_stops_cleanly() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (escalated, mut escalations) = tokio::sync::mpsc::unbounded_channel();
+    let mut parent = runtime.new_agent_with_name::<Counter>("parent".to_string()).await;
+    parent.on_child_failure(move |_, escalation| {
+        let _ = escalated.send(escalation.ern.clone());
+        EscalationAction::Ignore
+    });
+    let parent = parent.start().await;
+
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let after_stop_count = stopped.clone();
+    let config = AgentConfig::new(Ern::with_root("worker")?, Some(parent.clone()), None)?;
+    let mut worker = runtime.create_actor_with_config::<Counter>(config).await;
+    worker.act_on::<Ping>(|_, _| panic!("deliberate panic")).after_stop(move |_| {
+        after_stop_count.fetch_add(1, Ordering::SeqCst);
+        AgentReply::immediate()
+    });
+    let worker = parent.supervise(worker).await?;
+    let config = AgentConfig::new(Ern::with_root("helper")?, Some(worker.clone()), None)?;
+    let helper = worker.supervise(runtime.create_actor_with_config::<Counter>(config).await).await?;
+
+    worker.send(Ping).await;
+    let escalation = tokio::time::timeout(Duration::from_secs(2), escalations.recv())
+        .await?
+        .expect("the parent should hear of the failure");
+    assert_eq!(escalation, worker.id());
+    tokio::time::timeout(Duration::from_secs(2), worker.tracker().wait()).await?;
+
+    assert_eq!(stopped.load(Ordering::SeqCst), 1, "after_stop should run after the panic");
+    assert!(runtime.find(&worker.id()).is_none(), "the worker should be gone from the registry");
+    assert!(parent.children().is_empty(), "the worker should be gone from its parent");
+    // The worker's own children stop with it
+    tokio::time::timeout(Duration::from_secs(2), helper.tracker().wait()).await?;
+    assert_eq!(parent.health().state, HealthState::Healthy, "ignoring the failure keeps the parent running");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hook_panic_stops_cleanly() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let after_stop_count = stopped.clone();
+    let mut agent = runtime.new_agent_with_name::<Counter>("fragile".to_string()).await;
+    agent.after_start(|_| AgentReply::from_async(async { panic!("deliberate panic") })).after_stop(move |_| {
+        after_stop_count.fetch_add(1, Ordering::SeqCst);
+        AgentReply::immediate()
+    });
+    let agent = agent.start().await;
+
+    tokio::time::timeout(Duration::from_secs(2), agent.tracker().wait()).await?;
+    assert_eq!(stopped.load(Ordering::SeqCst), 1, "after_stop should run after the panic");
+    assert!(runtime.find(&agent.id()).is_none(), "the agent should be gone from the registry");
+    assert_eq!(runtime.agent_count(), 0, "the agent should be gone from the roots");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_panic_recovery() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let final_count = Arc::new(AtomicUsize::new(usize::MAX));
+
+    let config = AgentConfig::new_with_name("recovering_counter")?.with_panic_recovery(true);
+    let mut counter = runtime.create_actor_with_config::<Counter>(config).await;
+    let error_count = errors.clone();
+    let after_stop_count = final_count.clone();
+    counter
+        .act_on::<Ping>(|_, _| panic!("deliberate panic"))
+        .act_on::<Pong>(|agent, _| {
+            agent.model.count += 1;
+            AgentReply::immediate()
+        })
+        .on_error(move |_, error, _| {
+            assert!(error.to_string().contains("deliberate panic"));
+            error_count.fetch_add(1, Ordering::SeqCst);
+            AgentReply::immediate()
+        })
+        .after_stop(move |agent| {
+            after_stop_count.store(agent.model.count, Ordering::SeqCst);
+            AgentReply::immediate()
+        });
+    let counter = counter.start().await;
+
+    counter.send(Pong).await;
+    counter.send(Ping).await;
+    counter.send(Pong).await;
+    counter.stop().await?;
+
+    assert_eq!(errors.load(Ordering::SeqCst), 1, "on_error should see the panic");
+    assert_eq!(final_count.load(Ordering::SeqCst), 2, "messages after the panic should still be handled");
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_escalation_to_grandparent() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (escalated, mut escalations) = tokio::sync::mpsc::unbounded_channel();
+    let mut root = runtime.new_agent_with_name::<Counter>("root".to_string()).await;
+    root.on_child_failure(move |_, escalation| {
+        let _ = escalated.send(escalation.clone());
+        EscalationAction::Ignore
+    });
+    let mut supervisor = root.create_child("supervisor".to_string()).await?;
+    supervisor.on_child_failure(|_, _| EscalationAction::Escalate);
+    let root = root.start().await;
+    let supervisor = root.supervise(supervisor).await?;
+
+    // Children don't get their own config from `create_child`, so build the worker's here
+    let config = AgentConfig::new(Ern::with_root("worker")?, Some(supervisor.clone()), None)?
+        .with_supervision(SupervisionStrategy::Restart { max_retries: 1, within: Duration::from_secs(60) });
+    let mut worker = runtime.create_actor_with_config::<Counter>(config).await;
+    worker.act_on::<Ping>(|_, _| panic!("permanent failure"));
+    let worker = supervisor.supervise(worker).await?;
+
+    // The first panic restarts the worker; the second is one too many
+    worker.send(Ping).await;
+    worker.send(Ping).await;
+
+    let escalation = tokio::time::timeout(Duration::from_secs(2), escalations.recv())
+        .await?
+        .expect("the root should hear of the failure");
+    assert_eq!(escalation.ern, supervisor.id(), "the supervisor escalated after the worker failed");
+    assert!(escalation.error.to_string().contains("permanent failure"), "{}", escalation.error);
+    supervisor.tracker().wait().await;
+    worker.tracker().wait().await;
+    assert!(root.children().is_empty(), "the supervisor should have stopped");
+    assert_eq!(root.health().state, HealthState::Healthy, "ignoring the failure keeps the root running");
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failure_report() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (reported, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    let mut parent = runtime.new_agent_with_name::<Counter>("parent".to_string()).await;
+    parent.act_on::<FailureReport>(move |_, context| {
+        let _ = reported.send(context.message().clone());
+        AgentReply::immediate()
+    });
+    let parent = parent.start().await;
+
+    let config = AgentConfig::new(Ern::with_root("worker")?, Some(parent.clone()), None)?.with_panic_recovery(true);
+    let mut worker = runtime.create_actor_with_config::<Counter>(config).await;
+    worker
+        .act_on::<Ping>(|_, _| panic!("deliberate panic"))
+        .act_on_fallible::<Pong>(|_, _| Box::pin(async { Err(anyhow::anyhow!("deliberate error")) }));
+    let worker = parent.supervise(worker).await?;
+
+    worker.send(Ping).await;
+    let report = tokio::time::timeout(Duration::from_secs(2), reports.recv())
+        .await?
+        .expect("the parent should hear of the panic");
+    assert_eq!(report.child, worker.id());
+    assert_eq!(report.kind, FailureKind::Panic);
+    assert!(report.error.contains("deliberate panic"), "{}", report.error);
+
+    // The worker recovered, so its next failure is reported too
+    worker.send(Pong).await;
+    let report = tokio::time::timeout(Duration::from_secs(2), reports.recv())
+        .await?
+        .expect("the parent should hear of the error");
+    assert_eq!(report.kind, FailureKind::HandlerError);
+    assert!(report.error.contains("deliberate error"), "{}", report.error);
+
+    runtime.shutdown_all().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_detached_handler_panic() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let (reported, mut reports) = tokio::sync::mpsc::unbounded_channel();
+    let mut parent = runtime.new_agent_with_name::<Counter>("parent".to_string()).await;
+    parent.act_on::<FailureReport>(move |_, context| {
+        let _ = reported.send(context.message().clone());
+        AgentReply::immediate()
+    });
+    let parent = parent.start().await;
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let error_count = errors.clone();
+    let config = AgentConfig::new(Ern::with_root("worker")?, Some(parent.clone()), None)?.with_panic_recovery(true);
+    let mut worker = runtime.create_actor_with_config::<Counter>(config).await;
+    worker
+        .act_on_with_timeout::<Ping>(Duration::from_secs(5), OnTimeout::Detach, |_, _| {
+            Box::pin(async { panic!("deliberate panic") })
+        })
+        .on_error(move |_, _, _| {
+            error_count.fetch_add(1, Ordering::SeqCst);
+            AgentReply::immediate()
+        });
+    let worker = parent.supervise(worker).await?;
+
+    worker.send(Ping).await;
+    let report = tokio::time::timeout(Duration::from_secs(2), reports.recv())
+        .await?
+        .expect("the parent should hear of the panic");
+    assert_eq!(report.kind, FailureKind::Panic, "the detached panic shouldn't pass for a success");
+    assert!(report.error.contains("deliberate panic"), "{}", report.error);
+
+    worker.stop().await?;
+    assert_eq!(errors.load(Ordering::SeqCst), 1, "on_error should see the panic");
+    runtime.shutdown_all().await?;
+    Ok(())
+}
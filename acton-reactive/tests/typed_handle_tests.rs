@@ -0,0 +1,54 @@
+/*
+ * Copyright (c) 2024. Govcraft
+ *
+ * Licensed under either of
+ *   * Apache License, Version 2.0 (the "License");
+ *     you may not use this file except in compliance with the License.
+ *     You may obtain a copy of the License at http://www.apache.org/licenses/LICENSE-2.0
+ *   * MIT license: http://opensource.org/licenses/MIT
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the applicable License for the specific language governing permissions and
+ * limitations under that License.
+ */
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use acton_reactive::prelude::*;
+use acton_test::prelude::*;
+
+use crate::setup::*;
+
+mod setup;
+
+#[acton_test]
+async fn test_typed_handle() -> anyhow::Result<()> {
+    initialize_tracing();
+    let mut runtime: AgentRuntime = ActonApp::launch();
+
+    let pings = Arc::new(AtomicUsize::new(0));
+    let mut counter = runtime.new_agent::<Counter>().await;
+    let ping_count = pings.clone();
+    counter.act_on::<Ping>(move |_, _| {
+        ping_count.fetch_add(1, Ordering::SeqCst);
+        AgentReply::immediate()
+    });
+    let typed: TypedAgentHandle<Ping> = counter.start().await.typed();
+
+    let cloned = typed.clone();
+    tokio::spawn(async move { cloned.send(Ping).await }).await?;
+    typed.send(Ping).await;
+
+    // Lifecycle calls go through the untyped handle
+    typed.stop().await?;
+    assert_eq!(pings.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[test]
+fn test_typed_handle_rejects_other_messages() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/typed_handle_wrong_message.rs");
+}